@@ -1,4 +1,3 @@
-use crate::consts::TREESITTER_CMAKE_LANGUAGE;
 /// Get the tree of ast
 use crate::utils::treehelper::point_to_position;
 use async_lsp::lsp_types;
@@ -15,7 +14,11 @@ const COMMAND_KEYWORDS: [&str; 5] = [
     "target_link_libraries",
     "target_include_directories",
 ];
-pub async fn getast(client: &mut ClientSocket, context: &str) -> Option<DocumentSymbolResponse> {
+pub async fn getast(
+    client: &mut ClientSocket,
+    context: &str,
+    root: tree_sitter::Node<'_>,
+) -> Option<DocumentSymbolResponse> {
     let line = context.lines().count();
     if line > 10000 {
         client
@@ -25,12 +28,7 @@ pub async fn getast(client: &mut ClientSocket, context: &str) -> Option<Document
             })
             .unwrap();
     }
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(context, None);
-    let tree = thetree.unwrap();
-    getsubast(tree.root_node(), &context.lines().collect(), line > 10000)
-        .map(DocumentSymbolResponse::Nested)
+    getsubast(root, &context.lines().collect(), line > 10000).map(DocumentSymbolResponse::Nested)
 }
 #[allow(deprecated)]
 fn getsubast(
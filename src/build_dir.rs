@@ -0,0 +1,138 @@
+/// discover the build directory of the project, in order: the build_dir in the settings, the
+/// binaryDir of the configure preset, the target of the compile_commands.json symlink, and the
+/// common layouts like build*, out/build/* and cmake-build-*
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::languageserver::config::Config;
+use crate::presets;
+
+const CACHE_FILE: &str = "CMakeCache.txt";
+
+fn is_configured(dir: &Path) -> bool {
+    dir.join(CACHE_FILE).is_file()
+}
+
+fn cache_modified(dir: &Path) -> SystemTime {
+    std::fs::metadata(dir.join(CACHE_FILE))
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn sub_dirs(dir: &Path, filter: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter(|entry| entry.file_name().to_str().is_some_and(&filter))
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// the directory which the compile_commands.json symlink in the root points to
+fn compile_commands_dir(root: &Path) -> Option<PathBuf> {
+    let link = root.join("compile_commands.json");
+    if !std::fs::symlink_metadata(&link).ok()?.is_symlink() {
+        return None;
+    }
+    let target = std::fs::read_link(&link).ok()?;
+    Some(root.join(target).parent()?.to_path_buf())
+}
+
+/// the binary directory of the preset in the settings, or the first visible preset
+fn preset_dir(root: &Path, config: &Config) -> Option<PathBuf> {
+    let presets = presets::load_configure_presets(root);
+    let preset = match config.preset.as_deref() {
+        Some(name) => presets.into_iter().find(|preset| preset.name == name)?,
+        None => presets.into_iter().find(|preset| !preset.hidden)?,
+    };
+    preset.binary_dir
+}
+
+/// the configured build directories, the recently configured one is the first
+pub fn get_candidates(root: &Path, config: &Config) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if config.build_dir.is_some() {
+        dirs.push(config.get_build_dir(root));
+    }
+    dirs.extend(preset_dir(root, config));
+    dirs.extend(compile_commands_dir(root));
+    dirs.append(&mut sub_dirs(root, |name| {
+        name.starts_with("build") || name.starts_with("cmake-build-")
+    }));
+    dirs.append(&mut sub_dirs(&root.join("out/build"), |_| true));
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        if is_configured(&dir) && !candidates.contains(&dir) {
+            candidates.push(dir);
+        }
+    }
+    candidates.sort_by_key(|dir| std::cmp::Reverse(cache_modified(dir)));
+    candidates
+}
+
+/// the build directory of the project, the one in the settings is always used
+pub fn discover_build_dir(root: &Path, config: &Config) -> PathBuf {
+    if config.build_dir.is_some() {
+        return config.get_build_dir(root);
+    }
+    if let Some(dir) =
+        preset_dir(root, config).filter(|dir| config.preset.is_some() || is_configured(dir))
+    {
+        return dir;
+    }
+    if let Some(dir) = compile_commands_dir(root).filter(|dir| is_configured(dir)) {
+        return dir;
+    }
+    get_candidates(root, config)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| config.get_build_dir(root))
+}
+
+#[test]
+fn tst_discover_build_dir() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().to_path_buf();
+    let configure = |dir: &str| {
+        let dir = root.join(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(CACHE_FILE), "").unwrap();
+        dir
+    };
+    let config = Config::default();
+    assert_eq!(discover_build_dir(&root, &config), root.join("build"));
+
+    let debug = configure("cmake-build-debug");
+    assert_eq!(discover_build_dir(&root, &config), debug);
+    let release = configure("out/build/release");
+    std::fs::write(release.join(CACHE_FILE), "# newer").unwrap();
+    assert!(get_candidates(&root, &config).contains(&release));
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(
+            "cmake-build-debug/compile_commands.json",
+            root.join("compile_commands.json"),
+        )
+        .unwrap();
+        assert_eq!(discover_build_dir(&root, &config), debug);
+    }
+
+    std::fs::write(
+        root.join("CMakePresets.json"),
+        r#"{ "version": 3, "configurePresets": [
+            { "name": "ninja", "binaryDir": "${sourceDir}/out/build/release" }
+        ] }"#,
+    )
+    .unwrap();
+    assert_eq!(discover_build_dir(&root, &config), release);
+
+    let config = Config {
+        build_dir: Some("custom".to_string()),
+        ..Config::default()
+    };
+    assert_eq!(discover_build_dir(&root, &config), root.join("custom"));
+}
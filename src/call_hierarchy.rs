@@ -0,0 +1,356 @@
+/// provide call hierarchy, the items are the functions and macros of the project, and the
+/// calls are the commands which invoke them
+use async_lsp::lsp_types;
+use lsp_types::{
+    CallHierarchyIncomingCall, CallHierarchyItem, CallHierarchyOutgoingCall, Position, Range,
+    SymbolKind, Url,
+};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tree_sitter::Node;
+
+use crate::document::{get_or_read, Document};
+use crate::rename::is_command_name_argument;
+use crate::utils::get_node_content;
+use crate::utils::treehelper::{point_to_position, position_to_point};
+
+/// a command call, the caller is the index of the function or macro which contains it
+#[derive(Debug, Clone)]
+struct Call {
+    caller: Option<usize>,
+    name: String,
+    range: Range,
+}
+
+/// the definitions and the calls of one document, they are kept without the tree so they can
+/// be cached until the text is changed
+#[derive(Debug, Clone)]
+pub struct FileIndex {
+    file_item: CallHierarchyItem,
+    definitions: Vec<CallHierarchyItem>,
+    calls: Vec<Call>,
+}
+
+type IndexKV = HashMap<Url, (u64, Arc<FileIndex>)>;
+
+/// NOTE: the index of every document and the hash of its text
+static CALL_INDEX: Lazy<Mutex<IndexKV>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn node_range(node: Node) -> Range {
+    Range {
+        start: point_to_position(node.start_position()),
+        end: point_to_position(node.end_position()),
+    }
+}
+
+/// load the documents of the files, the opened buffers are used first
+pub fn load_documents(files: &[PathBuf], buffers: &HashMap<Url, Document>) -> Vec<(Url, Document)> {
+    let mut documents: Vec<(Url, Document)> = Vec::new();
+    for path in files {
+        let Ok(uri) = Url::from_file_path(path) else {
+            continue;
+        };
+        if documents.iter().any(|(loaded, _)| *loaded == uri) {
+            continue;
+        }
+        if let Some(document) = get_or_read(buffers, path) {
+            documents.push((uri, document));
+        }
+    }
+    documents
+}
+
+/// get the index of the files, the opened buffers are used first, the index is built again
+/// only when the text is changed
+pub fn load_index(files: &[PathBuf], buffers: &HashMap<Url, Document>) -> Vec<Arc<FileIndex>> {
+    let mut indexes: Vec<Arc<FileIndex>> = Vec::new();
+    for path in files {
+        let Ok(uri) = Url::from_file_path(path) else {
+            continue;
+        };
+        if indexes.iter().any(|index| index.file_item.uri == uri) {
+            continue;
+        }
+        if let Some(document) = get_or_read(buffers, path) {
+            indexes.push(get_index(&uri, &document));
+        }
+    }
+    indexes
+}
+
+pub fn get_index(uri: &Url, document: &Document) -> Arc<FileIndex> {
+    let hash = document.text_hash();
+    let mut cache = CALL_INDEX.lock().unwrap();
+    if let Some((cached, index)) = cache.get(uri) {
+        if *cached == hash {
+            return index.clone();
+        }
+    }
+    let index = Arc::new(index_document(uri, document));
+    cache.insert(uri.clone(), (hash, index.clone()));
+    index
+}
+
+pub fn index_document(uri: &Url, document: &Document) -> FileIndex {
+    let newsource: Vec<&str> = document.text.lines().collect();
+    let mut index = FileIndex {
+        file_item: new_file_item(document.root_node(), uri),
+        definitions: Vec::new(),
+        calls: Vec::new(),
+    };
+    collect_calls(document.root_node(), &newsource, uri, None, &mut index);
+    index
+}
+
+fn get_definition_name<'a>(definition: Node<'a>, source: &Vec<&str>) -> Option<(String, Node<'a>)> {
+    let name = definition.child(0)?.child(2)?.named_child(0)?;
+    Some((get_node_content(source, &name), name))
+}
+
+fn new_item(definition: Node, source: &Vec<&str>, uri: &Url) -> Option<CallHierarchyItem> {
+    let (name, name_node) = get_definition_name(definition, source)?;
+    let detail = if definition.kind() == "macro_def" {
+        "macro"
+    } else {
+        "function"
+    };
+    Some(CallHierarchyItem {
+        name,
+        kind: SymbolKind::FUNCTION,
+        tags: None,
+        detail: Some(detail.to_string()),
+        uri: uri.clone(),
+        range: node_range(definition),
+        selection_range: node_range(name_node),
+        data: None,
+    })
+}
+
+/// the item of the top level of the file, used when the call is not inside a function
+fn new_file_item(root: Node, uri: &Url) -> CallHierarchyItem {
+    let name = uri
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default()
+        .to_string();
+    CallHierarchyItem {
+        name,
+        kind: SymbolKind::FILE,
+        tags: None,
+        detail: Some(uri.path().to_string()),
+        uri: uri.clone(),
+        range: node_range(root),
+        selection_range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+        data: None,
+    }
+}
+
+/// collect the definitions and the calls, the definitions without name are not callers
+fn collect_calls(
+    input: Node,
+    source: &Vec<&str>,
+    uri: &Url,
+    caller: Option<usize>,
+    index: &mut FileIndex,
+) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        match child.kind() {
+            "function_def" | "macro_def" => {
+                let caller = match new_item(child, source, uri) {
+                    Some(item) => {
+                        index.definitions.push(item);
+                        Some(index.definitions.len() - 1)
+                    }
+                    None => caller,
+                };
+                collect_calls(child, source, uri, caller, index);
+            }
+            "normal_command" => {
+                if let Some(identifier) = child.child(0) {
+                    index.calls.push(Call {
+                        caller,
+                        name: get_node_content(source, &identifier).to_lowercase(),
+                        range: node_range(identifier),
+                    });
+                }
+            }
+            "line_comment" | "bracket_comment" => {}
+            _ => collect_calls(child, source, uri, caller, index),
+        }
+    }
+}
+
+/// find the definitions of the command in all the documents
+fn find_items(name: &str, indexes: &[Arc<FileIndex>]) -> Vec<CallHierarchyItem> {
+    indexes
+        .iter()
+        .flat_map(|index| index.definitions.iter())
+        .filter(|item| item.name.eq_ignore_ascii_case(name))
+        .cloned()
+        .collect()
+}
+
+/// count the calls of every command in all the documents, the names are lowercase
+pub fn count_calls(indexes: &[Arc<FileIndex>]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for call in indexes.iter().flat_map(|index| index.calls.iter()) {
+        *counts.entry(call.name.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+pub fn prepare(
+    location: Position,
+    document: &Document,
+    indexes: &[Arc<FileIndex>],
+) -> Option<Vec<CallHierarchyItem>> {
+    let newsource: Vec<&str> = document.text.lines().collect();
+    let point = position_to_point(location);
+    let mut node = document
+        .root_node()
+        .descendant_for_point_range(point, point)?;
+    if node.kind() != "identifier" && !is_command_name_argument(node) && point.column > 0 {
+        // NOTE: the cursor may be just at the end of the word
+        let mut point = point;
+        point.column -= 1;
+        node = document
+            .root_node()
+            .descendant_for_point_range(point, point)?;
+    }
+    if node.kind() != "identifier" && !is_command_name_argument(node) {
+        return None;
+    }
+    let name = get_node_content(&newsource, &node);
+    let items = find_items(&name, indexes);
+    if items.is_empty() {
+        None
+    } else {
+        Some(items)
+    }
+}
+
+pub fn incoming_calls(
+    item: &CallHierarchyItem,
+    indexes: &[Arc<FileIndex>],
+) -> Vec<CallHierarchyIncomingCall> {
+    let name = item.name.to_lowercase();
+    let mut calls: Vec<CallHierarchyIncomingCall> = Vec::new();
+    for index in indexes {
+        for call in index.calls.iter().filter(|call| call.name == name) {
+            let from = match call.caller {
+                Some(caller) => &index.definitions[caller],
+                None => &index.file_item,
+            };
+            match calls
+                .iter_mut()
+                .find(|incoming| incoming.from.uri == from.uri && incoming.from.range == from.range)
+            {
+                Some(incoming) => incoming.from_ranges.push(call.range),
+                None => calls.push(CallHierarchyIncomingCall {
+                    from: from.clone(),
+                    from_ranges: vec![call.range],
+                }),
+            }
+        }
+    }
+    calls
+}
+
+pub fn outgoing_calls(
+    item: &CallHierarchyItem,
+    indexes: &[Arc<FileIndex>],
+) -> Vec<CallHierarchyOutgoingCall> {
+    let Some(index) = indexes.iter().find(|index| index.file_item.uri == item.uri) else {
+        return Vec::new();
+    };
+    let Some(caller) = index
+        .definitions
+        .iter()
+        .position(|definition| definition.range == item.range)
+    else {
+        return Vec::new();
+    };
+
+    let mut callees: HashMap<String, Vec<CallHierarchyItem>> = HashMap::new();
+    let mut calls: Vec<CallHierarchyOutgoingCall> = Vec::new();
+    for call in index
+        .calls
+        .iter()
+        .filter(|call| call.caller == Some(caller))
+    {
+        let items = callees
+            .entry(call.name.clone())
+            .or_insert_with(|| find_items(&call.name, indexes));
+        for to in items.iter() {
+            match calls
+                .iter_mut()
+                .find(|outgoing| outgoing.to.uri == to.uri && outgoing.to.range == to.range)
+            {
+                Some(outgoing) => outgoing.from_ranges.push(call.range),
+                None => calls.push(CallHierarchyOutgoingCall {
+                    to: to.clone(),
+                    from_ranges: vec![call.range],
+                }),
+            }
+        }
+    }
+    calls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"function(_company_setup_target target)
+  message(${target})
+endfunction()
+macro(company_add_library name)
+  add_library(${name})
+  _company_setup_target(${name})
+  _COMPANY_SETUP_TARGET(${name})
+endmacro()
+company_add_library(foo)
+"#;
+
+    fn get_documents() -> (Document, Vec<Arc<FileIndex>>) {
+        let document = Document::new(SOURCE.to_string(), 0);
+        let uri = Url::parse("file:///tmp/CMakeLists.txt").unwrap();
+        let index = Arc::new(index_document(&uri, &document));
+        (document, vec![index])
+    }
+
+    #[test]
+    fn tst_prepare() {
+        let (document, indexes) = get_documents();
+        let items = prepare(Position::new(5, 4), &document, &indexes).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "_company_setup_target");
+        assert_eq!(items[0].range.start.line, 0);
+        assert_eq!(items[0].selection_range.start, Position::new(0, 9));
+        // builtin command
+        assert!(prepare(Position::new(4, 4), &document, &indexes).is_none());
+    }
+
+    #[test]
+    fn tst_calls() {
+        let (document, indexes) = get_documents();
+        let items = prepare(Position::new(3, 8), &document, &indexes).unwrap();
+        let incoming = incoming_calls(&items[0], &indexes);
+        assert_eq!(incoming.len(), 1);
+        assert_eq!(incoming[0].from.kind, SymbolKind::FILE);
+        assert_eq!(incoming[0].from_ranges[0].start, Position::new(8, 0));
+
+        let outgoing = outgoing_calls(&items[0], &indexes);
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].to.name, "_company_setup_target");
+        assert_eq!(outgoing[0].from_ranges.len(), 2);
+
+        let incoming = incoming_calls(&outgoing[0].to, &indexes);
+        assert_eq!(incoming.len(), 1);
+        assert_eq!(incoming[0].from.name, "company_add_library");
+        assert_eq!(incoming[0].from_ranges.len(), 2);
+    }
+}
@@ -0,0 +1,344 @@
+/// provide quick fixes for the diagnostics of grammar.rs, the fixes are computed from the
+/// data attached to the diagnostic
+use async_lsp::lsp_types;
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, Range, TextEdit, Url,
+    WorkspaceEdit,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use tree_sitter::Node;
+
+use crate::document::{point_to_utf16_position, position_to_byte, Document};
+use crate::grammar::DiagnosticData;
+use crate::indexing::get_indexed;
+
+/// the max number of the suggestions for one diagnostic
+const MAX_SUGGESTIONS: usize = 3;
+
+pub fn get_code_actions(
+    uri: &Url,
+    document: &Document,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeActionOrCommand> {
+    let mut actions = Vec::new();
+    for diagnostic in diagnostics {
+        let Some(data) = diagnostic
+            .data
+            .clone()
+            .and_then(|data| serde_json::from_value::<DiagnosticData>(data).ok())
+        else {
+            continue;
+        };
+        match data {
+            DiagnosticData::PackageNotFound { package } => {
+                // NOTE: no names are suggested while the packages are being indexed
+                let packages = get_indexed(&crate::utils::CMAKE_PACKAGES_WITHKEY)
+                    .into_iter()
+                    .flat_map(|packages| packages.keys())
+                    .map(|name| name.as_str());
+                for name in closest_names(&package, packages) {
+                    actions.push(new_action(
+                        format!("Change to \"{name}\""),
+                        uri,
+                        diagnostic,
+                        vec![TextEdit {
+                            range: diagnostic.range,
+                            new_text: name,
+                        }],
+                    ));
+                }
+            }
+            DiagnosticData::IncludeNotFound {
+                argument,
+                include_path,
+            } => {
+                let include_path = Path::new(&include_path);
+                let (Some(dir), Some(file_name)) = (
+                    include_path.parent(),
+                    include_path.file_name().and_then(|name| name.to_str()),
+                ) else {
+                    continue;
+                };
+                let Ok(entries) = std::fs::read_dir(dir) else {
+                    continue;
+                };
+                let files: Vec<String> = entries
+                    .flatten()
+                    .filter(|entry| entry.path().is_file())
+                    .filter_map(|entry| entry.file_name().to_str().map(|name| name.to_string()))
+                    .collect();
+                let quoted = is_quoted(&document.text, diagnostic.range);
+                for name in closest_names(file_name, files.iter().map(|name| name.as_str())) {
+                    let new_argument = match argument.rsplit_once('/') {
+                        Some((prefix, _)) => format!("{prefix}/{name}"),
+                        None => name,
+                    };
+                    let new_text = if quoted {
+                        format!("\"{new_argument}\"")
+                    } else {
+                        new_argument.clone()
+                    };
+                    actions.push(new_action(
+                        format!("Change to \"{new_argument}\""),
+                        uri,
+                        diagnostic,
+                        vec![TextEdit {
+                            range: diagnostic.range,
+                            new_text,
+                        }],
+                    ));
+                }
+            }
+            DiagnosticData::IncludeDirectory {
+                argument,
+                include_path,
+            } => {
+                if !Path::new(&include_path).join("CMakeLists.txt").is_file() {
+                    continue;
+                }
+                let Some(identifier) =
+                    get_command_node(document.root_node(), &document.text, diagnostic.range)
+                        .and_then(|command| command.child(0))
+                else {
+                    continue;
+                };
+                actions.push(new_action(
+                    format!("Use add_subdirectory({argument})"),
+                    uri,
+                    diagnostic,
+                    vec![TextEdit {
+                        range: node_range(&document.text, identifier),
+                        new_text: "add_subdirectory".to_string(),
+                    }],
+                ));
+            }
+            DiagnosticData::EmptyArgument => {
+                let Some(command) =
+                    get_command_node(document.root_node(), &document.text, diagnostic.range)
+                else {
+                    continue;
+                };
+                actions.push(new_action(
+                    "Remove the empty include".to_string(),
+                    uri,
+                    diagnostic,
+                    vec![TextEdit {
+                        range: node_range(&document.text, command),
+                        new_text: String::new(),
+                    }],
+                ));
+            }
+        }
+    }
+    actions
+}
+
+fn new_action(
+    title: String,
+    uri: &Url,
+    diagnostic: &Diagnostic,
+    edits: Vec<TextEdit>,
+) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+/// NOTE: the diagnostics and the edits are in utf-16, the nodes are in bytes
+fn node_range(source: &str, node: Node) -> Range {
+    Range {
+        start: point_to_utf16_position(source, node.start_position()),
+        end: point_to_utf16_position(source, node.end_position()),
+    }
+}
+
+/// find the normal_command which contains the range
+fn get_command_node<'a>(root: Node<'a>, source: &str, range: Range) -> Option<Node<'a>> {
+    let (_, start) = position_to_byte(source, range.start);
+    let (_, end) = position_to_byte(source, range.end);
+    let mut node = root.descendant_for_point_range(start, end)?;
+    while node.kind() != "normal_command" {
+        node = node.parent()?;
+    }
+    Some(node)
+}
+
+fn is_quoted(source: &str, range: Range) -> bool {
+    let (start, _) = position_to_byte(source, range.start);
+    source[start..].starts_with('"')
+}
+
+/// get the names which are close to the target, the closest is the first
+fn closest_names<'a>(target: &str, names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let target_lower = target.to_lowercase();
+    let max_distance = (target.chars().count() / 3).max(2);
+    let mut scored: Vec<(usize, &str)> = names
+        .filter(|name| *name != target)
+        .filter_map(|name| {
+            let distance = edit_distance(&target_lower, &name.to_lowercase());
+            (distance <= max_distance).then_some((distance, name))
+        })
+        .collect();
+    scored.sort();
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// the levenshtein distance of two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::Position;
+
+    fn get_edits(action: &CodeActionOrCommand) -> Vec<TextEdit> {
+        let CodeActionOrCommand::CodeAction(action) = action else {
+            unreachable!();
+        };
+        action
+            .edit
+            .as_ref()
+            .and_then(|edit| edit.changes.as_ref())
+            .and_then(|changes| changes.values().next().cloned())
+            .unwrap()
+    }
+
+    #[test]
+    fn tst_closest_names() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        let names = ["Qt5", "Qt6", "Boost", "Protobuf", "protobuf-c"];
+        assert_eq!(
+            closest_names("Protobf", names.into_iter()),
+            vec!["Protobuf".to_string()]
+        );
+        assert_eq!(
+            closest_names("Qt7", names.into_iter()),
+            vec!["Qt5".to_string(), "Qt6".to_string()]
+        );
+        assert!(closest_names("Vulkan", names.into_iter()).is_empty());
+    }
+
+    #[test]
+    fn tst_include_fixes() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_path_buf();
+        let subdir = dir.join("sub");
+        std::fs::create_dir_all(&subdir).unwrap();
+        std::fs::write(dir.join("Utils.cmake"), "").unwrap();
+        std::fs::write(subdir.join("CMakeLists.txt"), "").unwrap();
+
+        let source = "include(\"Utisl.cmake\")\ninclude(sub)\ninclude(\"\")\n";
+        let document = Document::new(source.to_string(), 0);
+        let uri = Url::from_file_path(dir.join("CMakeLists.txt")).unwrap();
+        let new_diagnostic = |line, start, end, data: DiagnosticData| Diagnostic {
+            range: Range {
+                start: Position::new(line, start),
+                end: Position::new(line, end),
+            },
+            data: Some(serde_json::to_value(data).unwrap()),
+            ..Default::default()
+        };
+        let diagnostics = vec![
+            new_diagnostic(
+                0,
+                8,
+                21,
+                DiagnosticData::IncludeNotFound {
+                    argument: "Utisl.cmake".to_string(),
+                    include_path: dir.join("Utisl.cmake").to_str().unwrap().to_string(),
+                },
+            ),
+            new_diagnostic(
+                1,
+                8,
+                11,
+                DiagnosticData::IncludeDirectory {
+                    argument: "sub".to_string(),
+                    include_path: subdir.to_str().unwrap().to_string(),
+                },
+            ),
+            new_diagnostic(2, 8, 10, DiagnosticData::EmptyArgument),
+        ];
+        let actions = get_code_actions(&uri, &document, &diagnostics);
+        assert_eq!(actions.len(), 3);
+        assert_eq!(get_edits(&actions[0])[0].new_text, "\"Utils.cmake\"");
+        let edit = &get_edits(&actions[1])[0];
+        assert_eq!(edit.new_text, "add_subdirectory");
+        assert_eq!(edit.range.end, Position::new(1, 7));
+        let edit = &get_edits(&actions[2])[0];
+        assert_eq!(edit.range.start, Position::new(2, 0));
+        assert_eq!(edit.range.end, Position::new(2, 11));
+    }
+
+    #[test]
+    fn tst_include_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_path_buf();
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub").join("CMakeLists.txt"), "").unwrap();
+        std::fs::create_dir_all(dir.join("données")).unwrap();
+        std::fs::write(dir.join("Utils.cmake"), "").unwrap();
+
+        let source = "include(sub)
+set(é 1) include(\"Utisl.cmake\")
+include(données)
+set(é 1) include(sub)
+";
+        let path = dir.join("CMakeLists.txt");
+        let document = Document::new(source.to_string(), 0);
+        let uri = Url::from_file_path(&path).unwrap();
+        let diagnostics = crate::diagnostic::get_diagnostics(&path, &document, false);
+        let actions = get_code_actions(&uri, &document, &diagnostics);
+        let titles: Vec<String> = actions
+            .iter()
+            .map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action.title.clone(),
+                CodeActionOrCommand::Command(command) => command.title.clone(),
+            })
+            .collect();
+        // NOTE: the directory without CMakeLists.txt is reported but can not be fixed
+        assert_eq!(
+            titles,
+            vec![
+                "Use add_subdirectory(sub)",
+                "Change to \"Utils.cmake\"",
+                "Use add_subdirectory(sub)"
+            ]
+        );
+        assert!(diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.range.start.line == 2
+                && diagnostic.message.ends_with("is a directory")));
+        assert_eq!(get_edits(&actions[1])[0].new_text, "\"Utils.cmake\"");
+        // the command after the non-ascii text is found, the edit is in utf-16
+        let edit = &get_edits(&actions[2])[0];
+        assert_eq!(edit.range.start, Position::new(3, 9));
+        assert_eq!(edit.range.end, Position::new(3, 16));
+    }
+}
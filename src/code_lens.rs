@@ -0,0 +1,258 @@
+/// provide codeLens, show the calls of the functions and macros, the targets which link
+/// against the library, and the commands to build the targets and run the tests
+use async_lsp::lsp_types;
+use lsp_types::{CodeLens, Command, Range, Url};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tree_sitter::Node;
+
+use crate::call_hierarchy::{count_calls, get_index};
+use crate::command::{BUILD_TARGET, RUN_TEST};
+use crate::document::Document;
+use crate::signature_help::get_arguments;
+use crate::utils::get_node_content;
+use crate::utils::treehelper::point_to_position;
+
+/// the libraries and the targets which link against them in one document
+type Links = Vec<(String, String)>;
+
+type LinksKV = HashMap<Url, (u64, Arc<Links>)>;
+
+/// NOTE: the links of every document and the hash of its text
+static LINK_INDEX: Lazy<Mutex<LinksKV>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// the uri of the document is passed to the commands, the workspace folder is found by it
+pub fn get_code_lenses(
+    uri: &Url,
+    document: &Document,
+    documents: &[(Url, Document)],
+) -> Vec<CodeLens> {
+    let newsource: Vec<&str> = document.text.lines().collect();
+    let indexes: Vec<_> = documents
+        .iter()
+        .map(|(uri, document)| get_index(uri, document))
+        .collect();
+    let calls = count_calls(&indexes);
+    let mut links: HashMap<String, HashSet<String>> = HashMap::new();
+    for (uri, document) in documents {
+        for (library, target) in get_links(uri, document).iter() {
+            links
+                .entry(library.clone())
+                .or_default()
+                .insert(target.clone());
+        }
+    }
+    let mut lenses = Vec::new();
+    collect_lenses(
+        document.root_node(),
+        &newsource,
+        &calls,
+        &links,
+        &mut lenses,
+    );
+    for lens in lenses.iter_mut() {
+        if let Some(arguments) = lens
+            .command
+            .as_mut()
+            .and_then(|command| command.arguments.as_mut())
+        {
+            arguments.push(uri.to_string().into());
+        }
+    }
+    lenses
+}
+
+fn new_lens(node: Node, title: String, command: &str, arguments: Vec<String>) -> CodeLens {
+    CodeLens {
+        range: Range {
+            start: point_to_position(node.start_position()),
+            end: point_to_position(node.start_position()),
+        },
+        command: Some(Command {
+            title,
+            command: command.to_string(),
+            arguments: if arguments.is_empty() {
+                None
+            } else {
+                Some(arguments.into_iter().map(Into::into).collect())
+            },
+        }),
+        data: None,
+    }
+}
+
+fn plural(count: usize, word: &str) -> String {
+    if count == 1 {
+        format!("{count} {word}")
+    } else {
+        format!("{count} {word}s")
+    }
+}
+
+/// the links of the document, they are collected again only when the text is changed
+fn get_links(uri: &Url, document: &Document) -> Arc<Links> {
+    let hash = document.text_hash();
+    let mut cache = LINK_INDEX.lock().unwrap();
+    if let Some((cached, links)) = cache.get(uri) {
+        if *cached == hash {
+            return links.clone();
+        }
+    }
+    let newsource: Vec<&str> = document.text.lines().collect();
+    let mut links = Vec::new();
+    collect_links(document.root_node(), &newsource, &mut links);
+    let links = Arc::new(links);
+    cache.insert(uri.clone(), (hash, links.clone()));
+    links
+}
+
+fn collect_links(input: Node, source: &Vec<&str>, links: &mut Links) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        if child.kind() != "normal_command" {
+            collect_links(child, source, links);
+            continue;
+        }
+        let (Some(identifier), Some(argument_list)) = (child.child(0), child.child(2)) else {
+            continue;
+        };
+        if !get_node_content(source, &identifier).eq_ignore_ascii_case("target_link_libraries") {
+            continue;
+        }
+        let arguments = get_arguments(argument_list, source);
+        let Some((target, libraries)) = arguments.split_first() else {
+            continue;
+        };
+        for library in libraries {
+            links.push((library.clone(), target.clone()));
+        }
+    }
+}
+
+fn collect_lenses(
+    input: Node,
+    source: &Vec<&str>,
+    calls: &HashMap<String, usize>,
+    links: &HashMap<String, HashSet<String>>,
+    lenses: &mut Vec<CodeLens>,
+) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        match child.kind() {
+            "function_def" | "macro_def" => {
+                if let Some(name) = child
+                    .child(0)
+                    .and_then(|command| command.child(2))
+                    .and_then(|argument_list| argument_list.named_child(0))
+                {
+                    let name = get_node_content(source, &name).to_lowercase();
+                    let count = calls.get(&name).copied().unwrap_or(0);
+                    lenses.push(new_lens(child, plural(count, "call"), "", Vec::new()));
+                }
+                collect_lenses(child, source, calls, links, lenses);
+            }
+            "normal_command" => {
+                let (Some(identifier), Some(argument_list)) = (child.child(0), child.child(2))
+                else {
+                    continue;
+                };
+                let arguments = get_arguments(argument_list, source);
+                let Some(first) = arguments.first() else {
+                    continue;
+                };
+                match get_node_content(source, &identifier)
+                    .to_lowercase()
+                    .as_str()
+                {
+                    "add_library" | "add_executable" => {
+                        if first.contains('$') {
+                            continue;
+                        }
+                        let count = links.get(first).map(|targets| targets.len()).unwrap_or(0);
+                        lenses.push(new_lens(
+                            child,
+                            format!("linked by {}", plural(count, "target")),
+                            "",
+                            Vec::new(),
+                        ));
+                        if !arguments
+                            .iter()
+                            .any(|argument| argument == "IMPORTED" || argument == "ALIAS")
+                        {
+                            lenses.push(new_lens(
+                                child,
+                                "Build".to_string(),
+                                BUILD_TARGET,
+                                vec![first.clone()],
+                            ));
+                        }
+                    }
+                    "add_test" => {
+                        let name = if first == "NAME" {
+                            arguments.get(1)
+                        } else {
+                            Some(first)
+                        };
+                        if let Some(name) = name.filter(|name| !name.contains('$')) {
+                            lenses.push(new_lens(
+                                child,
+                                "Run test".to_string(),
+                                RUN_TEST,
+                                vec![name.clone()],
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "line_comment" | "bracket_comment" => {}
+            _ => collect_lenses(child, source, calls, links, lenses),
+        }
+    }
+}
+
+#[test]
+fn tst_code_lenses() {
+    let source = r#"function(add_foo name)
+  add_library(${name} foo.cpp)
+endfunction()
+add_library(core core.cpp)
+add_library(core::alias ALIAS core)
+add_executable(app main.cpp)
+target_link_libraries(app PRIVATE core)
+add_foo(plugin)
+target_link_libraries(plugin PUBLIC core)
+add_test(NAME app_test COMMAND app)
+ADD_FOO(other)
+"#;
+    let document = Document::new(source.to_string(), 0);
+    let uri = Url::parse("file:///tmp/CMakeLists.txt").unwrap();
+    let documents = vec![(uri.clone(), document.clone())];
+    let lenses = get_code_lenses(&uri, &document, &documents);
+    assert_eq!(
+        lenses[2].command.as_ref().unwrap().arguments,
+        Some(vec!["core".into(), uri.to_string().into()])
+    );
+    let lenses: Vec<(u32, String, String)> = lenses
+        .into_iter()
+        .map(|lens| {
+            let command = lens.command.unwrap();
+            (lens.range.start.line, command.title, command.command)
+        })
+        .collect();
+    let lens =
+        |line: u32, title: &str, command: &str| (line, title.to_string(), command.to_string());
+    assert_eq!(
+        lenses,
+        vec![
+            lens(0, "2 calls", ""),
+            lens(3, "linked by 2 targets", ""),
+            lens(3, "Build", BUILD_TARGET),
+            lens(4, "linked by 0 targets", ""),
+            lens(5, "linked by 0 targets", ""),
+            lens(5, "Build", BUILD_TARGET),
+            lens(9, "Run test", RUN_TEST),
+        ]
+    );
+}
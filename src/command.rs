@@ -0,0 +1,122 @@
+/// the commands of workspace/executeCommand, the output of the programs is sent to the
+/// client by window/logMessage
+use async_lsp::lsp_types::{LogMessageParams, MessageType};
+use async_lsp::{ClientSocket, LanguageClient};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+pub const BUILD_TARGET: &str = "neocmakelsp.buildTarget";
+pub const RUN_TEST: &str = "neocmakelsp.runTest";
+/// select the build directory, the configured ones are returned without the argument
+pub const SELECT_BUILD_DIR: &str = "neocmakelsp.selectBuildDir";
+/// select the configure preset, the visible ones are returned without the argument
+pub const SELECT_PRESET: &str = "neocmakelsp.selectPreset";
+
+pub fn get_commands() -> Vec<String> {
+    vec![
+        BUILD_TARGET.to_string(),
+        RUN_TEST.to_string(),
+        SELECT_BUILD_DIR.to_string(),
+        SELECT_PRESET.to_string(),
+    ]
+}
+
+fn log(client: &ClientSocket, typ: MessageType, message: String) {
+    let _ = client
+        .clone()
+        .log_message(LogMessageParams { typ, message });
+}
+
+/// run the program in the background, every line of the output is logged
+pub fn spawn_with_log(
+    client: &ClientSocket,
+    program: &str,
+    args: &[String],
+    cwd: &Path,
+) -> std::io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(cwd)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    log(
+        client,
+        MessageType::INFO,
+        format!("$ {program} {}", args.join(" ")),
+    );
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let stderr_client = client.clone();
+    let stderr_thread = stderr.map(|stderr| {
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                log(&stderr_client, MessageType::WARNING, line);
+            }
+        })
+    });
+    let client = client.clone();
+    let program = program.to_string();
+    std::thread::spawn(move || {
+        if let Some(stdout) = stdout {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                log(&client, MessageType::INFO, line);
+            }
+        }
+        if let Some(stderr_thread) = stderr_thread {
+            let _ = stderr_thread.join();
+        }
+        match child.wait() {
+            Ok(status) if status.success() => {
+                log(&client, MessageType::INFO, format!("{program} finished"));
+            }
+            Ok(status) => {
+                log(
+                    &client,
+                    MessageType::ERROR,
+                    format!("{program} failed: {status}"),
+                );
+            }
+            Err(e) => {
+                log(
+                    &client,
+                    MessageType::ERROR,
+                    format!("{program} failed: {e}"),
+                );
+            }
+        }
+    });
+    Ok(())
+}
+
+/// the arguments of cmake to build the target
+pub fn build_target_args(build_dir: &Path, target: &str) -> Vec<String> {
+    vec![
+        "--build".to_string(),
+        build_dir.display().to_string(),
+        "--target".to_string(),
+        target.to_string(),
+    ]
+}
+
+/// the arguments of ctest to run the test, the name should match exactly
+pub fn run_test_args(test: &str) -> Vec<String> {
+    vec![
+        "-R".to_string(),
+        format!("^{}$", regex::escape(test)),
+        "--output-on-failure".to_string(),
+    ]
+}
+
+#[test]
+fn tst_command_args() {
+    assert_eq!(
+        build_target_args(Path::new("/tmp/build"), "foo"),
+        vec!["--build", "/tmp/build", "--target", "foo"]
+    );
+    assert_eq!(
+        run_test_args("foo.bar"),
+        vec!["-R", "^foo\\.bar$", "--output-on-failure"]
+    );
+}
@@ -1,7 +1,12 @@
 mod buildin;
 mod findpackage;
 mod includescanner;
+mod keyword;
 use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+use crate::document::Document;
+use crate::fileapi;
+use crate::filewatcher;
+use crate::indexing::get_indexed;
 use crate::languageserver::BUFFERS_CACHE;
 use crate::scansubs::TREE_MAP;
 use crate::utils;
@@ -9,17 +14,22 @@ use crate::utils::get_node_content;
 use crate::utils::treehelper::{get_pos_type, PositionType};
 use async_lsp::lsp_types;
 use async_lsp::lsp_types::{
-    CompletionItem, CompletionItemKind, CompletionResponse, Documentation, Position,
+    CompletionItem, CompletionItemKind, CompletionList, CompletionResponse, Documentation,
+    MarkupContent, MarkupKind, Position,
 };
-use buildin::{BUILDIN_COMMAND, BUILDIN_MODULE, BUILDIN_VARIABLE};
+use buildin::BUILDIN_MODULE;
+pub use buildin::{BUILDIN_COMMAND, BUILDIN_VARIABLE};
 use futures::executor::block_on;
-use std::collections::HashMap;
+pub use keyword::is_command_keyword;
+use keyword::KeywordComplete;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs;
 use tokio::sync::Mutex;
 
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use substring::Substring;
 
 pub type CompleteKV = HashMap<PathBuf, Vec<CompletionItem>>;
@@ -32,6 +42,163 @@ pub static COMPLETE_CACHE: Lazy<Arc<Mutex<CompleteKV>>> =
 #[cfg(unix)]
 const PKG_IMPORT_TARGET: &str = "IMPORTED_TARGET";
 
+/// where the documentation of the item comes from, it is sent as the data of the item, and the
+/// documentation is filled by completionItem/resolve
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum DocumentationSource {
+    Command,
+    Variable,
+    Module,
+    Package,
+    PkgConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CompletionData {
+    source: DocumentationSource,
+}
+
+/// None if the items are still being indexed
+fn get_documented_items(source: DocumentationSource) -> Option<&'static [CompletionItem]> {
+    let items = match source {
+        DocumentationSource::Command => get_indexed(&BUILDIN_COMMAND)?.as_ref(),
+        DocumentationSource::Variable => get_indexed(&BUILDIN_VARIABLE)?.as_ref(),
+        DocumentationSource::Module => get_indexed(&BUILDIN_MODULE)?.as_ref(),
+        DocumentationSource::Package => return Some(get_indexed(&findpackage::CMAKE_SOURCE)?),
+        #[cfg(unix)]
+        DocumentationSource::PkgConfig => {
+            return Some(get_indexed(&findpackage::PKGCONFIG_SOURCE)?)
+        }
+        #[cfg(not(unix))]
+        DocumentationSource::PkgConfig => return Some(&[]),
+    };
+    Some(items.map(|items| items.as_slice()).unwrap_or_default())
+}
+
+/// build the items of the builtin commands, variables and modules
+/// false if it is cancelled, the cancel is checked between the sources
+pub fn index_builtin(is_cancelled: &dyn Fn() -> bool) -> bool {
+    let steps: [fn(); 3] = [
+        || {
+            Lazy::force(&BUILDIN_COMMAND);
+        },
+        || {
+            Lazy::force(&BUILDIN_VARIABLE);
+        },
+        || {
+            Lazy::force(&BUILDIN_MODULE);
+        },
+    ];
+    run_steps(&steps, is_cancelled)
+}
+
+fn run_steps(steps: &[fn()], is_cancelled: &dyn Fn() -> bool) -> bool {
+    for step in steps {
+        if is_cancelled() {
+            return false;
+        }
+        step();
+    }
+    true
+}
+
+/// build the items of the packages
+/// false if it is cancelled, the cancel is checked between the sources
+pub fn index_packages(is_cancelled: &dyn Fn() -> bool) -> bool {
+    let steps: &[fn()] = &[
+        || {
+            Lazy::force(&findpackage::CMAKE_SOURCE);
+        },
+        #[cfg(unix)]
+        || {
+            Lazy::force(&findpackage::PKGCONFIG_SOURCE);
+        },
+    ];
+    run_steps(steps, is_cancelled)
+}
+
+/// the targets known by the file api, the ones found in the source are skipped
+fn get_target_complete(path: &Path, complete: &[CompletionItem]) -> Vec<CompletionItem> {
+    fileapi::get_targets(path)
+        .into_iter()
+        .filter(|target| !complete.iter().any(|item| item.label == target.name))
+        .map(|target| CompletionItem {
+            label: target.name,
+            kind: Some(CompletionItemKind::CLASS),
+            detail: Some(format!("Target ({})", target.kind)),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// the variables in CMakeCache.txt, the internal ones are skipped
+fn get_cache_complete(path: &Path, complete: &[CompletionItem]) -> Vec<CompletionItem> {
+    filewatcher::with_cache_entries(path, |entries| {
+        entries
+            .values()
+            .filter(|entry| !entry.is_internal())
+            .filter(|entry| !complete.iter().any(|item| item.label == entry.key))
+            .map(|entry| CompletionItem {
+                label: entry.key.clone(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: Some(format!("Cache ({})", entry.entry_type)),
+                documentation: Some(Documentation::String(entry.document())),
+                ..Default::default()
+            })
+            .collect()
+    })
+}
+
+/// the items are sent without the documentation, to keep the response small
+fn without_documentation(source: DocumentationSource) -> Option<Vec<CompletionItem>> {
+    let data = serde_json::to_value(CompletionData { source }).ok();
+    let items = get_documented_items(source)?
+        .iter()
+        .map(|item| CompletionItem {
+            label: item.label.clone(),
+            kind: item.kind,
+            detail: item.detail.clone(),
+            insert_text: item.insert_text.clone(),
+            insert_text_format: item.insert_text_format,
+            data: data.clone(),
+            ..Default::default()
+        })
+        .collect();
+    Some(items)
+}
+
+/// fill the documentation of the item as markdown
+pub fn resolve_completion(mut item: CompletionItem) -> CompletionItem {
+    let Some(CompletionData { source }) = item
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value(data).ok())
+    else {
+        return item;
+    };
+    let Some(Documentation::String(documentation)) = get_documented_items(source)
+        .unwrap_or_default()
+        .iter()
+        .find(|documented| documented.label == item.label)
+        .and_then(|documented| documented.documentation.as_ref())
+    else {
+        return item;
+    };
+    let value = match source {
+        // NOTE: every line is a field, keep the line breaks
+        DocumentationSource::Package | DocumentationSource::PkgConfig => {
+            documentation.lines().collect::<Vec<&str>>().join("  \n")
+        }
+        _ => documentation.trim().to_string(),
+    };
+    item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value: format!("### {}\n\n{value}", item.label),
+    }));
+    item
+}
+
 pub fn rst_doc_read(doc: String, filename: &str) -> Vec<CompletionItem> {
     doc.lines()
         .filter(|line| line.starts_with(".. command:: "))
@@ -83,10 +250,10 @@ pub async fn get_cached_completion<P: AsRef<Path>>(path: P) -> Vec<CompletionIte
             completions.append(&mut data.clone());
         } else if let Ok(context) = fs::read_to_string(parent).await {
             let mut buffer_cache = BUFFERS_CACHE.lock().await;
-            buffer_cache.insert(
-                lsp_types::Url::from_file_path(parent).unwrap(),
-                context.clone(),
-            );
+            // NOTE: do not override the opened buffers, they are synced by didChange
+            buffer_cache
+                .entry(lsp_types::Url::from_file_path(parent).unwrap())
+                .or_insert_with(|| Document::new(context.clone(), 0));
             drop(complete_cache);
             completions.append(&mut update_cache(parent, context.as_str()).await);
             path.clone_from(parent);
@@ -101,15 +268,12 @@ pub async fn get_cached_completion<P: AsRef<Path>>(path: P) -> Vec<CompletionIte
 /// get the complete messages
 pub fn getcomplete(
     source: &str,
+    root: tree_sitter::Node<'_>,
     location: Position,
     _client: &async_lsp::ClientSocket,
     local_path: &str,
     find_cmake_in_package: bool,
 ) -> Option<CompletionResponse> {
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(source, None);
-    let tree = thetree.unwrap();
     let mut complete: Vec<CompletionItem> = vec![];
     let mut cached_compeletion = block_on(get_cached_completion(local_path));
     if !cached_compeletion.is_empty() {
@@ -121,15 +285,36 @@ pub fn getcomplete(
     let subline = line.substring(0, location.character as usize).to_string();
     let mut text = subline.split_whitespace().next_back().unwrap_or("");
 
-    if let Some(index) = text.rfind(|c| c == '(' || c == '[' || c == '{' || c == ',') {
+    if let Some(index) = text.rfind(['(', '[', '{', ',']) {
         text = &text[(index + 1)..];
     }
 
-    let postype = get_pos_type(location, tree.root_node(), source, PositionType::NotFind);
+    // NOTE: in the arguments of the builtin command, the commands, packages and modules
+    // cannot be there
+    // NOTE: the sources which are still being indexed are skipped, the result is incomplete
+    let mut indexing = false;
+    let mut append_source =
+        |complete: &mut Vec<CompletionItem>, source| match without_documentation(source) {
+            Some(mut items) => complete.append(&mut items),
+            None => indexing = true,
+        };
+    let keyword_complete = keyword::get_keyword_complete(root, source, location);
+    let in_arguments = keyword_complete.is_some();
+    let postype = match keyword_complete {
+        Some(KeywordComplete::SubCommands(sub_commands)) => {
+            complete = sub_commands;
+            PositionType::NotFind
+        }
+        Some(KeywordComplete::Keywords(mut keywords)) => {
+            complete.append(&mut keywords);
+            get_pos_type(location, root, source, PositionType::NotFind)
+        }
+        None => get_pos_type(location, root, source, PositionType::NotFind),
+    };
     match postype {
         PositionType::Variable | PositionType::TargetLink | PositionType::TargetInclude => {
             if let Some(mut message) = getsubcomplete(
-                tree.root_node(),
+                root,
                 &source.lines().collect(),
                 Path::new(local_path),
                 postype,
@@ -142,29 +327,32 @@ pub fn getcomplete(
                 complete.append(&mut message);
             }
 
-            if let Ok(messages) = &*BUILDIN_COMMAND {
-                complete.append(&mut messages.clone());
-            }
-            if let Ok(messages) = &*BUILDIN_VARIABLE {
-                complete.append(&mut messages.clone());
+            complete.append(&mut get_target_complete(Path::new(local_path), &complete));
+            complete.append(&mut get_cache_complete(Path::new(local_path), &complete));
+            if !in_arguments {
+                append_source(&mut complete, DocumentationSource::Command);
             }
+            append_source(&mut complete, DocumentationSource::Variable);
+            // NOTE: the builtin variables in the cache are shown with their values
+            let mut seen = HashSet::new();
+            complete.retain(|item| {
+                item.kind != Some(CompletionItemKind::VARIABLE) || seen.insert(item.label.clone())
+            });
         }
-        PositionType::FindPackage => {
-            complete.append(&mut findpackage::CMAKE_SOURCE.clone());
+        PositionType::FindPackage if !in_arguments => {
+            append_source(&mut complete, DocumentationSource::Package);
         }
         #[cfg(unix)]
         PositionType::FindPkgConfig => {
-            complete.append(&mut findpackage::PKGCONFIG_SOURCE.clone());
+            append_source(&mut complete, DocumentationSource::PkgConfig);
         }
-        PositionType::Include => {
-            if let Ok(messages) = &*BUILDIN_MODULE {
-                complete.append(&mut messages.clone());
-            }
+        PositionType::Include if !in_arguments => {
+            append_source(&mut complete, DocumentationSource::Module);
         }
         _ => {}
     }
 
-    if complete.is_empty() {
+    if complete.is_empty() && !indexing {
         // client
         //     .log_message(LogMessageParams {
         //         typ: MessageType::INFO,
@@ -172,15 +360,23 @@ pub fn getcomplete(
         //     })
         //     .unwrap();
         None
-    } else if !text.is_empty() {
-        Some(CompletionResponse::Array(
+    } else {
+        let complete: Vec<CompletionItem> = if text.is_empty() {
+            complete
+        } else {
             complete
                 .into_iter()
                 .filter(|item| item.label.starts_with(text) && item.label != text)
-                .collect(),
-        ))
-    } else {
-        Some(CompletionResponse::Array(complete))
+                .collect()
+        };
+        if indexing {
+            Some(CompletionResponse::List(CompletionList {
+                is_incomplete: true,
+                items: complete,
+            }))
+        } else {
+            Some(CompletionResponse::Array(complete))
+        }
     }
 }
 /// get the variable from the loop
@@ -602,7 +798,7 @@ fn get_cmake_package_complete(
     include_files: &mut Vec<PathBuf>,
     complete_packages: &mut Vec<String>,
 ) -> Option<Vec<CompletionItem>> {
-    let packageinfo = utils::CMAKE_PACKAGES_WITHKEY.get(package_name)?;
+    let packageinfo = get_indexed(&utils::CMAKE_PACKAGES_WITHKEY)?.get(package_name)?;
     let mut complete_infos = Vec::new();
 
     for path in packageinfo.tojump.iter() {
@@ -619,3 +815,26 @@ fn get_cmake_package_complete(
 
     Some(complete_infos)
 }
+
+#[test]
+fn tst_completion_resolve() {
+    let item = CompletionItem {
+        label: "not_a_command".to_string(),
+        data: serde_json::to_value(CompletionData {
+            source: DocumentationSource::Command,
+        })
+        .ok(),
+        ..Default::default()
+    };
+    assert_eq!(item.data, Some(serde_json::json!({ "source": "command" })));
+    // unknown items are not changed
+    assert_eq!(resolve_completion(item.clone()), item);
+    let items = without_documentation(DocumentationSource::Variable).unwrap();
+    assert!(items.iter().all(|item| item.documentation.is_none()));
+    if let Some(variable) = items.first() {
+        assert!(matches!(
+            resolve_completion(variable.clone()).documentation,
+            Some(Documentation::MarkupContent(_))
+        ));
+    }
+}
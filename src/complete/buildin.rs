@@ -232,7 +232,7 @@ mod tests {
                 );
             }
         } else {
-            assert!(false);
+            unreachable!();
         }
 
         let re = regex::Regex::new(r"[z-zA-z]+\n-+").unwrap();
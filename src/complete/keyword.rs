@@ -0,0 +1,308 @@
+/// complete the keywords of the builtin commands, they are derived from the signatures in
+/// `cmake --help-commands`, and the signatures below are used when cmake is not found
+use async_lsp::lsp_types::{CompletionItem, CompletionItemKind, Position};
+use tree_sitter::Node;
+
+use crate::indexing::get_indexed;
+use crate::signature_help::{
+    get_command_context, get_keywords, get_parameters, is_keyword, parse_signatures,
+};
+use crate::utils::treehelper::MESSAGE_STORAGE;
+
+const FALLBACK_SIGNATURES: &[&str] = &[
+    "target_link_libraries(<target> <PRIVATE|PUBLIC|INTERFACE> <item>...)",
+    "target_include_directories(<target> [SYSTEM] [AFTER|BEFORE] <PRIVATE|PUBLIC|INTERFACE> [items]...)",
+    "target_compile_definitions(<target> <PRIVATE|PUBLIC|INTERFACE> [items]...)",
+    "target_compile_options(<target> [BEFORE] <PRIVATE|PUBLIC|INTERFACE> [items]...)",
+    "target_compile_features(<target> <PRIVATE|PUBLIC|INTERFACE> <feature>...)",
+    "target_link_options(<target> [BEFORE] <PRIVATE|PUBLIC|INTERFACE> [items]...)",
+    "target_link_directories(<target> [BEFORE] <PRIVATE|PUBLIC|INTERFACE> [items]...)",
+    "target_sources(<target> <PRIVATE|PUBLIC|INTERFACE> [items]...)",
+    "target_precompile_headers(<target> <PRIVATE|PUBLIC|INTERFACE> [header]...)",
+    "find_package(<PackageName> [version] [EXACT] [QUIET] [REQUIRED] [CONFIG|MODULE|NO_MODULE] [COMPONENTS <components>...] [OPTIONAL_COMPONENTS <components>...] [GLOBAL] [NO_POLICY_SCOPE])",
+    "install(TARGETS <target>... [EXPORT <export-name>] [RUNTIME|LIBRARY|ARCHIVE|FRAMEWORK|BUNDLE|PUBLIC_HEADER|PRIVATE_HEADER|RESOURCE|FILE_SET <set>] [DESTINATION <dir>] [PERMISSIONS <permission>...] [CONFIGURATIONS <config>...] [COMPONENT <component>] [OPTIONAL] [EXCLUDE_FROM_ALL] [INCLUDES DESTINATION <dir>...])",
+    "install(FILES <file>... [TYPE <type>] [DESTINATION <dir>] [PERMISSIONS <permission>...] [CONFIGURATIONS <config>...] [COMPONENT <component>] [RENAME <name>] [OPTIONAL] [EXCLUDE_FROM_ALL])",
+    "install(PROGRAMS <program>... [TYPE <type>] [DESTINATION <dir>] [PERMISSIONS <permission>...] [CONFIGURATIONS <config>...] [COMPONENT <component>] [RENAME <name>] [OPTIONAL] [EXCLUDE_FROM_ALL])",
+    "install(DIRECTORY <dir>... [TYPE <type>] [DESTINATION <dir>] [FILE_PERMISSIONS <permission>...] [DIRECTORY_PERMISSIONS <permission>...] [USE_SOURCE_PERMISSIONS] [OPTIONAL] [MESSAGE_NEVER] [CONFIGURATIONS <config>...] [COMPONENT <component>] [EXCLUDE_FROM_ALL] [FILES_MATCHING] [PATTERN <pattern>] [REGEX <regex>] [EXCLUDE] [PERMISSIONS <permission>...])",
+    "install(SCRIPT <file> [COMPONENT <component>] [EXCLUDE_FROM_ALL])",
+    "install(CODE <code> [COMPONENT <component>] [EXCLUDE_FROM_ALL])",
+    "install(EXPORT <export-name> DESTINATION <dir> [NAMESPACE <namespace>] [FILE <name>.cmake] [PERMISSIONS <permission>...] [CONFIGURATIONS <config>...] [COMPONENT <component>] [EXCLUDE_FROM_ALL])",
+    "file(READ <filename> <variable> [OFFSET <offset>] [LIMIT <max-in>] [HEX])",
+    "file(STRINGS <filename> <variable> [LENGTH_MAXIMUM <max-len>] [LENGTH_MINIMUM <min-len>] [LIMIT_COUNT <max-num>] [LIMIT_INPUT <max-in>] [LIMIT_OUTPUT <max-out>] [NEWLINE_CONSUME] [NO_HEX_CONVERSION] [REGEX <regex>] [ENCODING <encoding-type>])",
+    "file(MD5|SHA1|SHA224|SHA256|SHA384|SHA512 <filename> <variable>)",
+    "file(TIMESTAMP <filename> <variable> [<format>] [UTC])",
+    "file(WRITE <filename> <content>...)",
+    "file(APPEND <filename> <content>...)",
+    "file(TOUCH <files>...)",
+    "file(TOUCH_NOCREATE <files>...)",
+    "file(GENERATE OUTPUT <output-file> [INPUT <input-file>|CONTENT <content>] [CONDITION <expression>] [TARGET <target>] [NO_SOURCE_PERMISSIONS] [USE_SOURCE_PERMISSIONS] [FILE_PERMISSIONS <permission>...] [NEWLINE_STYLE <style>])",
+    "file(CONFIGURE OUTPUT <output-file> CONTENT <content> [ESCAPE_QUOTES] [@ONLY] [NEWLINE_STYLE <style>])",
+    "file(GLOB <variable> [LIST_DIRECTORIES true|false] [RELATIVE <path>] [CONFIGURE_DEPENDS] [<globbing-expressions>...])",
+    "file(GLOB_RECURSE <variable> [FOLLOW_SYMLINKS] [LIST_DIRECTORIES true|false] [RELATIVE <path>] [CONFIGURE_DEPENDS] [<globbing-expressions>...])",
+    "file(MAKE_DIRECTORY <directories>...)",
+    "file(REMOVE <files>...)",
+    "file(REMOVE_RECURSE <files>...)",
+    "file(RENAME <oldname> <newname> [RESULT <result>] [NO_REPLACE])",
+    "file(COPY_FILE <oldname> <newname> [RESULT <result>] [ONLY_IF_DIFFERENT] [INPUT_MAY_BE_RECENT])",
+    "file(COPY <files>... DESTINATION <dir> [NO_SOURCE_PERMISSIONS] [USE_SOURCE_PERMISSIONS] [FILE_PERMISSIONS <permission>...] [DIRECTORY_PERMISSIONS <permission>...] [FOLLOW_SYMLINK_CHAIN] [FILES_MATCHING] [PATTERN <pattern>] [REGEX <regex>] [EXCLUDE] [PERMISSIONS <permission>...])",
+    "file(SIZE <filename> <variable>)",
+    "file(READ_SYMLINK <linkname> <variable>)",
+    "file(CREATE_LINK <original> <linkname> [RESULT <result>] [COPY_ON_ERROR] [SYMBOLIC])",
+    "file(CHMOD <files>... <directories>... [PERMISSIONS <permission>...] [FILE_PERMISSIONS <permission>...] [DIRECTORY_PERMISSIONS <permission>...])",
+    "file(CHMOD_RECURSE <files>... <directories>... [PERMISSIONS <permission>...] [FILE_PERMISSIONS <permission>...] [DIRECTORY_PERMISSIONS <permission>...])",
+    "file(REAL_PATH <path> <out-var> [BASE_DIRECTORY <dir>] [EXPAND_TILDE])",
+    "file(RELATIVE_PATH <variable> <directory> <file>)",
+    "file(TO_CMAKE_PATH <path> <variable>)",
+    "file(TO_NATIVE_PATH <path> <variable>)",
+    "file(DOWNLOAD <url> [<file>] [INACTIVITY_TIMEOUT <seconds>] [LOG <variable>] [SHOW_PROGRESS] [STATUS <variable>] [TIMEOUT <seconds>] [USERPWD <username>:<password>] [HTTPHEADER <HTTP-header>] [NETRC <level>] [NETRC_FILE <file>] [TLS_VERIFY <ON|OFF>] [TLS_CAINFO <file>] [EXPECTED_HASH <algorithm>=<value>] [RANGE_START <value>] [RANGE_END <value>])",
+    "file(UPLOAD <file> <url> [INACTIVITY_TIMEOUT <seconds>] [LOG <variable>] [SHOW_PROGRESS] [STATUS <variable>] [TIMEOUT <seconds>] [USERPWD <username>:<password>] [HTTPHEADER <HTTP-header>] [NETRC <level>] [NETRC_FILE <file>] [TLS_VERIFY <ON|OFF>] [TLS_CAINFO <file>])",
+    "file(LOCK <path> [DIRECTORY] [RELEASE] [GUARD <FUNCTION|FILE|PROCESS>] [RESULT_VARIABLE <variable>] [TIMEOUT <seconds>])",
+    "file(ARCHIVE_CREATE OUTPUT <archive> PATHS <paths>... [FORMAT <format>] [COMPRESSION <compression>] [COMPRESSION_LEVEL <level>] [MTIME <mtime>] [VERBOSE])",
+    "file(ARCHIVE_EXTRACT INPUT <archive> [DESTINATION <dir>] [PATTERNS <patterns>...] [LIST_ONLY] [VERBOSE] [TOUCH])",
+    "file(GET_RUNTIME_DEPENDENCIES [RESOLVED_DEPENDENCIES_VAR <deps_var>] [UNRESOLVED_DEPENDENCIES_VAR <unresolved_deps_var>] [CONFLICTING_DEPENDENCIES_PREFIX <conflicting_deps_prefix>] [EXECUTABLES <executable_files>...] [LIBRARIES <library_files>...] [MODULES <module_files>...] [DIRECTORIES <directories>...])",
+    "string(FIND <string> <substring> <out-var> [REVERSE])",
+    "string(REPLACE <match-string> <replace-string> <out-var> <input>...)",
+    "string(REGEX MATCH|MATCHALL|REPLACE <regular-expression> <out-var> <input>...)",
+    "string(APPEND <string-var> [<input>...])",
+    "string(PREPEND <string-var> [<input>...])",
+    "string(CONCAT <out-var> [<input>...])",
+    "string(JOIN <glue> <out-var> [<input>...])",
+    "string(TOLOWER <string> <out-var>)",
+    "string(TOUPPER <string> <out-var>)",
+    "string(LENGTH <string> <out-var>)",
+    "string(SUBSTRING <string> <begin> <length> <out-var>)",
+    "string(STRIP <string> <out-var>)",
+    "string(GENEX_STRIP <string> <out-var>)",
+    "string(REPEAT <string> <count> <out-var>)",
+    "string(COMPARE LESS|GREATER|EQUAL|NOTEQUAL|LESS_EQUAL|GREATER_EQUAL <string1> <string2> <out-var>)",
+    "string(MD5|SHA1|SHA224|SHA256|SHA384|SHA512 <out-var> <input>)",
+    "string(ASCII <number>... <out-var>)",
+    "string(HEX <string> <out-var>)",
+    "string(CONFIGURE <string> <out-var> [@ONLY] [ESCAPE_QUOTES])",
+    "string(MAKE_C_IDENTIFIER <string> <out-var>)",
+    "string(RANDOM [LENGTH <length>] [ALPHABET <alphabet>] [RANDOM_SEED <seed>] <out-var>)",
+    "string(TIMESTAMP <out-var> [<format string>] [UTC])",
+    "string(UUID <out-var> NAMESPACE <namespace> NAME <name> TYPE <MD5|SHA1> [UPPER])",
+    "string(JSON <out-var> [ERROR_VARIABLE <error-var>] GET|TYPE|LENGTH|REMOVE|MEMBER|SET|EQUAL <json-string> <member|index>...)",
+    "list(LENGTH <list> <out-var>)",
+    "list(GET <list> <element index> [<index>...] <out-var>)",
+    "list(JOIN <list> <glue> <out-var>)",
+    "list(SUBLIST <list> <begin> <length> <out-var>)",
+    "list(FIND <list> <value> <out-var>)",
+    "list(APPEND <list> [<element>...])",
+    "list(FILTER <list> INCLUDE|EXCLUDE REGEX <regex>)",
+    "list(INSERT <list> <index> [<element>...])",
+    "list(POP_BACK <list> [<out-var>...])",
+    "list(POP_FRONT <list> [<out-var>...])",
+    "list(PREPEND <list> [<element>...])",
+    "list(REMOVE_ITEM <list> <value>...)",
+    "list(REMOVE_AT <list> <index>...)",
+    "list(REMOVE_DUPLICATES <list>)",
+    "list(TRANSFORM <list> APPEND|PREPEND|TOLOWER|TOUPPER|STRIP|GENEX_STRIP|REPLACE [AT|FOR|REGEX] [OUTPUT_VARIABLE <output variable>])",
+    "list(REVERSE <list>)",
+    "list(SORT <list> [COMPARE <STRING|FILE_BASENAME|NATURAL>] [CASE <SENSITIVE|INSENSITIVE>] [ORDER <ASCENDING|DESCENDING>])",
+    "get_filename_component(<var> <FileName> DIRECTORY|NAME|EXT|NAME_WE|LAST_EXT|NAME_WLE|PATH [CACHE])",
+];
+
+/// NOTE: the scopes of the target_* commands can be repeated, like
+/// `target_link_libraries(foo PUBLIC a PRIVATE b PUBLIC c)`
+const SCOPE_KEYWORDS: &[&str] = &["PRIVATE", "PUBLIC", "INTERFACE"];
+
+/// the keyword completions in the arguments of the builtin command
+#[derive(Debug)]
+pub enum KeywordComplete {
+    /// the first argument is the sub-command, nothing else can be there
+    SubCommands(Vec<CompletionItem>),
+    /// the keywords can be mixed with the variables
+    Keywords(Vec<CompletionItem>),
+}
+
+fn get_signatures(name: &str) -> Vec<String> {
+    let signatures = get_indexed(&MESSAGE_STORAGE)
+        .and_then(|storage| storage.get(name))
+        .map(|doc| parse_signatures(name, doc))
+        .unwrap_or_default();
+    if !signatures.is_empty() {
+        return signatures;
+    }
+    let prefix = format!("{name}(");
+    FALLBACK_SIGNATURES
+        .iter()
+        .filter(|signature| signature.starts_with(&prefix))
+        .map(|signature| signature.to_string())
+        .collect()
+}
+
+/// the parameter like `READ` or `<HASH>`, the signature is a form of the sub-command
+fn is_sub_command(parameter: &str) -> bool {
+    let parameter = parameter
+        .strip_prefix('<')
+        .and_then(|parameter| parameter.strip_suffix('>'))
+        .unwrap_or(parameter);
+    parameter.split('|').all(is_keyword)
+}
+
+/// whether the word is a keyword in one of the forms of the builtin command
+pub fn is_command_keyword(command: &str, word: &str) -> bool {
+    is_keyword(word)
+        && get_signatures(&command.to_lowercase())
+            .iter()
+            .any(|signature| {
+                get_parameters(signature)
+                    .iter()
+                    .any(|parameter| get_keywords(parameter).contains(&word))
+            })
+}
+
+fn new_item(label: &str, detail: &str) -> CompletionItem {
+    CompletionItem {
+        label: label.to_string(),
+        kind: Some(CompletionItemKind::KEYWORD),
+        detail: Some(detail.to_string()),
+        ..Default::default()
+    }
+}
+
+pub fn get_keyword_complete(
+    root: Node,
+    source: &str,
+    location: Position,
+) -> Option<KeywordComplete> {
+    let context = get_command_context(root, source, location)?;
+    let name = context.name.to_lowercase();
+    let signatures = get_signatures(&name);
+    if signatures.is_empty() {
+        return None;
+    }
+    let forms: Vec<Vec<&str>> = signatures
+        .iter()
+        .map(|signature| get_parameters(signature))
+        .collect();
+    let has_sub_commands = forms.iter().all(|parameters| {
+        parameters
+            .first()
+            .is_some_and(|first| is_sub_command(first))
+    });
+
+    let mut keywords: Vec<&str> = Vec::new();
+    if context.current == 0 {
+        for parameters in &forms {
+            for keyword in parameters
+                .first()
+                .map(|first| get_keywords(first))
+                .unwrap_or_default()
+            {
+                if !keywords.contains(&keyword) {
+                    keywords.push(keyword);
+                }
+            }
+        }
+        if has_sub_commands {
+            return Some(KeywordComplete::SubCommands(
+                keywords
+                    .into_iter()
+                    .map(|keyword| new_item(keyword, "Sub-command"))
+                    .collect(),
+            ));
+        }
+        // NOTE: the first argument may be a package or a module, keep the old completions
+        if keywords.is_empty() {
+            return None;
+        }
+    } else {
+        let first = context
+            .arguments
+            .first()
+            .map(String::as_str)
+            .unwrap_or_default();
+        for parameters in &forms {
+            // only the forms of the typed sub-command
+            if has_sub_commands
+                && !parameters
+                    .first()
+                    .is_some_and(|parameter| get_keywords(parameter).contains(&first))
+            {
+                continue;
+            }
+            let skip = if has_sub_commands { 1 } else { 0 };
+            for parameter in parameters.iter().skip(skip) {
+                for keyword in get_keywords(parameter) {
+                    if !keywords.contains(&keyword) {
+                        keywords.push(keyword);
+                    }
+                }
+            }
+        }
+    }
+    let used = &context.arguments[..context.current.min(context.arguments.len())];
+    let is_repeatable =
+        |keyword: &str| name.starts_with("target_") && SCOPE_KEYWORDS.contains(&keyword);
+    Some(KeywordComplete::Keywords(
+        keywords
+            .into_iter()
+            .filter(|keyword| {
+                is_repeatable(keyword) || !used.iter().any(|argument| argument == keyword)
+            })
+            .map(|keyword| new_item(keyword, "Keyword"))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(source: &str, location: Position) -> Option<(bool, Vec<String>)> {
+        let document = crate::document::Document::new(source.to_string(), 0);
+        match get_keyword_complete(document.root_node(), source, location)? {
+            KeywordComplete::SubCommands(items) => {
+                Some((true, items.into_iter().map(|item| item.label).collect()))
+            }
+            KeywordComplete::Keywords(items) => {
+                Some((false, items.into_iter().map(|item| item.label).collect()))
+            }
+        }
+    }
+
+    #[test]
+    fn tst_keyword_complete() {
+        let (sub_commands, keywords) = labels(
+            "target_link_libraries(foo PUBLIC bar ",
+            Position::new(0, 38),
+        )
+        .unwrap();
+        assert!(!sub_commands);
+        assert_eq!(keywords, vec!["PRIVATE", "PUBLIC", "INTERFACE"]);
+
+        let (_, keywords) = labels(
+            "target_include_directories(foo SYSTEM PUBLIC bar ",
+            Position::new(0, 50),
+        )
+        .unwrap();
+        assert!(!keywords.contains(&"SYSTEM".to_string()));
+        assert!(keywords.contains(&"PUBLIC".to_string()));
+
+        let (_, keywords) = labels("find_package(Foo REQUIRED ", Position::new(0, 26)).unwrap();
+        assert!(keywords.contains(&"COMPONENTS".to_string()));
+        assert!(keywords.contains(&"CONFIG".to_string()));
+        assert!(!keywords.contains(&"REQUIRED".to_string()));
+
+        let (sub_commands, keywords) = labels("file(", Position::new(0, 5)).unwrap();
+        assert!(sub_commands);
+        assert!(keywords.contains(&"GLOB_RECURSE".to_string()));
+        assert!(keywords.contains(&"SHA256".to_string()));
+
+        let (sub_commands, keywords) =
+            labels("install(TARGETS foo ", Position::new(0, 20)).unwrap();
+        assert!(!sub_commands);
+        assert!(keywords.contains(&"DESTINATION".to_string()));
+        assert!(keywords.contains(&"COMPONENT".to_string()));
+        assert!(keywords.contains(&"EXPORT".to_string()));
+        assert!(!keywords.contains(&"RENAME".to_string()));
+
+        let (_, keywords) = labels("string(REGEX ", Position::new(0, 13)).unwrap();
+        assert_eq!(keywords, vec!["MATCH", "MATCHALL", "REPLACE"]);
+
+        assert!(labels("my_function(", Position::new(0, 12)).is_none());
+    }
+}
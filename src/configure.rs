@@ -0,0 +1,425 @@
+/// run `cmake -S <root> -B <dir>` in the background after saving, the errors and warnings in its
+/// output are published as the diagnostics of the files they point to. the runs are debounced,
+/// and a new save cancels the running one. the runs in the real build directory are never killed,
+/// they finish and their results are dropped
+use async_lsp::lsp_types;
+use async_lsp::ClientSocket;
+use lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, Location,
+    Position, Range, Url,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::progress::WorkDone;
+
+/// how often the running cmake checks whether it is cancelled
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+static HEADER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^CMake (Error|Warning|Deprecation Error|Deprecation Warning)( \(dev\))? (?:at|in) (.+?)(?::(\d+))?(?: \(([^)]+)\))?:$",
+    )
+    .unwrap()
+});
+
+/// the messages without location, like `CMake Error: The source directory does not exist`
+static BARE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^CMake (Error|Warning)( \(dev\))?: (.+)$").unwrap());
+
+static FRAME_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s+(.+?):(\d+) \(([^)]+)\)$").unwrap());
+
+const CALL_STACK: &str = "Call Stack (most recent call first):";
+
+/// the diagnostics of every file, the key is the root of the folder
+type ResultsKV = HashMap<PathBuf, HashMap<PathBuf, Vec<Diagnostic>>>;
+
+static RESULTS: Lazy<Arc<Mutex<ResultsKV>>> = Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+/// the id of the latest run of every folder, the older runs stop at their next check
+static RUNS: Lazy<Mutex<HashMap<PathBuf, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// NOTE: the runs in the same real build directory wait for each other, two cmake must not write
+/// one CMakeCache.txt at the same time
+static BUILD_DIRS: Lazy<Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn build_dir_lock(build_dir: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    match BUILD_DIRS.lock() {
+        Ok(mut dirs) => dirs.entry(build_dir.to_path_buf()).or_default().clone(),
+        Err(_) => Arc::default(),
+    }
+}
+
+/// sent to the server when a run is finished, with the files whose diagnostics are changed
+pub struct ConfigureFinished(pub Vec<PathBuf>);
+
+/// a block of the output, like `CMake Error at CMakeLists.txt:3 (foo):` and its message
+#[derive(Debug)]
+struct Block {
+    severity: DiagnosticSeverity,
+    deprecated: bool,
+    file: PathBuf,
+    line: u32,
+    message: Vec<String>,
+    /// the frames of the call stack, the most recent one is the first
+    stack: Vec<(PathBuf, u32, String)>,
+}
+
+fn line_range(line: u32) -> Range {
+    // NOTE: cmake only reports the line, the whole line is marked
+    let line = line.saturating_sub(1);
+    Range {
+        start: Position::new(line, 0),
+        end: Position::new(line + 1, 0),
+    }
+}
+
+fn location(file: &Path, line: u32) -> Option<Location> {
+    Some(Location {
+        uri: Url::from_file_path(file).ok()?,
+        range: line_range(line),
+    })
+}
+
+impl Block {
+    fn new(kind: &str, file: PathBuf, line: u32) -> Self {
+        Block {
+            severity: if kind.ends_with("Error") {
+                DiagnosticSeverity::ERROR
+            } else {
+                DiagnosticSeverity::WARNING
+            },
+            deprecated: kind.starts_with("Deprecation"),
+            file,
+            line,
+            message: Vec::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// the file and the diagnostic, the errors out of the project are moved to the first call
+    /// in the project
+    fn into_diagnostic(self, root: &Path) -> (PathBuf, Diagnostic) {
+        let message = self.message.join("\n").trim_matches('\n').to_string();
+        let mut related: Vec<DiagnosticRelatedInformation> = self
+            .stack
+            .iter()
+            .filter_map(|(file, line, command)| {
+                Some(DiagnosticRelatedInformation {
+                    location: location(file, *line)?,
+                    message: format!("{command}() is called here"),
+                })
+            })
+            .collect();
+        let (file, line) = match self
+            .stack
+            .iter()
+            .find(|(file, _, _)| file.starts_with(root))
+        {
+            Some((file, line, _)) if !self.file.starts_with(root) => {
+                if let Some(location) = location(&self.file, self.line) {
+                    related.insert(
+                        0,
+                        DiagnosticRelatedInformation {
+                            location,
+                            message: "reported here".to_string(),
+                        },
+                    );
+                }
+                (file.clone(), *line)
+            }
+            _ => (self.file, self.line),
+        };
+        let diagnostic = Diagnostic {
+            range: line_range(line),
+            severity: Some(self.severity),
+            source: Some("cmake".to_string()),
+            message,
+            related_information: (!related.is_empty()).then_some(related),
+            tags: self.deprecated.then(|| vec![DiagnosticTag::DEPRECATED]),
+            ..Default::default()
+        };
+        (file, diagnostic)
+    }
+}
+
+/// parse the errors and warnings in the output of cmake, the relative paths are from the root
+fn parse_output(output: &str, root: &Path) -> HashMap<PathBuf, Vec<Diagnostic>> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+    let mut in_stack = false;
+    for line in output.lines() {
+        if let Some(captures) = HEADER_REGEX.captures(line) {
+            blocks.extend(current.take());
+            in_stack = false;
+            let line = captures
+                .get(4)
+                .and_then(|line| line.as_str().parse().ok())
+                .unwrap_or(1);
+            current = Some(Block::new(&captures[1], root.join(&captures[3]), line));
+            continue;
+        }
+        if let Some(captures) = BARE_REGEX.captures(line) {
+            blocks.extend(current.take());
+            in_stack = false;
+            let mut block = Block::new(&captures[1], root.join("CMakeLists.txt"), 1);
+            block.message.push(captures[3].to_string());
+            current = Some(block);
+            continue;
+        }
+        let Some(block) = current.as_mut() else {
+            continue;
+        };
+        if line == CALL_STACK {
+            in_stack = true;
+        } else if in_stack {
+            if let Some(captures) = FRAME_REGEX.captures(line) {
+                let line = captures[2].parse().unwrap_or(1);
+                block
+                    .stack
+                    .push((root.join(&captures[1]), line, captures[3].to_string()));
+            } else if !line.trim().is_empty() {
+                blocks.extend(current.take());
+            }
+        } else if line.is_empty() || line.starts_with(' ') {
+            block.message.push(line.trim().to_string());
+        } else {
+            // NOTE: like `This warning is for project developers.`, the block is ended
+            blocks.extend(current.take());
+        }
+    }
+    blocks.extend(current);
+    let mut results: HashMap<PathBuf, Vec<Diagnostic>> = HashMap::new();
+    for block in blocks {
+        let (file, diagnostic) = block.into_diagnostic(root);
+        results.entry(file).or_default().push(diagnostic);
+    }
+    results
+}
+
+/// the diagnostics of the file from the last run
+pub fn get_diagnostics(path: &Path) -> Vec<Diagnostic> {
+    let Ok(results) = RESULTS.lock() else {
+        return Vec::new();
+    };
+    results
+        .values()
+        .filter_map(|files| files.get(path))
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// replace the results of the folder, the files of both the old and new ones are returned
+fn set_results(root: &Path, files: HashMap<PathBuf, Vec<Diagnostic>>) -> Vec<PathBuf> {
+    let Ok(mut results) = RESULTS.lock() else {
+        return Vec::new();
+    };
+    let mut changed: Vec<PathBuf> = files.keys().cloned().collect();
+    if let Some(old) = results.insert(root.to_path_buf(), files) {
+        for file in old.into_keys() {
+            if !changed.contains(&file) {
+                changed.push(file);
+            }
+        }
+    }
+    changed
+}
+
+/// stop the run of the folder and remove its results, the files of the results are returned
+pub fn clear(root: &Path) -> Vec<PathBuf> {
+    // NOTE: the id is bumped instead of removed, so the ids of the later runs never repeat the
+    // one of the run which is stopped
+    if let Ok(mut runs) = RUNS.lock() {
+        if let Some(id) = runs.get_mut(root) {
+            *id += 1;
+        }
+    }
+    RESULTS
+        .lock()
+        .ok()
+        .and_then(|mut results| results.remove(root))
+        .map(|files| files.into_keys().collect())
+        .unwrap_or_default()
+}
+
+/// a temporary build directory of the folder, so the real one is not touched
+pub fn scratch_dir(root: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    root.hash(&mut hasher);
+    std::env::temp_dir()
+        .join("neocmakelsp-configure")
+        .join(format!("{:x}", hasher.finish()))
+}
+
+pub fn configure_args(root: &Path, build_dir: &Path) -> Vec<String> {
+    vec![
+        "-S".to_string(),
+        root.display().to_string(),
+        "-B".to_string(),
+        build_dir.display().to_string(),
+    ]
+}
+
+fn is_outdated(root: &Path, id: u64) -> bool {
+    RUNS.lock().is_ok_and(|runs| runs.get(root) != Some(&id))
+}
+
+/// configure the folder after the debounce, the run before is cancelled. cmake is only killed in
+/// the scratch directory, a half written build directory is worse than a late result
+pub fn schedule_configure(
+    client: ClientSocket,
+    root: PathBuf,
+    build_dir: PathBuf,
+    in_scratch: bool,
+    debounce: Duration,
+) -> tokio::task::JoinHandle<()> {
+    let id = match RUNS.lock() {
+        Ok(mut runs) => {
+            let id = runs.get(&root).map_or(0, |id| id + 1);
+            runs.insert(root.clone(), id);
+            id
+        }
+        Err(_) => 0,
+    };
+    tokio::spawn(async move {
+        tokio::time::sleep(debounce).await;
+        if is_outdated(&root, id) {
+            return;
+        }
+        let lock = build_dir_lock(&build_dir);
+        let _guard = if in_scratch {
+            None
+        } else {
+            Some(lock.lock().await)
+        };
+        if is_outdated(&root, id) {
+            return;
+        }
+        let progress = WorkDone::begin(&client, "Configuring").await;
+        let child = tokio::process::Command::new("cmake")
+            .args(configure_args(&root, &build_dir))
+            .current_dir(&root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(in_scratch)
+            .spawn();
+        let child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                progress.end(format!("cannot run cmake: {e}"));
+                return;
+            }
+        };
+        // NOTE: cmake is killed when the output is dropped in the scratch directory
+        let output = child.wait_with_output();
+        tokio::pin!(output);
+        let output = loop {
+            tokio::select! {
+                output = &mut output => break output,
+                _ = tokio::time::sleep(POLL_INTERVAL) => {
+                    if in_scratch && (is_outdated(&root, id) || progress.is_cancelled()) {
+                        progress.end("configure cancelled".to_string());
+                        return;
+                    }
+                }
+            }
+        };
+        if is_outdated(&root, id) || progress.is_cancelled() {
+            progress.end("configure cancelled".to_string());
+            return;
+        }
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => {
+                progress.end(format!("configure failed: {e}"));
+                return;
+            }
+        };
+        let results = parse_output(&String::from_utf8_lossy(&output.stderr), &root);
+        let count: usize = results.values().map(Vec::len).sum();
+        let files = set_results(&root, results);
+        progress.end(format!("configure finished with {count} problems"));
+        let _ = client.emit(ConfigureFinished(files));
+    })
+}
+
+#[test]
+fn tst_parse_output() {
+    let root = Path::new("/tmp/project");
+    let output = r#"CMake Warning (dev) at CMakeLists.txt:2 (project):
+  cmake_minimum_required() should be called prior to this top-level project()
+  call.
+This warning is for project developers.  Use -Wno-dev to suppress it.
+
+CMake Error at cmake/helper.cmake:3 (foo):
+  Unknown CMake command "foo".
+Call Stack (most recent call first):
+  CMakeLists.txt:5 (include)
+
+
+CMake Error at /usr/share/cmake/Modules/FindPackageHandleStandardArgs.cmake:230 (message):
+  Could NOT find Bar (missing: BAR_LIBRARY)
+
+  Reason given by package: not found
+Call Stack (most recent call first):
+  /usr/share/cmake/Modules/FindPackageHandleStandardArgs.cmake:600 (_FPHSA_FAILURE_MESSAGE)
+  CMakeLists.txt:7 (find_package)
+
+
+-- Configuring incomplete, errors occurred!
+"#;
+    let results = parse_output(output, root);
+    let main = &results[&root.join("CMakeLists.txt")];
+    assert_eq!(main.len(), 2);
+    assert_eq!(main[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(main[0].range.start, Position::new(1, 0));
+    assert_eq!(
+        main[0].message,
+        "cmake_minimum_required() should be called prior to this top-level project()\ncall."
+    );
+    assert_eq!(main[1].range.start, Position::new(6, 0));
+    assert_eq!(
+        main[1].message,
+        "Could NOT find Bar (missing: BAR_LIBRARY)\n\nReason given by package: not found"
+    );
+    let related = main[1].related_information.as_ref().unwrap();
+    assert_eq!(related.len(), 3);
+    assert_eq!(related[0].message, "reported here");
+    assert_eq!(related[0].location.range.start, Position::new(229, 0));
+
+    let helper = &results[&root.join("cmake/helper.cmake")];
+    assert_eq!(helper[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(helper[0].message, "Unknown CMake command \"foo\".");
+    let related = helper[0].related_information.as_ref().unwrap();
+    assert_eq!(related[0].message, "include() is called here");
+    assert_eq!(
+        related[0].location.uri,
+        Url::from_file_path(root.join("CMakeLists.txt")).unwrap()
+    );
+
+    let changed = set_results(root, results);
+    assert_eq!(changed.len(), 2);
+    RUNS.lock().unwrap().insert(root.to_path_buf(), 0);
+    assert_eq!(get_diagnostics(&root.join("cmake/helper.cmake")).len(), 1);
+    let mut cleared = clear(root);
+    cleared.sort();
+    assert_eq!(
+        cleared,
+        [root.join("CMakeLists.txt"), root.join("cmake/helper.cmake")]
+    );
+    assert!(get_diagnostics(&root.join("cmake/helper.cmake")).is_empty());
+    // the stopped run is outdated, the next run gets a new id
+    assert!(is_outdated(root, 0));
+    assert_eq!(RUNS.lock().unwrap().get(root), Some(&1));
+}
@@ -0,0 +1,233 @@
+/// provide diagnostics, both pushed by publishDiagnostics and pulled by textDocument/diagnostic
+/// and workspace/diagnostic. the result id is the hash of the inputs of the check, so the
+/// files which are not changed are reported as unchanged
+use async_lsp::lsp_types;
+use lsp_types::{
+    Diagnostic, DocumentDiagnosticReport, FullDocumentDiagnosticReport, PreviousResultId, Range,
+    RelatedFullDocumentDiagnosticReport, RelatedUnchangedDocumentDiagnosticReport,
+    UnchangedDocumentDiagnosticReport, Url, WorkspaceDiagnosticReport,
+    WorkspaceDocumentDiagnosticReport, WorkspaceFullDocumentDiagnosticReport,
+    WorkspaceUnchangedDocumentDiagnosticReport,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tree_sitter::Node;
+
+use crate::configure;
+use crate::document::{get_or_read, point_to_utf16_position, Document};
+use crate::grammar::checkerror;
+use crate::presets;
+use crate::utils::get_node_content;
+
+/// changed when the inputs out of the file are changed, like CMakeCache.txt and the settings
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// all the result ids before are outdated
+pub fn invalidate_results() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn get_diagnostics(path: &Path, document: &Document, use_cmake_lint: bool) -> Vec<Diagnostic> {
+    if presets::is_presets_file(path) {
+        return presets::get_diagnostics(path, &document.text);
+    }
+    // NOTE: the problems found by the last configure are reported with the ones of the syntax
+    let configure_diagnostics = configure::get_diagnostics(path);
+    let Some(errors) = checkerror(path, &document.text, document.root_node(), use_cmake_lint)
+    else {
+        return configure_diagnostics;
+    };
+    errors
+        .inner
+        .into_iter()
+        .map(|(start, end, message, severity, data)| Diagnostic {
+            range: Range {
+                start: point_to_utf16_position(&document.text, start),
+                end: point_to_utf16_position(&document.text, end),
+            },
+            severity,
+            message,
+            data: data.and_then(|data| serde_json::to_value(data).ok()),
+            ..Default::default()
+        })
+        .chain(configure_diagnostics)
+        .collect()
+}
+
+pub fn get_result_id(path: &Path, document: &Document, use_cmake_lint: bool) -> String {
+    let mut hasher = DefaultHasher::new();
+    document.text.hash(&mut hasher);
+    use_cmake_lint.hash(&mut hasher);
+    GENERATION.load(Ordering::Relaxed).hash(&mut hasher);
+    // NOTE: the included files may be created, deleted or changed without any event
+    let newsource: Vec<&str> = document.text.lines().collect();
+    if let Some(dir) = path.parent() {
+        hash_includes(document.root_node(), &newsource, dir, &mut hasher);
+    }
+    format!("{:x}", hasher.finish())
+}
+
+/// hash the state of the paths of include(), the diagnostics of include() depend on whether
+/// they exist, are directories and their content
+fn hash_includes(input: Node, source: &Vec<&str>, dir: &Path, hasher: &mut DefaultHasher) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        if child.kind() != "normal_command" {
+            hash_includes(child, source, dir, hasher);
+            continue;
+        }
+        let (Some(identifier), Some(argument)) = (
+            child.child(0),
+            child.child(2).and_then(|arguments| arguments.child(0)),
+        ) else {
+            continue;
+        };
+        if !get_node_content(source, &identifier).eq_ignore_ascii_case("include") {
+            continue;
+        }
+        let argument = get_node_content(source, &argument);
+        let argument = argument.trim_matches('"');
+        if argument.is_empty() || argument.contains('$') {
+            continue;
+        }
+        let state = std::fs::metadata(dir.join(argument))
+            .ok()
+            .map(|metadata| (metadata.is_dir(), metadata.modified().ok()));
+        (argument, state).hash(hasher);
+    }
+}
+
+/// the diagnostics are only checked when the result id is changed
+fn check(
+    path: &Path,
+    document: &Document,
+    use_cmake_lint: bool,
+    previous_result_id: Option<&str>,
+) -> Result<FullDocumentDiagnosticReport, UnchangedDocumentDiagnosticReport> {
+    let result_id = get_result_id(path, document, use_cmake_lint);
+    if previous_result_id == Some(result_id.as_str()) {
+        return Err(UnchangedDocumentDiagnosticReport { result_id });
+    }
+    Ok(FullDocumentDiagnosticReport {
+        result_id: Some(result_id),
+        items: get_diagnostics(path, document, use_cmake_lint),
+    })
+}
+
+pub fn document_report(
+    path: &Path,
+    document: &Document,
+    use_cmake_lint: bool,
+    previous_result_id: Option<&str>,
+) -> DocumentDiagnosticReport {
+    match check(path, document, use_cmake_lint, previous_result_id) {
+        Ok(report) => DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report: report,
+        }),
+        Err(report) => {
+            DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                related_documents: None,
+                unchanged_document_diagnostic_report: report,
+            })
+        }
+    }
+}
+
+/// report the diagnostics of the files, the opened buffers are used first
+pub fn workspace_report(
+    files: &[PathBuf],
+    buffers: &HashMap<Url, Document>,
+    use_cmake_lint: bool,
+    previous_result_ids: &[PreviousResultId],
+) -> WorkspaceDiagnosticReport {
+    let mut items = Vec::new();
+    for path in files {
+        let Ok(uri) = Url::from_file_path(path) else {
+            continue;
+        };
+        let Some(document) = get_or_read(buffers, path) else {
+            continue;
+        };
+        let version = buffers.get(&uri).map(|document| document.version as i64);
+        let previous_result_id = previous_result_ids
+            .iter()
+            .find(|previous| previous.uri == uri)
+            .map(|previous| previous.value.as_str());
+        items.push(
+            match check(path, &document, use_cmake_lint, previous_result_id) {
+                Ok(report) => {
+                    WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+                        uri,
+                        version,
+                        full_document_diagnostic_report: report,
+                    })
+                }
+                Err(report) => WorkspaceDocumentDiagnosticReport::Unchanged(
+                    WorkspaceUnchangedDocumentDiagnosticReport {
+                        uri,
+                        version,
+                        unchanged_document_diagnostic_report: report,
+                    },
+                ),
+            },
+        );
+    }
+    WorkspaceDiagnosticReport { items }
+}
+
+#[test]
+fn tst_pull_diagnostics() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path().to_path_buf();
+    let path = dir.join("CMakeLists.txt");
+    std::fs::write(&path, "include(missing.cmake)\n").unwrap();
+    let uri = Url::from_file_path(&path).unwrap();
+    let files = vec![path.clone()];
+
+    let report = workspace_report(&files, &HashMap::new(), false, &[]);
+    let WorkspaceDocumentDiagnosticReport::Full(full) = &report.items[0] else {
+        panic!("the first report should be full");
+    };
+    assert_eq!(full.version, None);
+    assert_eq!(full.full_document_diagnostic_report.items.len(), 1);
+    let result_id = full
+        .full_document_diagnostic_report
+        .result_id
+        .clone()
+        .unwrap();
+
+    // the opened buffer is used, its version is reported
+    let buffers = HashMap::from([(
+        uri.clone(),
+        Document::new("include(missing.cmake)\n".to_string(), 3),
+    )]);
+    let previous = [PreviousResultId {
+        uri: uri.clone(),
+        value: result_id.clone(),
+    }];
+    let report = workspace_report(&files, &buffers, false, &previous);
+    let WorkspaceDocumentDiagnosticReport::Unchanged(unchanged) = &report.items[0] else {
+        panic!("the same text should be unchanged");
+    };
+    assert_eq!(unchanged.version, Some(3));
+
+    let document = Document::new("message(hello)\n".to_string(), 4);
+    let DocumentDiagnosticReport::Full(full) =
+        document_report(&path, &document, false, Some(&result_id))
+    else {
+        panic!("the changed text should be full");
+    };
+    assert!(full.full_document_diagnostic_report.items.is_empty());
+
+    // the included file is created
+    std::fs::write(dir.join("missing.cmake"), "").unwrap();
+    let report = workspace_report(&files, &HashMap::new(), false, &previous);
+    let WorkspaceDocumentDiagnosticReport::Full(full) = &report.items[0] else {
+        panic!("the report should be full when the included file is created");
+    };
+    assert!(full.full_document_diagnostic_report.items.is_empty());
+}
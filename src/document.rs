@@ -0,0 +1,280 @@
+/// The state of an opened buffer, keep the text and the last parsed tree
+/// so we can reparse incrementally
+use async_lsp::lsp_types;
+use lsp_types::{Position, TextDocumentContentChangeEvent, Url};
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tree_sitter::{InputEdit, Node, Point, Tree};
+
+use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+
+type DiskKV = HashMap<PathBuf, (SystemTime, Document)>;
+
+/// NOTE: the files which are not opened, they are parsed again only when the modified time of
+/// the file is changed
+static DISK_DOCUMENTS: Lazy<Mutex<DiskKV>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub text: String,
+    pub version: i32,
+    pub tree: Tree,
+}
+
+fn parse(text: &str, old_tree: Option<&Tree>) -> Tree {
+    let mut parse = tree_sitter::Parser::new();
+    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
+    parse.parse(text, old_tree).unwrap()
+}
+
+impl Document {
+    pub fn new(text: String, version: i32) -> Self {
+        let tree = parse(&text, None);
+        Self {
+            text,
+            version,
+            tree,
+        }
+    }
+
+    pub fn root_node(&self) -> Node<'_> {
+        self.tree.root_node()
+    }
+
+    /// the hash of the text, the indexes built from the document are reused when it is same
+    pub fn text_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.text.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// replace the whole text, used by full sync and did_save
+    pub fn set_text(&mut self, text: String, version: i32) {
+        self.tree = parse(&text, None);
+        self.text = text;
+        self.version = version;
+    }
+
+    /// apply the changes of didChange, and reparse with the edited tree
+    pub fn apply_changes(&mut self, changes: Vec<TextDocumentContentChangeEvent>, version: i32) {
+        let mut need_full_parse = false;
+        for change in changes {
+            match change.range {
+                Some(range) => self.apply_edit(range.start, range.end, &change.text),
+                None => {
+                    self.text = change.text;
+                    need_full_parse = true;
+                }
+            }
+        }
+        self.tree = if need_full_parse {
+            parse(&self.text, None)
+        } else {
+            parse(&self.text, Some(&self.tree))
+        };
+        self.version = version;
+    }
+
+    fn apply_edit(&mut self, start: Position, end: Position, new_text: &str) {
+        let (start_byte, start_position) = position_to_byte(&self.text, start);
+        let (old_end_byte, old_end_position) = position_to_byte(&self.text, end);
+        let old_end_byte = old_end_byte.max(start_byte);
+        let old_end_position = old_end_position.max(start_position);
+
+        let new_end_byte = start_byte + new_text.len();
+        let new_end_position = match new_text.rfind('\n') {
+            Some(index) => Point {
+                row: start_position.row + new_text.matches('\n').count(),
+                column: new_text.len() - index - 1,
+            },
+            None => Point {
+                row: start_position.row,
+                column: start_position.column + new_text.len(),
+            },
+        };
+
+        self.text.replace_range(start_byte..old_end_byte, new_text);
+        self.tree.edit(&InputEdit {
+            start_byte,
+            old_end_byte,
+            new_end_byte,
+            start_position,
+            old_end_position,
+            new_end_position,
+        });
+    }
+}
+
+/// get the document of the path, use the opened buffer first, or read it from the disk
+pub fn get_or_read(buffers: &HashMap<Url, Document>, path: &Path) -> Option<Document> {
+    if let Some(document) = Url::from_file_path(path)
+        .ok()
+        .and_then(|uri| buffers.get(&uri))
+    {
+        return Some(document.clone());
+    }
+    read_document(path)
+}
+
+/// the same as get_or_read, but the document is visited instead of cloned
+pub fn with_document<T>(
+    buffers: &HashMap<Url, Document>,
+    path: &Path,
+    f: impl FnOnce(&Document) -> T,
+) -> Option<T> {
+    if let Some(document) = Url::from_file_path(path)
+        .ok()
+        .and_then(|uri| buffers.get(&uri))
+    {
+        return Some(f(document));
+    }
+    with_disk_document(path, f)
+}
+
+/// read the document from the disk, the parsed one is reused when the file is not modified
+pub fn read_document(path: &Path) -> Option<Document> {
+    with_disk_document(path, Document::clone)
+}
+
+/// NOTE: the document is visited under the lock of DISK_DOCUMENTS, f must not read any document
+fn with_disk_document<T>(path: &Path, f: impl FnOnce(&Document) -> T) -> Option<T> {
+    let Ok(modified) = std::fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+        DISK_DOCUMENTS.lock().unwrap().remove(path);
+        return None;
+    };
+    let mut documents = DISK_DOCUMENTS.lock().unwrap();
+    if let Some((cached, document)) = documents.get(path) {
+        if *cached == modified {
+            return Some(f(document));
+        }
+    }
+    let document = Document::new(std::fs::read_to_string(path).ok()?, 0);
+    let result = f(&document);
+    documents.insert(path.to_path_buf(), (modified, document));
+    Some(result)
+}
+
+/// forget the parsed document, used when the file is changed outside
+pub fn forget_document(path: &Path) {
+    DISK_DOCUMENTS.lock().unwrap().remove(path);
+}
+
+/// convert the tree-sitter point (byte based) to the lsp position, the character is counted in
+/// utf-16
+pub fn point_to_utf16_position(text: &str, point: Point) -> Position {
+    let character = text
+        .lines()
+        .nth(point.row)
+        .map(|line| {
+            let column = point.column.min(line.len());
+            match line.get(..column) {
+                Some(before) => before.encode_utf16().count(),
+                None => column,
+            }
+        })
+        .unwrap_or(point.column);
+    Position::new(point.row as u32, character as u32)
+}
+
+/// convert the lsp position (utf-16 based) to the byte offset and the tree-sitter point
+/// position out of the text is clamped to the end of the line or the end of the text
+pub fn position_to_byte(text: &str, position: Position) -> (usize, Point) {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(index) => line_start += index + 1,
+            None => {
+                let row = text.matches('\n').count();
+                let column = text.len() - line_start;
+                return (text.len(), Point { row, column });
+            }
+        }
+    }
+    let line = match text[line_start..].find('\n') {
+        Some(index) => &text[line_start..line_start + index],
+        None => &text[line_start..],
+    };
+    let mut column = 0;
+    let mut utf16_count = 0;
+    for c in line.chars() {
+        if utf16_count >= position.character as usize {
+            break;
+        }
+        utf16_count += c.len_utf16();
+        column += c.len_utf8();
+    }
+    (
+        line_start + column,
+        Point {
+            row: position.line as usize,
+            column,
+        },
+    )
+}
+
+#[test]
+fn tst_incremental_edit() {
+    use lsp_types::Range;
+    let source = "set(A \"ss\")\nmessage(${A})\n";
+    let mut document = Document::new(source.to_string(), 0);
+    document.apply_changes(
+        vec![TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position::new(1, 10),
+                end: Position::new(1, 11),
+            }),
+            range_length: None,
+            text: "BB\nproject(".to_string(),
+        }],
+        1,
+    );
+    assert_eq!(document.text, "set(A \"ss\")\nmessage(${BB\nproject(})\n");
+    document.apply_changes(
+        vec![TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position::new(1, 12),
+                end: Position::new(2, 8),
+            }),
+            range_length: None,
+            text: "".to_string(),
+        }],
+        2,
+    );
+    assert_eq!(document.text, "set(A \"ss\")\nmessage(${BB})\n");
+    assert_eq!(document.version, 2);
+    let full = Document::new(document.text.clone(), 2);
+    assert_eq!(document.root_node().to_sexp(), full.root_node().to_sexp());
+}
+
+#[test]
+fn tst_utf16_position() {
+    let source = "set(测试 \"𝄞a\")";
+    let (byte, point) = position_to_byte(source, Position::new(0, 10));
+    assert_eq!(&source[byte..], "a\")");
+    assert_eq!(point.column, byte);
+    assert_eq!(point_to_utf16_position(source, point), Position::new(0, 10));
+    let (byte, point) = position_to_byte(source, Position::new(3, 0));
+    assert_eq!(byte, source.len());
+    assert_eq!(point.row, 0);
+}
+
+#[test]
+fn tst_read_document() {
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("read_document.cmake");
+    std::fs::write(&path, "set(A 1)\n").unwrap();
+    assert_eq!(read_document(&path).unwrap().text, "set(A 1)\n");
+    // NOTE: the modified time decides whether the file is parsed again
+    std::fs::write(&path, "set(B 1)\n").unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(SystemTime::now() + std::time::Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(read_document(&path).unwrap().text, "set(B 1)\n");
+    std::fs::remove_file(&path).unwrap();
+    assert!(read_document(&path).is_none());
+}
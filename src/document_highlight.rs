@@ -0,0 +1,323 @@
+/// provide documentHighlight, highlight the occurrences of the symbol in the document,
+/// or the paired keywords of the block
+use async_lsp::lsp_types;
+use lsp_types::{DocumentHighlight, DocumentHighlightKind, Position, Range};
+use tree_sitter::Node;
+
+use crate::rename::is_command_name_argument;
+use crate::utils::get_node_content;
+use crate::utils::treehelper::{get_position_string, point_to_position, position_to_point};
+
+const BLOCK_KEYWORDS: [&str; 14] = [
+    "if",
+    "elseif",
+    "else",
+    "endif",
+    "foreach",
+    "endforeach",
+    "while",
+    "endwhile",
+    "function",
+    "endfunction",
+    "macro",
+    "endmacro",
+    "block",
+    "endblock",
+];
+
+/// the list subcommands which modify the list in place
+const LIST_MODIFY_SUBCOMMANDS: [&str; 12] = [
+    "APPEND",
+    "PREPEND",
+    "INSERT",
+    "REMOVE_ITEM",
+    "REMOVE_AT",
+    "REMOVE_DUPLICATES",
+    "FILTER",
+    "TRANSFORM",
+    "SORT",
+    "REVERSE",
+    "POP_BACK",
+    "POP_FRONT",
+];
+
+/// the list subcommands which store the result in the last argument
+const LIST_OUTPUT_SUBCOMMANDS: [&str; 5] = ["LENGTH", "GET", "FIND", "JOIN", "SUBLIST"];
+
+pub fn get_document_highlights(
+    location: Position,
+    root: Node,
+    source: &str,
+) -> Option<Vec<DocumentHighlight>> {
+    let newsource: Vec<&str> = source.lines().collect();
+    let point = position_to_point(location);
+    let node = root.descendant_for_point_range(point, point)?;
+    if BLOCK_KEYWORDS.contains(&node.kind()) {
+        return get_keyword_highlights(node.parent()?.parent()?, &newsource);
+    }
+    if node.kind() == "identifier" && is_loop_control(node, &newsource) {
+        // break and continue belong to the nearest loop
+        let mut block = node.parent()?;
+        while !matches!(block.kind(), "foreach_loop" | "while_loop") {
+            block = block.parent()?;
+        }
+        return get_keyword_highlights(block, &newsource);
+    }
+
+    let name = get_position_string(location, root, source)?;
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let is_command = match node.kind() {
+        "identifier" => true,
+        "unquoted_argument" => is_command_name_argument(node),
+        _ => false,
+    };
+    let mut highlights = Vec::new();
+    collect_highlights(root, &newsource, &name, is_command, &mut highlights);
+    if highlights.is_empty() {
+        None
+    } else {
+        Some(highlights)
+    }
+}
+
+fn node_range(node: Node) -> Range {
+    Range {
+        start: point_to_position(node.start_position()),
+        end: point_to_position(node.end_position()),
+    }
+}
+
+fn is_loop_control(identifier: Node, source: &Vec<&str>) -> bool {
+    matches!(
+        get_node_content(source, &identifier)
+            .to_lowercase()
+            .as_str(),
+        "break" | "continue"
+    )
+}
+
+/// highlight the keywords of all the commands of the block, like if/elseif/else/endif
+fn get_keyword_highlights(block: Node, source: &Vec<&str>) -> Option<Vec<DocumentHighlight>> {
+    let mut highlights = Vec::new();
+    let mut course = block.walk();
+    for child in block.children(&mut course) {
+        if child.kind().ends_with("_command") {
+            if let Some(keyword) = child
+                .child(0)
+                .filter(|keyword| BLOCK_KEYWORDS.contains(&keyword.kind()))
+            {
+                highlights.push(DocumentHighlight {
+                    range: node_range(keyword),
+                    kind: Some(DocumentHighlightKind::TEXT),
+                });
+            }
+        } else if child.kind() == "body" && matches!(block.kind(), "foreach_loop" | "while_loop") {
+            // find the break and continue of the loop, but not of the nested loops
+            collect_loop_controls(child, source, &mut highlights);
+        }
+    }
+    highlights.sort_by_key(|highlight| highlight.range.start);
+    Some(highlights)
+}
+
+fn collect_loop_controls(input: Node, source: &Vec<&str>, highlights: &mut Vec<DocumentHighlight>) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        match child.kind() {
+            "foreach_loop" | "while_loop" | "function_def" | "macro_def" => {}
+            "normal_command" => {
+                if let Some(identifier) = child
+                    .child(0)
+                    .filter(|identifier| is_loop_control(*identifier, source))
+                {
+                    highlights.push(DocumentHighlight {
+                        range: node_range(identifier),
+                        kind: Some(DocumentHighlightKind::TEXT),
+                    });
+                }
+            }
+            _ => collect_loop_controls(child, source, highlights),
+        }
+    }
+}
+
+fn collect_highlights(
+    input: Node,
+    source: &Vec<&str>,
+    name: &str,
+    is_command: bool,
+    highlights: &mut Vec<DocumentHighlight>,
+) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        if child.child_count() != 0 {
+            collect_highlights(child, source, name, is_command, highlights);
+            continue;
+        }
+        if child.start_position().row != child.end_position().row {
+            continue;
+        }
+        let kind = match (child.kind(), is_command) {
+            ("identifier", true) | ("unquoted_argument", true) => {
+                if (child.kind() == "unquoted_argument" && !is_command_name_argument(child))
+                    || !get_node_content(source, &child).eq_ignore_ascii_case(name)
+                {
+                    continue;
+                }
+                DocumentHighlightKind::TEXT
+            }
+            ("variable", false) => {
+                if get_node_content(source, &child) != name {
+                    continue;
+                }
+                DocumentHighlightKind::READ
+            }
+            ("unquoted_argument", false) => {
+                if is_command_name_argument(child) || get_node_content(source, &child) != name {
+                    continue;
+                }
+                get_argument_kind(child, source)
+            }
+            _ => continue,
+        };
+        highlights.push(DocumentHighlight {
+            range: node_range(child),
+            kind: Some(kind),
+        });
+    }
+}
+
+/// whether the unquoted argument is written or read by the command
+fn get_argument_kind(node: Node, source: &Vec<&str>) -> DocumentHighlightKind {
+    let Some(argument) = node.parent() else {
+        return DocumentHighlightKind::READ;
+    };
+    let Some(argument_list) = argument.parent() else {
+        return DocumentHighlightKind::READ;
+    };
+    let Some(command) = argument_list.parent() else {
+        return DocumentHighlightKind::READ;
+    };
+    let mut course = argument_list.walk();
+    let arguments: Vec<Node> = argument_list
+        .named_children(&mut course)
+        .filter(|child| child.kind() == "argument")
+        .collect();
+    let Some(index) = arguments.iter().position(|child| *child == argument) else {
+        return DocumentHighlightKind::READ;
+    };
+    let is_write = match command.kind() {
+        "function_command" | "macro_command" => index >= 1,
+        "foreach_command" => index == 0,
+        "normal_command" => {
+            let Some(identifier) = command.child(0) else {
+                return DocumentHighlightKind::READ;
+            };
+            match get_node_content(source, &identifier)
+                .to_lowercase()
+                .as_str()
+            {
+                "set" | "option" | "unset" => index == 0,
+                "list" => {
+                    let subcommand = get_node_content(source, &arguments[0]);
+                    (index == 1 && LIST_MODIFY_SUBCOMMANDS.contains(&subcommand.as_str()))
+                        || (index == arguments.len() - 1
+                            && index >= 2
+                            && LIST_OUTPUT_SUBCOMMANDS.contains(&subcommand.as_str()))
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    };
+    if is_write {
+        DocumentHighlightKind::WRITE
+    } else {
+        DocumentHighlightKind::READ
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+
+    const SOURCE: &str = r#"set(FOO a)
+list(APPEND FOO b)
+list(LENGTH FOO FOO_LEN)
+if(FOO)
+  message(${FOO})
+elseif(BAR)
+else()
+endif()
+function(add_foo FOO)
+  foreach(FOO IN LISTS FOO)
+    if(FOO)
+      break()
+    endif()
+  endforeach()
+endfunction()
+ADD_FOO(FOO)
+"#;
+
+    fn get_highlights(line: u32, character: u32) -> Vec<(u32, u32, DocumentHighlightKind)> {
+        let document = Document::new(SOURCE.to_string(), 0);
+        get_document_highlights(Position::new(line, character), document.root_node(), SOURCE)
+            .unwrap()
+            .into_iter()
+            .map(|highlight| {
+                (
+                    highlight.range.start.line,
+                    highlight.range.start.character,
+                    highlight.kind.unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tst_variable_highlight() {
+        use DocumentHighlightKind as Kind;
+        assert_eq!(
+            get_highlights(0, 5),
+            vec![
+                (0, 4, Kind::WRITE),
+                (1, 12, Kind::WRITE),
+                (2, 12, Kind::READ),
+                (3, 3, Kind::READ),
+                (4, 12, Kind::READ),
+                (8, 17, Kind::WRITE),
+                (9, 10, Kind::WRITE),
+                (9, 23, Kind::READ),
+                (10, 7, Kind::READ),
+                (15, 8, Kind::READ),
+            ]
+        );
+        assert_eq!(get_highlights(2, 17), vec![(2, 16, Kind::WRITE)]);
+    }
+
+    #[test]
+    fn tst_keyword_highlight() {
+        let lines: Vec<(u32, u32)> = get_highlights(6, 1)
+            .into_iter()
+            .map(|(line, character, _)| (line, character))
+            .collect();
+        assert_eq!(lines, vec![(3, 0), (5, 0), (6, 0), (7, 0)]);
+        let lines: Vec<(u32, u32)> = get_highlights(13, 3)
+            .into_iter()
+            .map(|(line, character, _)| (line, character))
+            .collect();
+        assert_eq!(lines, vec![(9, 2), (11, 6), (13, 2)]);
+    }
+
+    #[test]
+    fn tst_command_highlight() {
+        let lines: Vec<u32> = get_highlights(15, 2)
+            .into_iter()
+            .map(|(line, _, _)| line)
+            .collect();
+        assert_eq!(lines, vec![8, 15]);
+    }
+}
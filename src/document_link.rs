@@ -0,0 +1,219 @@
+/// provide documentLink, make the paths in include(), add_subdirectory(), configure_file()
+/// and the source files of the targets clickable
+use async_lsp::lsp_types;
+use lsp_types::{DocumentLink, Range, Url};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tree_sitter::Node;
+
+use crate::jump::include::{find_module, ismodule};
+use crate::utils::get_node_content;
+use crate::utils::treehelper::point_to_position;
+
+const TARGET_SOURCE_COMMANDS: [&str; 3] = ["add_library", "add_executable", "target_sources"];
+
+pub fn get_document_links(
+    source: &str,
+    root: Node,
+    local_path: &Path,
+    dirs: &HashMap<String, String>,
+) -> Vec<DocumentLink> {
+    let newsource: Vec<&str> = source.lines().collect();
+    let Some(current_dir) = local_path.parent() else {
+        return Vec::new();
+    };
+    let mut links = Vec::new();
+    collect_links(root, &newsource, current_dir, dirs, &mut links);
+    links
+}
+
+fn collect_links(
+    input: Node,
+    source: &Vec<&str>,
+    current_dir: &Path,
+    dirs: &HashMap<String, String>,
+    links: &mut Vec<DocumentLink>,
+) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        if child.kind() != "normal_command" {
+            collect_links(child, source, current_dir, dirs, links);
+            continue;
+        }
+        let Some(identifier) = child.child(0) else {
+            continue;
+        };
+        let Some(argument_list) = child
+            .child(2)
+            .filter(|argument_list| argument_list.kind() == "argument_list")
+        else {
+            continue;
+        };
+        let mut argcourse = argument_list.walk();
+        let arguments: Vec<Node> = argument_list
+            .named_children(&mut argcourse)
+            .filter(|argument| argument.kind() == "argument")
+            .collect();
+        let command = get_node_content(source, &identifier).to_lowercase();
+        match command.as_str() {
+            "include" => {
+                let Some((range, argument)) = arguments.first().and_then(|a| get_path(*a, source))
+                else {
+                    continue;
+                };
+                let target = if ismodule(&argument) && !argument.contains('$') {
+                    find_module(&argument)
+                } else {
+                    resolve_path(&argument, current_dir, dirs).filter(|path| path.is_file())
+                };
+                push_link(links, range, target);
+            }
+            "add_subdirectory" => {
+                let Some((range, argument)) = arguments.first().and_then(|a| get_path(*a, source))
+                else {
+                    continue;
+                };
+                let target = resolve_path(&argument, current_dir, dirs)
+                    .map(|dir| dir.join("CMakeLists.txt"))
+                    .filter(|path| path.is_file());
+                push_link(links, range, target);
+            }
+            "configure_file" => {
+                if let Some((range, argument)) =
+                    arguments.first().and_then(|a| get_path(*a, source))
+                {
+                    let target =
+                        resolve_path(&argument, current_dir, dirs).filter(|path| path.is_file());
+                    push_link(links, range, target);
+                }
+                // the output is relative to the binary dir
+                if let Some((range, argument)) = arguments.get(1).and_then(|a| get_path(*a, source))
+                {
+                    let binary_dir = dirs
+                        .get("CMAKE_CURRENT_BINARY_DIR")
+                        .map(PathBuf::from)
+                        .unwrap_or_else(|| current_dir.to_path_buf());
+                    let target =
+                        resolve_path(&argument, &binary_dir, dirs).filter(|path| path.is_file());
+                    push_link(links, range, target);
+                }
+            }
+            command if TARGET_SOURCE_COMMANDS.contains(&command) => {
+                // the first argument is the name of the target
+                for argument in arguments.iter().skip(1) {
+                    let Some((range, argument)) = get_path(*argument, source) else {
+                        continue;
+                    };
+                    let target =
+                        resolve_path(&argument, current_dir, dirs).filter(|path| path.is_file());
+                    push_link(links, range, target);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn push_link(links: &mut Vec<DocumentLink>, range: Range, target: Option<PathBuf>) {
+    let Some(target) = target.and_then(|target| Url::from_file_path(target).ok()) else {
+        return;
+    };
+    links.push(DocumentLink {
+        range,
+        target: Some(target),
+        tooltip: None,
+        data: None,
+    });
+}
+
+/// get the range and the content of the argument, the quotes are not a part of the link
+fn get_path(argument: Node, source: &Vec<&str>) -> Option<(Range, String)> {
+    if argument.start_position().row != argument.end_position().row {
+        return None;
+    }
+    let node = match argument.child(0) {
+        Some(quoted) if quoted.kind() == "quoted_argument" => quoted.named_child(0)?,
+        _ => argument,
+    };
+    let content = get_node_content(source, &node);
+    if content.is_empty() || content.contains("$<") {
+        return None;
+    }
+    Some((
+        Range {
+            start: point_to_position(node.start_position()),
+            end: point_to_position(node.end_position()),
+        },
+        content,
+    ))
+}
+
+/// resolve the path, the prefix like ${CMAKE_CURRENT_SOURCE_DIR} is replaced by its value
+fn resolve_path(argument: &str, base: &Path, dirs: &HashMap<String, String>) -> Option<PathBuf> {
+    let mut path = argument.to_string();
+    if let Some(rest) = argument.strip_prefix("${") {
+        let (name, rest) = rest.split_once('}')?;
+        path = format!("{}{rest}", dirs.get(name)?);
+    }
+    if path.contains('$') {
+        return None;
+    }
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        Some(base.join(path))
+    }
+}
+
+#[test]
+fn tst_document_links() {
+    use crate::document::Document;
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path().to_path_buf();
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    for file in [
+        "sub/CMakeLists.txt",
+        "src/main.cpp",
+        "config.h.in",
+        "utils.cmake",
+    ] {
+        std::fs::write(dir.join(file), "").unwrap();
+    }
+    let source = r#"include(utils.cmake)
+include(${CMAKE_CURRENT_LIST_DIR}/utils.cmake)
+include(missing.cmake)
+add_subdirectory(sub)
+configure_file("config.h.in" config.h)
+add_executable(app src/main.cpp ${PROJECT_SOURCE_DIR}/src/main.cpp src/other.cpp)
+"#;
+    let document = Document::new(source.to_string(), 0);
+    let local_path = dir.join("CMakeLists.txt");
+    let mut dirs = crate::inlay_hint::get_dir_values(&local_path, &HashMap::new());
+    dirs.insert("PROJECT_SOURCE_DIR".to_string(), dir.display().to_string());
+    let links: Vec<(u32, u32, u32, String)> =
+        get_document_links(source, document.root_node(), &local_path, &dirs)
+            .into_iter()
+            .map(|link| {
+                let target = link.target.unwrap().to_file_path().unwrap();
+                (
+                    link.range.start.line,
+                    link.range.start.character,
+                    link.range.end.character,
+                    target.strip_prefix(&dir).unwrap().display().to_string(),
+                )
+            })
+            .collect();
+    assert_eq!(
+        links,
+        vec![
+            (0, 8, 19, "utils.cmake".to_string()),
+            (1, 8, 45, "utils.cmake".to_string()),
+            (3, 17, 20, "sub/CMakeLists.txt".to_string()),
+            (4, 16, 27, "config.h.in".to_string()),
+            (5, 19, 31, "src/main.cpp".to_string()),
+            (5, 32, 66, "src/main.cpp".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,470 @@
+/// the CMake File API, a query is written into the build directory, and the reply written by
+/// cmake tells the real targets of the project, even the ones generated by macros
+use async_lsp::lsp_types;
+use lsp_types::{Location, Position, Range, Url};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const CLIENT_NAME: &str = "client-neocmakelsp";
+
+const QUERY: &str = r#"{
+  "requests": [
+    { "kind": "codemodel", "version": 2 },
+    { "kind": "cache", "version": 2 },
+    { "kind": "cmakeFiles", "version": 1 },
+    { "kind": "toolchains", "version": 1 }
+  ]
+}
+"#;
+
+/// a target in the codemodel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub name: String,
+    /// like EXECUTABLE and STATIC_LIBRARY
+    pub kind: String,
+    pub sources: Vec<PathBuf>,
+    /// the targets it depends on
+    pub dependencies: Vec<String>,
+    /// the libraries on the link command line
+    pub link_libraries: Vec<String>,
+    pub include_dirs: Vec<PathBuf>,
+    /// where the target is defined, the innermost call is the first, the line starts from 1
+    pub backtrace: Vec<(PathBuf, u32)>,
+}
+
+/// NOTE: every workspace folder has its own build directory, the key is the root of the folder
+type TargetsKV = HashMap<PathBuf, Vec<Target>>;
+
+static TARGETS: Lazy<Arc<Mutex<TargetsKV>>> = Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+#[derive(Deserialize)]
+struct Index {
+    reply: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ClientReply {
+    #[serde(rename = "query.json")]
+    query: Option<QueryReply>,
+}
+
+#[derive(Deserialize)]
+struct QueryReply {
+    #[serde(default)]
+    responses: Vec<ReplyObject>,
+}
+
+#[derive(Deserialize)]
+struct ReplyObject {
+    kind: Option<String>,
+    #[serde(rename = "jsonFile")]
+    json_file: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Codemodel {
+    paths: CodemodelPaths,
+    configurations: Vec<Configuration>,
+}
+
+#[derive(Deserialize)]
+struct CodemodelPaths {
+    source: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Configuration {
+    targets: Vec<TargetReference>,
+}
+
+#[derive(Deserialize)]
+struct TargetReference {
+    id: String,
+    name: String,
+    #[serde(rename = "jsonFile")]
+    json_file: String,
+}
+
+#[derive(Deserialize)]
+struct TargetObject {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    backtrace: Option<usize>,
+    #[serde(rename = "backtraceGraph")]
+    backtrace_graph: Option<BacktraceGraph>,
+    #[serde(default)]
+    sources: Vec<SourceObject>,
+    #[serde(default)]
+    dependencies: Vec<DependencyObject>,
+    link: Option<LinkObject>,
+    #[serde(rename = "compileGroups", default)]
+    compile_groups: Vec<CompileGroup>,
+}
+
+#[derive(Deserialize)]
+struct BacktraceGraph {
+    nodes: Vec<BacktraceNode>,
+    files: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct BacktraceNode {
+    file: usize,
+    line: Option<u32>,
+    parent: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SourceObject {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct DependencyObject {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct LinkObject {
+    #[serde(rename = "commandFragments", default)]
+    command_fragments: Vec<CommandFragment>,
+}
+
+#[derive(Deserialize)]
+struct CommandFragment {
+    fragment: String,
+    role: String,
+}
+
+#[derive(Deserialize)]
+struct CompileGroup {
+    #[serde(default)]
+    includes: Vec<IncludeObject>,
+}
+
+#[derive(Deserialize)]
+struct IncludeObject {
+    path: PathBuf,
+}
+
+fn query_dir(build_dir: &Path) -> PathBuf {
+    build_dir.join(".cmake/api/v1/query").join(CLIENT_NAME)
+}
+
+fn reply_dir(build_dir: &Path) -> PathBuf {
+    build_dir.join(".cmake/api/v1/reply")
+}
+
+/// write the query, cmake replies it in the next configure. nothing is written if the build
+/// directory does not exist
+pub fn write_query(build_dir: &Path) -> std::io::Result<()> {
+    if !build_dir.is_dir() {
+        return Ok(());
+    }
+    let dir = query_dir(build_dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("query.json");
+    if std::fs::read_to_string(&path).is_ok_and(|context| context == QUERY) {
+        return Ok(());
+    }
+    std::fs::write(path, QUERY)
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let context = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&context).ok()
+}
+
+/// the latest index file, the names are ordered by the time
+fn latest_index(reply_dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(reply_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("index-") && name.ends_with(".json"))
+        })
+        .max()
+}
+
+fn get_backtrace(target: &TargetObject, source_dir: &Path) -> Vec<(PathBuf, u32)> {
+    let (Some(mut index), Some(graph)) = (target.backtrace, target.backtrace_graph.as_ref()) else {
+        return Vec::new();
+    };
+    let mut backtrace = Vec::new();
+    // NOTE: the parents are always before the children, so it cannot loop
+    while let Some(node) = graph.nodes.get(index) {
+        if let (Some(file), Some(line)) = (graph.files.get(node.file), node.line) {
+            backtrace.push((source_dir.join(file), line));
+        }
+        match node.parent {
+            Some(parent) if parent < index => index = parent,
+            _ => break,
+        }
+    }
+    backtrace
+}
+
+/// read the targets from the reply in the build directory
+pub fn read_reply(build_dir: &Path) -> Option<Vec<Target>> {
+    let reply_dir = reply_dir(build_dir);
+    let index: Index = read_json(&latest_index(&reply_dir)?)?;
+    let client: ClientReply = serde_json::from_value(index.reply.get(CLIENT_NAME)?.clone()).ok()?;
+    let codemodel_file = client
+        .query?
+        .responses
+        .into_iter()
+        .find(|response| response.kind.as_deref() == Some("codemodel"))?
+        .json_file?;
+    let codemodel: Codemodel = read_json(&reply_dir.join(codemodel_file))?;
+    // NOTE: the targets are the same in every configuration, only the first one is used
+    let configuration = codemodel.configurations.into_iter().next()?;
+    let names: HashMap<&str, &str> = configuration
+        .targets
+        .iter()
+        .map(|target| (target.id.as_str(), target.name.as_str()))
+        .collect();
+    let source_dir = &codemodel.paths.source;
+    let mut targets = Vec::new();
+    for reference in configuration.targets.iter() {
+        let Some(target) = read_json::<TargetObject>(&reply_dir.join(&reference.json_file)) else {
+            continue;
+        };
+        let mut include_dirs: Vec<PathBuf> = Vec::new();
+        for include in target
+            .compile_groups
+            .iter()
+            .flat_map(|group| group.includes.iter())
+        {
+            if !include_dirs.contains(&include.path) {
+                include_dirs.push(include.path.clone());
+            }
+        }
+        targets.push(Target {
+            backtrace: get_backtrace(&target, source_dir),
+            sources: target
+                .sources
+                .iter()
+                .map(|source| source_dir.join(&source.path))
+                .collect(),
+            dependencies: target
+                .dependencies
+                .iter()
+                .filter_map(|dependency| names.get(dependency.id.as_str()))
+                .map(|name| name.to_string())
+                .collect(),
+            link_libraries: target
+                .link
+                .map(|link| {
+                    link.command_fragments
+                        .into_iter()
+                        .filter(|fragment| fragment.role == "libraries")
+                        .map(|fragment| fragment.fragment)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            include_dirs,
+            name: target.name,
+            kind: target.kind,
+        });
+    }
+    Some(targets)
+}
+
+/// write the query and read the reply of the project
+pub fn refresh_targets(root: &Path, build_dir: &Path) {
+    let _ = write_query(build_dir);
+    let targets = read_reply(build_dir);
+    let Ok(mut models) = TARGETS.lock() else {
+        return;
+    };
+    match targets {
+        Some(targets) => models.insert(root.to_path_buf(), targets),
+        None => models.remove(root),
+    };
+}
+
+pub fn clear_targets(root: &Path) {
+    let Ok(mut models) = TARGETS.lock() else {
+        return;
+    };
+    models.remove(root);
+}
+
+/// get the targets of the project which contains the path, the nested one is preferred
+pub fn get_targets(path: &Path) -> Vec<Target> {
+    let Ok(models) = TARGETS.lock() else {
+        return Vec::new();
+    };
+    models
+        .iter()
+        .filter(|(root, _)| path.starts_with(root))
+        .max_by_key(|(root, _)| root.components().count())
+        .map(|(_, targets)| targets.clone())
+        .unwrap_or_default()
+}
+
+pub fn get_target(path: &Path, name: &str) -> Option<Target> {
+    get_targets(path)
+        .into_iter()
+        .find(|target| target.name == name)
+}
+
+/// the calls which define the target
+pub fn get_definition(path: &Path, name: &str) -> Option<Vec<Location>> {
+    let target = get_target(path, name)?;
+    let locations: Vec<Location> = target
+        .backtrace
+        .iter()
+        .filter_map(|(file, line)| {
+            let position = Position::new(line.saturating_sub(1), 0);
+            Some(Location {
+                uri: Url::from_file_path(file).ok()?,
+                range: Range {
+                    start: position,
+                    end: position,
+                },
+            })
+        })
+        .collect();
+    if locations.is_empty() {
+        None
+    } else {
+        Some(locations)
+    }
+}
+
+fn format_list<T: AsRef<str>>(title: &str, items: &[T]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    let mut message = format!("\n{title}:\n");
+    for item in items {
+        message.push_str(&format!("- {}\n", item.as_ref()));
+    }
+    message
+}
+
+/// the document of the target, shown by hover
+pub fn get_target_doc(path: &Path, name: &str) -> Option<String> {
+    let target = get_target(path, name)?;
+    let display = |paths: &[PathBuf]| -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect()
+    };
+    let mut message = format!("Target: {}\nType: {}\n", target.name, target.kind);
+    if let Some((file, line)) = target.backtrace.first() {
+        message.push_str(&format!("Defined: {}:{line}\n", file.display()));
+    }
+    message.push_str(&format_list("Sources", &display(&target.sources)));
+    message.push_str(&format_list("Dependencies", &target.dependencies));
+    message.push_str(&format_list("Link libraries", &target.link_libraries));
+    message.push_str(&format_list(
+        "Include directories",
+        &display(&target.include_dirs),
+    ));
+    Some(message)
+}
+
+#[test]
+fn tst_read_reply() {
+    let temp = tempfile::tempdir().unwrap();
+    let build_dir = temp.path().join("build");
+    let reply_dir = reply_dir(&build_dir);
+    std::fs::create_dir_all(&reply_dir).unwrap();
+    let write = |name: &str, value: serde_json::Value| {
+        std::fs::write(reply_dir.join(name), value.to_string()).unwrap();
+    };
+    write(
+        "index-2024-01-01T00-00-00-0000.json",
+        serde_json::json!({
+            "reply": {
+                "client-neocmakelsp": {
+                    "query.json": {
+                        "responses": [
+                            { "kind": "codemodel", "version": { "major": 2, "minor": 6 },
+                              "jsonFile": "codemodel-v2-1.json" },
+                            { "kind": "cache", "version": { "major": 2, "minor": 0 },
+                              "jsonFile": "cache-v2-1.json" }
+                        ]
+                    }
+                }
+            }
+        }),
+    );
+    write(
+        "codemodel-v2-1.json",
+        serde_json::json!({
+            "paths": { "source": "/tmp/project", "build": "/tmp/project/build" },
+            "configurations": [{
+                "name": "Debug",
+                "targets": [
+                    { "id": "core::@6890", "name": "core", "jsonFile": "target-core.json" },
+                    { "id": "app::@6890", "name": "app", "jsonFile": "target-app.json" }
+                ]
+            }]
+        }),
+    );
+    write(
+        "target-core.json",
+        serde_json::json!({
+            "name": "core",
+            "type": "STATIC_LIBRARY",
+            "backtrace": 2,
+            "backtraceGraph": {
+                "commands": ["add_library", "make_library"],
+                "files": ["cmake/helpers.cmake", "CMakeLists.txt"],
+                "nodes": [
+                    { "file": 1 },
+                    { "file": 1, "line": 4, "command": 1, "parent": 0 },
+                    { "file": 0, "line": 2, "command": 0, "parent": 1 }
+                ]
+            },
+            "sources": [{ "path": "src/core.cpp" }],
+            "compileGroups": [{ "includes": [{ "path": "/tmp/project/include" }] }]
+        }),
+    );
+    write(
+        "target-app.json",
+        serde_json::json!({
+            "name": "app",
+            "type": "EXECUTABLE",
+            "dependencies": [{ "id": "core::@6890" }],
+            "link": { "commandFragments": [
+                { "fragment": "-g", "role": "flags" },
+                { "fragment": "libcore.a", "role": "libraries" }
+            ] }
+        }),
+    );
+    let targets = read_reply(&build_dir).unwrap();
+    assert_eq!(targets.len(), 2);
+    assert_eq!(targets[0].kind, "STATIC_LIBRARY");
+    assert_eq!(
+        targets[0].sources,
+        vec![PathBuf::from("/tmp/project/src/core.cpp")]
+    );
+    assert_eq!(
+        targets[0].backtrace,
+        vec![
+            (PathBuf::from("/tmp/project/cmake/helpers.cmake"), 2),
+            (PathBuf::from("/tmp/project/CMakeLists.txt"), 4)
+        ]
+    );
+    assert_eq!(targets[1].dependencies, vec!["core".to_string()]);
+    assert_eq!(targets[1].link_libraries, vec!["libcore.a".to_string()]);
+
+    write_query(&build_dir).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(query_dir(&build_dir).join("query.json")).unwrap(),
+        QUERY
+    );
+}
@@ -1,59 +1,249 @@
 use once_cell::sync::Lazy;
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::Mutex;
 
-// match like ss_DIR:PATH=ss_DIR-NOTFOUND
-
-static NOT_FOUND_LIBRARY: Lazy<regex::Regex> = Lazy::new(|| {
-    regex::Regex::new(r"^(?P<library>[\da-zA-Z]+)_DIR:PATH=([\da-zA-Z]+)_DIR-NOTFOUND$").unwrap()
+// match like CMAKE_BUILD_TYPE:STRING=Debug or "KEY WITH SPACE":BOOL=ON
+static CACHE_ENTRY: Lazy<regex::Regex> = Lazy::new(|| {
+    regex::Regex::new(r#"^(?P<key>"[^"]+"|[^:#/][^:]*):(?P<type>[A-Z]+)=(?P<value>.*)$"#).unwrap()
 });
 
-static ERROR_PACKAGES: Lazy<Arc<Mutex<Vec<String>>>> =
-    Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
+/// the suffix of the values which are not found by find_*
+pub const NOT_FOUND_SUFFIX: &str = "-NOTFOUND";
 
-#[test]
-fn failedtest() {
-    let a = "ss_DIR:PATH=ss_DIR-NOTFOUND";
-    assert!(NOT_FOUND_LIBRARY.is_match(a));
-    let cap = NOT_FOUND_LIBRARY.captures(a).unwrap();
-    assert_eq!("ss", &cap["library"]);
-}
-
-pub fn refresh_error_packages<P: AsRef<Path>>(p: P) {
-    let geterr = || -> anyhow::Result<_> {
-        let mut errors: Vec<String> = Vec::new();
-        let mut file = File::open(&p)?;
-        let mut context = String::new();
-        file.read_to_string(&mut context)?;
-        let lines: Vec<&str> = context.lines().collect();
-        for line in lines {
-            if NOT_FOUND_LIBRARY.is_match(line) {
-                let cap = NOT_FOUND_LIBRARY.captures(line).unwrap();
-                errors.push(cap["library"].to_string());
-            }
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CacheEntry {
+    pub key: String,
+    /// BOOL, PATH, FILEPATH, STRING, INTERNAL, STATIC or UNINITIALIZED
+    pub entry_type: String,
+    pub value: String,
+    /// the help string in the // comments before the entry
+    pub help: String,
+    /// marked by mark_as_advanced
+    pub advanced: bool,
+    /// the allowed values of the STRINGS property
+    pub strings: Vec<String>,
+}
+
+impl CacheEntry {
+    /// the entries used by cmake itself, they are not shown in cmake-gui
+    pub fn is_internal(&self) -> bool {
+        self.entry_type == "INTERNAL" || self.entry_type == "STATIC"
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.value.ends_with(NOT_FOUND_SUFFIX)
+    }
+
+    /// the document of the entry, shown by hover and completion
+    pub fn document(&self) -> String {
+        let mut message = format!(
+            "Cache: {}\nType: {}\nValue: {}\n",
+            self.key, self.entry_type, self.value
+        );
+        if self.advanced {
+            message.push_str("Advanced: true\n");
         }
-        Ok(errors)
-    };
-    let errorpackages = geterr().unwrap_or_else(|_| Vec::new());
-    let Ok(mut packages) = ERROR_PACKAGES.lock() else {
+        if !self.strings.is_empty() {
+            message.push_str(&format!("Strings: {}\n", self.strings.join(", ")));
+        }
+        if !self.help.is_empty() {
+            message.push_str(&format!("\n{}\n", self.help));
+        }
+        message
+    }
+}
+
+/// the data of CMakeCache.txt of a project
+#[derive(Debug, Default)]
+struct CacheData {
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// NOTE: every workspace folder has its own build directory, the key is the root of the folder
+static CACHES: Lazy<Arc<Mutex<HashMap<PathBuf, CacheData>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+/// find the cache of the project which contains the path, the nested one is preferred
+fn find_cache<'a>(caches: &'a HashMap<PathBuf, CacheData>, path: &Path) -> Option<&'a CacheData> {
+    caches
+        .iter()
+        .filter(|(root, _)| path.starts_with(root))
+        .max_by_key(|(root, _)| root.components().count())
+        .map(|(_, data)| data)
+}
+
+/// parse the entries of CMakeCache.txt, the properties like NAME-ADVANCED and NAME-STRINGS
+/// are applied to the entries instead of being entries
+pub fn parse_cache(context: &str) -> HashMap<String, CacheEntry> {
+    let mut entries: HashMap<String, CacheEntry> = HashMap::new();
+    let mut properties = Vec::new();
+    let mut help: Vec<&str> = Vec::new();
+    for line in context.lines() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix("//") {
+            help.push(comment);
+            continue;
+        }
+        let Some(cap) = CACHE_ENTRY.captures(line) else {
+            help.clear();
+            continue;
+        };
+        let key = cap["key"].trim_matches('"').to_string();
+        let value = cap["value"].to_string();
+        let property = ["-ADVANCED", "-STRINGS", "-MODIFIED", "-TYPE", "-HELPSTRING"]
+            .into_iter()
+            .find(|suffix| key.ends_with(suffix));
+        if let (Some(suffix), "INTERNAL") = (property, &cap["type"]) {
+            properties.push((key[..key.len() - suffix.len()].to_string(), suffix, value));
+            help.clear();
+            continue;
+        }
+        entries.insert(
+            key.clone(),
+            CacheEntry {
+                key,
+                entry_type: cap["type"].to_string(),
+                value,
+                help: help.join("\n"),
+                ..Default::default()
+            },
+        );
+        help.clear();
+    }
+    for (key, suffix, value) in properties {
+        let Some(entry) = entries.get_mut(&key) else {
+            continue;
+        };
+        match suffix {
+            "-ADVANCED" => entry.advanced = value == "1",
+            "-STRINGS" => entry.strings = value.split(';').map(|s| s.to_string()).collect(),
+            _ => {}
+        }
+    }
+    entries
+}
+
+#[test]
+fn tst_parse_cache() {
+    let context = r#"# This is the CMakeCache file.
+//Choose the type of build
+CMAKE_BUILD_TYPE:STRING=Debug
+BUILD_TESTING:BOOL=ON
+"KEY WITH SPACE":STRING=a b
+//Value Computed by CMake
+CMAKE_HOME_DIRECTORY:INTERNAL=/tmp/project
+EMPTY:PATH=
+//Path to a library.
+//It is found by find_library.
+ZLIB_LIBRARY:FILEPATH=ZLIB_LIBRARY-NOTFOUND
+
+########################
+# INTERNAL cache entries
+########################
+
+//ADVANCED property for variable: ZLIB_LIBRARY
+ZLIB_LIBRARY-ADVANCED:INTERNAL=1
+//STRINGS property for variable: CMAKE_BUILD_TYPE
+CMAKE_BUILD_TYPE-STRINGS:INTERNAL=Debug;Release
+"#;
+    let entries = parse_cache(context);
+    assert_eq!(entries.len(), 6);
+    assert_eq!(entries["CMAKE_BUILD_TYPE"].help, "Choose the type of build");
+    assert_eq!(
+        entries["CMAKE_BUILD_TYPE"].strings,
+        vec!["Debug", "Release"]
+    );
+    assert!(entries["CMAKE_HOME_DIRECTORY"].is_internal());
+    assert!(entries["BUILD_TESTING"].help.is_empty());
+    let library = &entries["ZLIB_LIBRARY"];
+    assert!(library.advanced && library.is_not_found());
+    assert_eq!(
+        library.help,
+        "Path to a library.\nIt is found by find_library."
+    );
+    assert_eq!(entries["CMAKE_BUILD_TYPE"].value, "Debug");
+    assert_eq!(entries["BUILD_TESTING"].entry_type, "BOOL");
+    assert_eq!(entries["KEY WITH SPACE"].value, "a b");
+    assert_eq!(entries["EMPTY"].value, "");
+}
+
+pub fn refresh_cache<P: AsRef<Path>>(root: &Path, p: P) {
+    let entries = std::fs::read_to_string(&p)
+        .map(|context| parse_cache(&context))
+        .unwrap_or_default();
+    let Ok(mut caches) = CACHES.lock() else {
         return;
     };
-    *packages = errorpackages;
+    caches.insert(root.to_path_buf(), CacheData { entries });
 }
 
-pub fn clear_error_packages() {
-    let Ok(mut packages) = ERROR_PACKAGES.lock() else {
+pub fn clear_cache(root: &Path) {
+    let Ok(mut caches) = CACHES.lock() else {
         return;
     };
-    *packages = Vec::new();
+    caches.remove(root);
 }
 
-pub fn get_error_packages() -> Vec<String> {
-    match ERROR_PACKAGES.lock() {
-        Ok(packages) => packages.to_vec(),
-        Err(_) => Vec::new(),
+/// visit the entries of the cache of the project which contains the path, they are used under
+/// the lock instead of being copied. the entries are empty if there is no cache
+pub fn with_cache_entries<T>(path: &Path, f: impl FnOnce(&HashMap<String, CacheEntry>) -> T) -> T {
+    let caches = CACHES.lock();
+    match caches
+        .as_ref()
+        .ok()
+        .and_then(|caches| find_cache(caches, path))
+    {
+        Some(data) => f(&data.entries),
+        None => f(&HashMap::new()),
     }
 }
+
+/// get one entry of the cache of the project which contains the path
+pub fn get_cache_entry(path: &Path, key: &str) -> Option<CacheEntry> {
+    with_cache_entries(path, |entries| entries.get(key).cloned())
+}
+
+/// the packages which are not found by find_package, their NAME_DIR is NAME_DIR-NOTFOUND
+pub fn get_error_packages(path: &Path) -> Vec<String> {
+    with_cache_entries(path, |entries| {
+        entries
+            .values()
+            .filter(|entry| entry.is_not_found())
+            .filter_map(|entry| entry.key.strip_suffix("_DIR").map(|name| name.to_string()))
+            .collect()
+    })
+}
+
+/// the variables which are not found by find_library, find_path, find_program and find_file
+pub fn get_not_found_variables(path: &Path) -> Vec<String> {
+    with_cache_entries(path, |entries| {
+        entries
+            .values()
+            .filter(|entry| entry.is_not_found() && !entry.key.ends_with("_DIR"))
+            .map(|entry| entry.key.clone())
+            .collect()
+    })
+}
+
+#[test]
+fn tst_cache_of_folders() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path().to_path_buf();
+    let cache = dir.join("CMakeCache.txt");
+    std::fs::write(
+        &cache,
+        "foo-bar_DIR:PATH=foo-bar_DIR-NOTFOUND\nFOO:BOOL=ON\nZ_LIB:FILEPATH=Z_LIB-NOTFOUND\n",
+    )
+    .unwrap();
+    let root = Path::new("/tmp/neocmakelsp_folder_a");
+    refresh_cache(root, &cache);
+    let file = root.join("sub").join("CMakeLists.txt");
+    assert_eq!(get_error_packages(&file), vec!["foo-bar".to_string()]);
+    assert_eq!(get_not_found_variables(&file), vec!["Z_LIB".to_string()]);
+    assert_eq!(get_cache_entry(&file, "FOO").unwrap().value, "ON");
+    assert!(get_error_packages(Path::new("/tmp/neocmakelsp_folder_b/CMakeLists.txt")).is_empty());
+    clear_cache(root);
+    assert!(with_cache_entries(&file, |entries| entries.is_empty()));
+}
@@ -0,0 +1,176 @@
+/// provide foldingRange, the ranges are built from the tree-sitter tree
+use async_lsp::lsp_types;
+use lsp_types::{FoldingRange, FoldingRangeKind};
+use tree_sitter::Node;
+
+pub fn get_folding_ranges(source: &str, root: Node) -> Vec<FoldingRange> {
+    let newsource: Vec<&str> = source.lines().collect();
+    let mut ranges = Vec::new();
+    let mut comment_rows = Vec::new();
+    collect_ranges(root, &newsource, &mut ranges, &mut comment_rows);
+
+    // the runs of line comments
+    comment_rows.sort_unstable();
+    comment_rows.dedup();
+    let mut index = 0;
+    while index < comment_rows.len() {
+        let start = comment_rows[index];
+        let mut end = start;
+        while index + 1 < comment_rows.len() && comment_rows[index + 1] == end + 1 {
+            index += 1;
+            end += 1;
+        }
+        push_range(&mut ranges, start, end, Some(FoldingRangeKind::Comment));
+        index += 1;
+    }
+    ranges.sort_by_key(|range| (range.start_line, range.end_line));
+    ranges
+}
+
+fn push_range(
+    ranges: &mut Vec<FoldingRange>,
+    start: usize,
+    end: usize,
+    kind: Option<FoldingRangeKind>,
+) {
+    if end <= start {
+        return;
+    }
+    ranges.push(FoldingRange {
+        start_line: start as u32,
+        start_character: None,
+        end_line: end as u32,
+        end_character: None,
+        kind,
+        collapsed_text: None,
+    });
+}
+
+/// fold from the start of the node to the line before the end command, so the end
+/// command is still visible
+fn push_block(ranges: &mut Vec<FoldingRange>, start: Node, end: Node) {
+    push_range(
+        ranges,
+        start.start_position().row,
+        end.start_position().row.saturating_sub(1),
+        None,
+    );
+}
+
+fn collect_ranges(
+    input: Node,
+    source: &Vec<&str>,
+    ranges: &mut Vec<FoldingRange>,
+    comment_rows: &mut Vec<usize>,
+) {
+    match input.kind() {
+        "if_condition" => {
+            // every branch is folded separately
+            let mut course = input.walk();
+            let branches: Vec<Node> = input
+                .children(&mut course)
+                .filter(|child| {
+                    matches!(
+                        child.kind(),
+                        "if_command" | "elseif_command" | "else_command" | "endif_command"
+                    )
+                })
+                .collect();
+            for window in branches.windows(2) {
+                push_block(ranges, window[0], window[1]);
+            }
+        }
+        "foreach_loop" | "while_loop" | "function_def" | "macro_def" | "block_def" => {
+            if let Some(end) = input.child(input.child_count().saturating_sub(1)) {
+                push_block(ranges, input, end);
+            }
+        }
+        "normal_command" => {
+            // fold to the last argument, keep the line of ")" visible
+            if let Some(last_argument) = input
+                .child(2)
+                .filter(|child| child.kind() == "argument_list")
+                .and_then(|argument_list| {
+                    argument_list.named_child(argument_list.named_child_count().saturating_sub(1))
+                })
+            {
+                push_range(
+                    ranges,
+                    input.start_position().row,
+                    last_argument.end_position().row,
+                    None,
+                );
+            }
+        }
+        "bracket_comment" => {
+            push_range(
+                ranges,
+                input.start_position().row,
+                input.end_position().row,
+                Some(FoldingRangeKind::Comment),
+            );
+            return;
+        }
+        "line_comment" => {
+            let row = input.start_position().row;
+            let column = input.start_position().column;
+            // NOTE: the comment after the code is not a part of the comment block
+            if source
+                .get(row)
+                .and_then(|line| line.get(..column))
+                .is_some_and(|before| before.trim().is_empty())
+            {
+                comment_rows.push(row);
+            }
+            return;
+        }
+        _ => {}
+    }
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        collect_ranges(child, source, ranges, comment_rows);
+    }
+}
+
+#[test]
+fn tst_folding_range() {
+    use crate::document::Document;
+    let source = r#"# the first line
+# the second line
+set(SOURCES
+  a.cpp
+  b.cpp
+)
+if(WIN32)
+  message(a)
+  message(b)
+elseif(APPLE)
+  message(c)
+else()
+endif()
+function(foo)
+  #[[
+  bracket comment
+  ]]
+  message(d) # not folded
+  # single comment
+endfunction()
+"#;
+    let document = Document::new(source.to_string(), 0);
+    let ranges: Vec<(u32, u32, Option<FoldingRangeKind>)> =
+        get_folding_ranges(source, document.root_node())
+            .into_iter()
+            .map(|range| (range.start_line, range.end_line, range.kind))
+            .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            (0, 1, Some(FoldingRangeKind::Comment)),
+            (2, 4, None),
+            (6, 8, None),
+            (9, 10, None),
+            (13, 18, None),
+            (14, 16, Some(FoldingRangeKind::Comment)),
+        ]
+    );
+}
@@ -7,7 +7,11 @@ use tempfile::NamedTempFile;
 
 use crate::utils::{execute_command, get_range_content};
 
-pub fn format_range(content: &str, range: Range) -> Result<Option<Vec<TextEdit>>, String> {
+pub fn format_range(
+    content: &str,
+    range: Range,
+    program: &str,
+) -> Result<Option<Vec<TextEdit>>, String> {
     let source: Vec<&str> = content.split('\n').collect();
     let source = get_range_content(
         &source,
@@ -22,7 +26,7 @@ pub fn format_range(content: &str, range: Range) -> Result<Option<Vec<TextEdit>>
     let _ = named_temp_file.as_file().write(source.as_bytes()).unwrap();
     named_temp_file.as_file().flush().unwrap();
 
-    match execute_command("cmake-format", &[named_temp_file.path().to_str().unwrap()]) {
+    match execute_command(program, &[named_temp_file.path().to_str().unwrap()]) {
         Err(err) => Err(err.to_string()),
         Ok(result) => {
             let (code, out, err) = result;
@@ -41,12 +45,12 @@ pub fn format_range(content: &str, range: Range) -> Result<Option<Vec<TextEdit>>
     }
 }
 
-pub fn getformat(path: &Path) -> Result<Option<Vec<TextEdit>>, String> {
+pub fn getformat(path: &Path, program: &str) -> Result<Option<Vec<TextEdit>>, String> {
     if !path.exists() {
         return Err(format!("File {:?} does not exist", path));
     }
 
-    match execute_command("cmake-format", &[path.to_str().unwrap()]) {
+    match execute_command(program, &[path.to_str().unwrap()]) {
         Err(err) => Err(err.to_string()),
         Ok(result) => {
             let (code, out, err) = result;
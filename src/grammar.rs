@@ -1,19 +1,43 @@
 use async_lsp::lsp_types::DiagnosticSeverity;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 use tree_sitter::Point;
 
 use crate::consts::TREESITTER_CMAKE_LANGUAGE;
 use crate::utils::execute_command;
+
+/// the data attached to the diagnostic, code action use it to provide quick fixes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DiagnosticData {
+    PackageNotFound {
+        package: String,
+    },
+    IncludeNotFound {
+        argument: String,
+        include_path: String,
+    },
+    IncludeDirectory {
+        argument: String,
+        include_path: String,
+    },
+    EmptyArgument,
+}
+
+/// start, end, message, severity and the data for quick fixes
+pub type ErrorItem = (
+    tree_sitter::Point,
+    tree_sitter::Point,
+    String,
+    Option<DiagnosticSeverity>,
+    Option<DiagnosticData>,
+);
+
 /// checkerror the gammer error
 /// if there is error , it will return the position of the error
 pub struct ErrorInfo {
-    pub inner: Vec<(
-        tree_sitter::Point,
-        tree_sitter::Point,
-        String,
-        Option<DiagnosticSeverity>,
-    )>,
+    pub inner: Vec<ErrorItem>,
 }
 
 pub fn checkerror(
@@ -70,7 +94,7 @@ fn run_cmake_lint(path: &Path) -> Option<ErrorInfo> {
 
                     let start_point = Point { row, column };
                     let end_point = start_point;
-                    info.push((start_point, end_point, message, Some(severity)));
+                    info.push((start_point, end_point, message, Some(severity), None));
                 }
             }
 
@@ -97,6 +121,7 @@ fn checkerror_inner(
                 input.end_position(),
                 "Grammar error".to_string(),
                 None,
+                None,
             )],
         });
     }
@@ -118,13 +143,16 @@ fn checkerror_inner(
         let y = ids.end_position().column;
         let name = &newsource[h][x..y];
         if name.to_lowercase() == "find_package" && node.child_count() >= 4 {
-            let mut walk = node.walk();
-            let errorpackages = crate::filewatcher::get_error_packages();
-            for child in node.children(&mut walk) {
+            let Some(argumentlist) = node.child(2) else {
+                continue;
+            };
+            let mut walk = argumentlist.walk();
+            let errorpackages = crate::filewatcher::get_error_packages(local_path);
+            for child in argumentlist.children(&mut walk) {
                 let h = child.start_position().row;
                 let x = child.start_position().column;
                 let y = child.end_position().column;
-                if h < newsource.len() && y > x && y < newsource[h].len() {
+                if h < newsource.len() && y > x && y <= newsource[h].len() {
                     let name = &newsource[h][x..y];
                     if errorpackages.contains(&name.to_string()) {
                         output.push((
@@ -132,6 +160,35 @@ fn checkerror_inner(
                             child.end_position(),
                             "Cannot find such package".to_string(),
                             Some(DiagnosticSeverity::ERROR),
+                            Some(DiagnosticData::PackageNotFound {
+                                package: name.to_string(),
+                            }),
+                        ));
+                    }
+                }
+            }
+        }
+        if matches!(
+            name.to_lowercase().as_str(),
+            "find_library" | "find_path" | "find_program" | "find_file"
+        ) {
+            // NOTE: the result variable is the first argument, it is VAR-NOTFOUND in the cache
+            if let Some(variable) = node.child(2).and_then(|arguments| arguments.named_child(0)) {
+                let h = variable.start_position().row;
+                let x = variable.start_position().column;
+                let y = variable.end_position().column;
+                if h == variable.end_position().row && y > x && y <= newsource[h].len() {
+                    let variable_name = &newsource[h][x..y];
+                    if crate::filewatcher::get_not_found_variables(local_path)
+                        .iter()
+                        .any(|not_found| not_found == variable_name)
+                    {
+                        output.push((
+                            variable.start_position(),
+                            variable.end_position(),
+                            format!("{variable_name} is not found by {name}"),
+                            Some(DiagnosticSeverity::WARNING),
+                            None,
                         ));
                     }
                 }
@@ -161,21 +218,13 @@ fn checkerror_inner(
                         first_arg_node.end_position(),
                         "Argument is empty".to_string(),
                         Some(DiagnosticSeverity::ERROR),
+                        Some(DiagnosticData::EmptyArgument),
                     ));
                     continue;
                 }
                 if first_arg.contains('$') {
                     continue;
                 }
-                {
-                    let path = Path::new(&first_arg);
-                    let is_last_char_sep =
-                        std::path::is_separator(first_arg.chars().last().unwrap());
-                    if !is_last_char_sep && path.extension().is_none() {
-                        // first_arg could be a module
-                        continue;
-                    }
-                }
                 let include_path = if cfg!(windows) {
                     let path = local_path.parent().unwrap().join(&first_arg);
                     let path_str = path.to_str().unwrap();
@@ -188,6 +237,15 @@ fn checkerror_inner(
                 } else {
                     local_path.parent().unwrap().join(&first_arg)
                 };
+                {
+                    let path = Path::new(&first_arg);
+                    let is_last_char_sep =
+                        std::path::is_separator(first_arg.chars().last().unwrap());
+                    if !is_last_char_sep && path.extension().is_none() && !include_path.is_dir() {
+                        // first_arg could be a module
+                        continue;
+                    }
+                }
                 match include_path.try_exists() {
                     Ok(true) => {
                         if include_path.is_file() {
@@ -197,6 +255,7 @@ fn checkerror_inner(
                                     first_arg_node.end_position(),
                                     "Error in include file".to_string(),
                                     Some(DiagnosticSeverity::ERROR),
+                                    None,
                                 ));
                             }
                         } else {
@@ -205,6 +264,10 @@ fn checkerror_inner(
                                 first_arg_node.end_position(),
                                 format!("\"{}\" is a directory", include_path.to_str().unwrap()),
                                 Some(DiagnosticSeverity::ERROR),
+                                Some(DiagnosticData::IncludeDirectory {
+                                    argument: first_arg.clone(),
+                                    include_path: include_path.to_str().unwrap().to_string(),
+                                }),
                             ));
                         }
                     }
@@ -217,6 +280,10 @@ fn checkerror_inner(
                                 include_path.to_str().unwrap()
                             ),
                             Some(DiagnosticSeverity::WARNING),
+                            Some(DiagnosticData::IncludeNotFound {
+                                argument: first_arg.clone(),
+                                include_path: include_path.to_str().unwrap().to_string(),
+                            }),
                         ));
                     }
                 }
@@ -281,8 +348,46 @@ aa.cmake:57: [C0301] Line too long (145/80)"#;
                 println!("{row}:{column} -- {message}");
             }
             None => {
-                assert!(false);
+                unreachable!();
             }
         }
     }
 }
+
+#[test]
+fn tst_cache_not_found() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path().to_path_buf();
+    let cache = dir.join("CMakeCache.txt");
+    std::fs::write(
+        &cache,
+        "foo-bar_DIR:PATH=foo-bar_DIR-NOTFOUND\nZLIB_LIBRARY:FILEPATH=ZLIB_LIBRARY-NOTFOUND\n",
+    )
+    .unwrap();
+    let root = std::path::Path::new("/tmp/neocmakelsp_grammar_project");
+    crate::filewatcher::refresh_cache(root, &cache);
+    let source =
+        "find_package(foo-bar)\nfind_library(ZLIB_LIBRARY z)\nfind_path(ZLIB_INCLUDE zlib.h)\n";
+    let mut parse = tree_sitter::Parser::new();
+    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
+    let thetree = parse.parse(source, None).unwrap();
+    let errors = checkerror_inner(
+        &root.join("CMakeLists.txt"),
+        &source.lines().collect(),
+        thetree.root_node(),
+    )
+    .unwrap();
+    let messages: Vec<(usize, String)> = errors
+        .inner
+        .into_iter()
+        .map(|(start, _, message, _, _)| (start.row, message))
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            (0, "Cannot find such package".to_string()),
+            (1, "ZLIB_LIBRARY is not found by find_library".to_string()),
+        ]
+    );
+    crate::filewatcher::clear_cache(root);
+}
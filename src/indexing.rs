@@ -0,0 +1,80 @@
+/// build the indexes of cmake in the background after initialized, like the builtin commands
+/// from `cmake --help-*` and the packages found in the system. the requests which come before
+/// they are ready return partial results instead of waiting for them
+use async_lsp::ClientSocket;
+use futures::executor::block_on;
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::complete;
+use crate::progress::WorkDone;
+use crate::utils;
+use crate::utils::treehelper::MESSAGE_STORAGE;
+
+/// whether the indexes are being built in the background
+static BACKGROUND: AtomicBool = AtomicBool::new(false);
+
+/// whether the background indexing is cancelled and the rest indexes are not resumed yet
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// the value of the index, None if it is still being built in the background. without the
+/// background indexing, it is built here
+pub fn get_indexed<T, F: FnOnce() -> T>(lazy: &'static Lazy<T, F>) -> Option<&'static T> {
+    if !BACKGROUND.load(Ordering::Relaxed) {
+        return Some(Lazy::force(lazy));
+    }
+    let value = Lazy::get(lazy);
+    if value.is_none() && CANCELLED.swap(false, Ordering::Relaxed) {
+        // NOTE: the cancelled indexes are finished on a blocking thread when they are used, the
+        // main loop never waits for them
+        std::thread::spawn(|| {
+            index_builtin(&|| false);
+            index_packages(&|| false);
+        });
+    }
+    value
+}
+
+/// every phase returns false when it is cancelled, the cancel is checked between its steps
+fn index_builtin(is_cancelled: &dyn Fn() -> bool) -> bool {
+    Lazy::force(&MESSAGE_STORAGE);
+    complete::index_builtin(is_cancelled)
+}
+
+fn index_packages(is_cancelled: &dyn Fn() -> bool) -> bool {
+    Lazy::force(&utils::CMAKE_PACKAGES_WITHKEY);
+    if is_cancelled() {
+        return false;
+    }
+    #[cfg(unix)]
+    Lazy::force(&utils::packagepkgconfig::PKG_CONFIG_PACKAGES_WITHKEY);
+    complete::index_packages(is_cancelled)
+}
+
+pub fn schedule_indexing(client: ClientSocket) -> tokio::task::JoinHandle<()> {
+    BACKGROUND.store(true, Ordering::Relaxed);
+    tokio::task::spawn_blocking(move || {
+        block_on(async {
+            let progress = WorkDone::begin(&client, "Indexing CMake").await;
+            type Phase = fn(&dyn Fn() -> bool) -> bool;
+            let phases: [(&str, Phase); 2] = [
+                ("builtin commands", index_builtin),
+                ("packages", index_packages),
+            ];
+            let is_cancelled = || progress.is_cancelled();
+            for (index, (name, index_phase)) in phases.iter().enumerate() {
+                progress.report(
+                    format!("indexing {name}"),
+                    Some((index * 100 / phases.len()) as u32),
+                );
+                if is_cancelled() || !index_phase(&is_cancelled) {
+                    // NOTE: the rest indexes are built when they are used
+                    CANCELLED.store(true, Ordering::Relaxed);
+                    progress.end("indexing cancelled".to_string());
+                    return;
+                }
+            }
+            progress.end("indexing finished".to_string());
+        });
+    })
+}
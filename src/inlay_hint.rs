@@ -0,0 +1,336 @@
+/// provide inlayHint, show the values in CMakeCache.txt for options and cache variables,
+/// the resolved CMAKE_*_DIR and the parameter names of the user defined commands
+use async_lsp::lsp_types;
+use lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, Position, Range};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tree_sitter::Node;
+
+use crate::document::Document;
+use crate::filewatcher::CacheEntry;
+use crate::signature_help::get_arguments;
+use crate::utils::get_node_content;
+use crate::utils::treehelper::point_to_position;
+
+/// the lowercase names of the user defined commands and their parameters
+pub type Functions = HashMap<String, Vec<String>>;
+
+type FunctionsKV = HashMap<PathBuf, (u64, Arc<Functions>)>;
+
+/// NOTE: the user defined commands of every file and the hash of its text
+static USER_FUNCTIONS: Lazy<Mutex<FunctionsKV>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// the data needed by the hints
+pub struct HintContext<'a> {
+    /// the entries of CMakeCache.txt
+    pub cache: &'a HashMap<String, CacheEntry>,
+    /// the lowercase names of the user defined commands and their parameters
+    pub functions: &'a HashMap<String, Vec<String>>,
+    /// the values of CMAKE_*_DIR
+    pub dirs: HashMap<String, String>,
+}
+
+impl<'a> HintContext<'a> {
+    pub fn new(
+        local_path: &Path,
+        cache: &'a HashMap<String, CacheEntry>,
+        functions: &'a HashMap<String, Vec<String>>,
+    ) -> Self {
+        Self {
+            cache,
+            functions,
+            dirs: get_dir_values(local_path, cache),
+        }
+    }
+}
+
+/// get the values of CMAKE_*_DIR from the path of the file and the cache
+pub fn get_dir_values(
+    local_path: &Path,
+    cache: &HashMap<String, CacheEntry>,
+) -> HashMap<String, String> {
+    let mut dirs = HashMap::new();
+    let current_dir = local_path.parent();
+    if let Some(current_dir) = current_dir {
+        let current_dir = current_dir.display().to_string();
+        dirs.insert("CMAKE_CURRENT_SOURCE_DIR".to_string(), current_dir.clone());
+        dirs.insert("CMAKE_CURRENT_LIST_DIR".to_string(), current_dir);
+    }
+    let source_dir = cache.get("CMAKE_HOME_DIRECTORY").map(|entry| &entry.value);
+    let binary_dir = cache.get("CMAKE_CACHEFILE_DIR").map(|entry| &entry.value);
+    if let Some(source_dir) = source_dir {
+        dirs.insert("CMAKE_SOURCE_DIR".to_string(), source_dir.clone());
+    }
+    if let Some(binary_dir) = binary_dir {
+        dirs.insert("CMAKE_BINARY_DIR".to_string(), binary_dir.clone());
+    }
+    if let (Some(source_dir), Some(binary_dir), Some(current_dir)) =
+        (source_dir, binary_dir, current_dir)
+    {
+        if let Ok(relative) = current_dir.strip_prefix(source_dir) {
+            dirs.insert(
+                "CMAKE_CURRENT_BINARY_DIR".to_string(),
+                Path::new(binary_dir).join(relative).display().to_string(),
+            );
+        }
+    }
+    for (key, entry) in cache {
+        if key.starts_with("CMAKE_") && key.ends_with("_DIR") && !entry.value.is_empty() {
+            dirs.entry(key.clone())
+                .or_insert_with(|| entry.value.clone());
+        }
+    }
+    dirs
+}
+
+/// the user defined commands of the file, they are collected again only when the text is changed
+pub fn get_user_functions(path: &Path, document: &Document) -> Arc<Functions> {
+    let hash = document.text_hash();
+    let mut cache = USER_FUNCTIONS.lock().unwrap();
+    if let Some((cached, functions)) = cache.get(path) {
+        if *cached == hash {
+            return functions.clone();
+        }
+    }
+    let mut functions = HashMap::new();
+    collect_user_functions(
+        document.root_node(),
+        &document.text.lines().collect(),
+        &mut functions,
+    );
+    let functions = Arc::new(functions);
+    cache.insert(path.to_path_buf(), (hash, functions.clone()));
+    functions
+}
+
+/// collect the parameters of the functions and macros
+fn collect_user_functions(
+    input: Node,
+    source: &Vec<&str>,
+    output: &mut HashMap<String, Vec<String>>,
+) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        match child.kind() {
+            "function_def" | "macro_def" => {
+                let arguments = child
+                    .child(0)
+                    .and_then(|command| command.child(2))
+                    .map(|argument_list| get_arguments(argument_list, source))
+                    .unwrap_or_default();
+                if let Some((name, parameters)) = arguments.split_first() {
+                    output
+                        .entry(name.to_lowercase())
+                        .or_insert_with(|| parameters.to_vec());
+                }
+            }
+            "normal_command" | "line_comment" | "bracket_comment" => {}
+            _ => collect_user_functions(child, source, output),
+        }
+    }
+}
+
+pub fn get_inlay_hints(
+    source: &str,
+    root: Node,
+    range: Range,
+    context: &HintContext,
+) -> Vec<InlayHint> {
+    let newsource: Vec<&str> = source.lines().collect();
+    let mut hints = Vec::new();
+    collect_hints(root, &newsource, range, context, &mut hints);
+    hints
+}
+
+fn new_hint(
+    position: Position,
+    label: String,
+    kind: Option<InlayHintKind>,
+    padding_left: bool,
+    padding_right: bool,
+) -> InlayHint {
+    InlayHint {
+        position,
+        label: InlayHintLabel::String(label),
+        kind,
+        text_edits: None,
+        tooltip: None,
+        padding_left: Some(padding_left),
+        padding_right: Some(padding_right),
+        data: None,
+    }
+}
+
+fn collect_hints(
+    input: Node,
+    source: &Vec<&str>,
+    range: Range,
+    context: &HintContext,
+    hints: &mut Vec<InlayHint>,
+) {
+    if input.start_position().row > range.end.line as usize
+        || input.end_position().row < range.start.line as usize
+    {
+        return;
+    }
+    match input.kind() {
+        "normal_command" => add_command_hints(input, source, context, hints),
+        "variable_ref" => {
+            let variable = input
+                .child(0)
+                .filter(|var| var.kind() == "normal_var")
+                .and_then(|var| var.named_child(0))
+                .filter(|variable| variable.kind() == "variable");
+            if let Some(value) = variable
+                .map(|variable| get_node_content(source, &variable))
+                .filter(|name| name.starts_with("CMAKE_") && name.ends_with("_DIR"))
+                .and_then(|name| context.dirs.get(&name))
+            {
+                hints.push(new_hint(
+                    point_to_position(input.end_position()),
+                    format!("= {value}"),
+                    None,
+                    true,
+                    true,
+                ));
+            }
+            return;
+        }
+        _ => {}
+    }
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        collect_hints(child, source, range, context, hints);
+    }
+}
+
+fn add_command_hints(
+    command: Node,
+    source: &Vec<&str>,
+    context: &HintContext,
+    hints: &mut Vec<InlayHint>,
+) {
+    let Some(identifier) = command.child(0) else {
+        return;
+    };
+    let Some(argument_list) = command
+        .child(2)
+        .filter(|child| child.kind() == "argument_list")
+    else {
+        return;
+    };
+    let name = get_node_content(source, &identifier).to_lowercase();
+    let arguments = get_arguments(argument_list, source);
+    match name.as_str() {
+        "option" | "set" => {
+            if name == "set" && !arguments.iter().any(|argument| argument == "CACHE") {
+                return;
+            }
+            if let Some(entry) = arguments.first().and_then(|key| context.cache.get(key)) {
+                let value = if entry.value.is_empty() {
+                    "\"\"".to_string()
+                } else {
+                    entry.value.clone()
+                };
+                hints.push(new_hint(
+                    point_to_position(command.end_position()),
+                    format!("= {value}"),
+                    None,
+                    true,
+                    false,
+                ));
+            }
+        }
+        _ => {
+            let Some(parameters) = context.functions.get(&name) else {
+                return;
+            };
+            let mut course = argument_list.walk();
+            let nodes = argument_list
+                .named_children(&mut course)
+                .filter(|child| child.kind() == "argument");
+            for ((node, argument), parameter) in nodes.zip(arguments.iter()).zip(parameters) {
+                let argument = argument
+                    .strip_prefix("${")
+                    .and_then(|argument| argument.strip_suffix('}'))
+                    .unwrap_or(argument);
+                if argument.eq_ignore_ascii_case(parameter) {
+                    continue;
+                }
+                hints.push(new_hint(
+                    point_to_position(node.start_position()),
+                    format!("{parameter}:"),
+                    Some(InlayHintKind::PARAMETER),
+                    false,
+                    true,
+                ));
+            }
+        }
+    }
+}
+
+#[test]
+fn tst_inlay_hints() {
+    use crate::document::Document;
+    let source = r#"option(BUILD_TESTING "" OFF)
+set(FOO_PATH "" CACHE PATH "foo")
+set(NOT_CACHE "")
+function(add_foo name target)
+endfunction()
+add_foo(bar ${target} baz)
+include(${CMAKE_CURRENT_LIST_DIR}/a.cmake)
+message(${CMAKE_BINARY_DIR})
+"#;
+    let cache = crate::filewatcher::parse_cache(
+        r#"BUILD_TESTING:BOOL=ON
+FOO_PATH:PATH=
+NOT_CACHE:STRING=a
+CMAKE_HOME_DIRECTORY:INTERNAL=/tmp/project
+CMAKE_CACHEFILE_DIR:INTERNAL=/tmp/project/build
+"#,
+    );
+    let document = Document::new(source.to_string(), 0);
+    let mut functions = HashMap::new();
+    collect_user_functions(
+        document.root_node(),
+        &source.lines().collect(),
+        &mut functions,
+    );
+    let context = HintContext::new(
+        Path::new("/tmp/project/sub/CMakeLists.txt"),
+        &cache,
+        &functions,
+    );
+    let range = Range {
+        start: Position::new(0, 0),
+        end: Position::new(8, 0),
+    };
+    let hints: Vec<(u32, u32, String)> =
+        get_inlay_hints(source, document.root_node(), range, &context)
+            .into_iter()
+            .map(|hint| {
+                let InlayHintLabel::String(label) = hint.label else {
+                    unreachable!();
+                };
+                (hint.position.line, hint.position.character, label)
+            })
+            .collect();
+    assert_eq!(
+        hints,
+        vec![
+            (0, 28, "= ON".to_string()),
+            (1, 33, "= \"\"".to_string()),
+            (5, 8, "name:".to_string()),
+            (6, 33, "= /tmp/project/sub".to_string()),
+            (7, 27, "= /tmp/project/build".to_string()),
+        ]
+    );
+
+    // the functions are collected once for the same text
+    let path = Path::new("/tmp/project/sub/CMakeLists.txt");
+    let cached = get_user_functions(path, &document);
+    assert_eq!(*cached, functions);
+    assert!(Arc::ptr_eq(&cached, &get_user_functions(path, &document)));
+}
@@ -1,13 +1,12 @@
 /// provide go to definition
-use crate::{
-    consts::TREESITTER_CMAKE_LANGUAGE,
-    utils::treehelper::{get_position_string, point_to_position},
-};
+use crate::fileapi;
+use crate::utils::treehelper::{get_position_string, point_to_position};
 use async_lsp::lsp_types;
 use lsp_types::{Position, Range, Url};
+use std::path::Path;
 use tree_sitter::Node;
 mod findpackage;
-mod include;
+pub mod include;
 mod subdirectory;
 use crate::utils::treehelper::{get_pos_type, PositionType};
 use lsp_types::Location;
@@ -18,25 +17,36 @@ const JUMP_FILITER_KIND: &[&str] = &["identifier", "unquoted_argument"];
 pub async fn godef(
     location: Position,
     source: &str,
+    root: Node<'_>,
     originuri: String,
     client: &async_lsp::ClientSocket,
     is_jump: bool,
 ) -> Option<Vec<Location>> {
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(source, None);
-    let tree = thetree.unwrap();
-    let positionstring = get_position_string(location, tree.root_node(), source);
+    let positionstring = get_position_string(location, root, source);
     match positionstring {
         Some(tofind) => {
             if &tofind != "(" && &tofind != ")" {
-                let jumptype =
-                    get_pos_type(location, tree.root_node(), source, PositionType::Variable);
+                let jumptype = get_pos_type(location, root, source, PositionType::Variable);
+                // NOTE: the targets known by the file api are the real ones, even the ones
+                // generated by macros
+                if is_jump
+                    && matches!(
+                        jumptype,
+                        PositionType::Variable
+                            | PositionType::TargetLink
+                            | PositionType::TargetInclude
+                    )
+                {
+                    if let Some(locations) = fileapi::get_definition(Path::new(&originuri), &tofind)
+                    {
+                        return Some(locations);
+                    }
+                }
                 match jumptype {
                     // TODO: maybe can hadle Include?
                     PositionType::Variable => {
                         let newsource: Vec<&str> = source.lines().collect();
-                        godefsub(tree.root_node(), &newsource, &tofind, originuri, is_jump)
+                        godefsub(root, &newsource, &tofind, originuri, is_jump)
                     }
                     PositionType::FindPackage
                     | PositionType::TargetLink
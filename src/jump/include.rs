@@ -2,10 +2,28 @@ use super::Location;
 use async_lsp::lsp_types;
 use lsp_types::Url;
 use std::path::PathBuf;
-fn ismodule(tojump: &str) -> bool {
+pub fn ismodule(tojump: &str) -> bool {
     tojump.split('.').count() == 1
 }
 
+/// find the builtin module file
+pub fn find_module(subpath: &str) -> Option<PathBuf> {
+    #[cfg(unix)]
+    let glob_pattern = format!("/usr/share/cmake*/Modules/{subpath}.cmake");
+    #[cfg(not(unix))]
+    let glob_pattern = {
+        let Ok(prefix) = std::env::var("CMAKE_PREFIX_PATH") else {
+            return None;
+        };
+        format!("{prefix}/cmake*/Modules/{subpath}.cmake")
+    };
+    glob::glob(glob_pattern.as_str())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .next()
+}
+
 pub(super) async fn cmpinclude(
     localpath: String,
     subpath: &str,
@@ -16,20 +34,7 @@ pub(super) async fn cmpinclude(
         let root_dir = path.parent().unwrap();
         root_dir.join(subpath)
     } else {
-        #[cfg(unix)]
-        let glob_pattern = format!("/usr/share/cmake*/Modules/{subpath}.cmake");
-        #[cfg(not(unix))]
-        let glob_pattern = {
-            let Ok(prefix) = std::env::var("CMAKE_PREFIX_PATH") else {
-                return None;
-            };
-            format!("{prefix}/cmake*/Modules/{subpath}.cmake")
-        };
-        glob::glob(glob_pattern.as_str())
-            .into_iter()
-            .flatten()
-            .flatten()
-            .next()?
+        find_module(subpath)?
     };
 
     if target.exists() {
@@ -1,19 +1,39 @@
-mod config;
+pub mod config;
 
-use self::config::Config;
+use self::config::{Config, CONFIG_SECTION};
 
-use super::Backend;
+use super::{Backend, ProjectFolder};
 use crate::ast;
+use crate::build_dir;
+use crate::call_hierarchy;
+use crate::code_action;
+use crate::code_lens;
+use crate::command;
 use crate::complete;
-use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+use crate::configure::{self, ConfigureFinished};
+use crate::diagnostic;
+use crate::document::{get_or_read, Document};
+use crate::document_highlight;
+use crate::document_link;
+use crate::fileapi;
 use crate::filewatcher;
+use crate::folding_range;
 use crate::formatting::format_range;
 use crate::formatting::getformat;
-use crate::grammar::checkerror;
+use crate::indexing;
+use crate::inlay_hint;
 use crate::jump;
+use crate::linked_editing;
+use crate::presets;
+use crate::progress;
+use crate::rename;
 use crate::scansubs;
 use crate::scansubs::schedule_scan_all;
+use crate::selection_range;
+use crate::semantic_token;
+use crate::signature_help;
 use crate::utils::treehelper;
+use crate::workspace_symbol;
 use async_lsp::lsp_types;
 use async_lsp::lsp_types::*;
 use async_lsp::ErrorCode;
@@ -26,11 +46,10 @@ use lsp_types::{
 
 use futures::future::BoxFuture;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::RwLock;
 use tokio::sync::Mutex;
-use tree_sitter::Parser;
 
 use std::ops::ControlFlow;
 
@@ -38,9 +57,33 @@ use once_cell::sync::Lazy;
 
 use async_lsp::{LanguageClient, LanguageServer, ResponseError};
 
-pub static BUFFERS_CACHE: Lazy<Arc<Mutex<HashMap<lsp_types::Url, String>>>> =
+/// the settings pulled by workspace/configuration, the root of the folder is None for the
+/// ones without scope
+pub struct ConfigurationChanged(Vec<(Option<PathBuf>, Config)>);
+
+pub static BUFFERS_CACHE: Lazy<Arc<Mutex<HashMap<lsp_types::Url, Document>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+/// NOTE: the presets files are json, they are kept out of BUFFERS_CACHE so the features of cmake
+/// never see them
+static PRESETS_BUFFERS: Lazy<Arc<Mutex<HashMap<lsp_types::Url, Document>>>> =
     Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
+/// the path of the file on the disk, the percent-encoded characters are decoded. the uris which
+/// are not files keep their path
+fn uri_path(uri: &Url) -> PathBuf {
+    uri.to_file_path()
+        .unwrap_or_else(|_| PathBuf::from(uri.path()))
+}
+
+fn get_buffers(uri: &Url) -> &'static Mutex<HashMap<lsp_types::Url, Document>> {
+    if presets::is_presets_file(&uri_path(uri)) {
+        &PRESETS_BUFFERS
+    } else {
+        &BUFFERS_CACHE
+    }
+}
+
 static CLIENT_CAPABILITIES: RwLock<Option<TextDocumentClientCapabilities>> = RwLock::new(None);
 
 fn set_client_text_document(text_document: Option<TextDocumentClientCapabilities>) {
@@ -53,6 +96,10 @@ pub fn get_client_capabilities() -> Option<TextDocumentClientCapabilities> {
     data.clone()
 }
 
+pub fn client_support_pull_diagnostics() -> bool {
+    get_client_capabilities().is_some_and(|c| c.diagnostic.is_some())
+}
+
 pub fn client_support_snippet() -> bool {
     match get_client_capabilities() {
         Some(c) => c
@@ -65,67 +112,431 @@ pub fn client_support_snippet() -> bool {
 }
 
 impl Backend {
-    async fn publish_diagnostics(&mut self, uri: Url, context: String, use_cmake_lint: bool) {
-        let mut parse = Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let thetree = parse.parse(&context, None);
-        let Some(tree) = thetree else {
+    async fn publish_diagnostics(&mut self, uri: Url, document: &Document) {
+        // NOTE: the client pulls the diagnostics by itself
+        if client_support_pull_diagnostics() {
+            return;
+        }
+        let path = &uri_path(&uri);
+        let diagnostics =
+            diagnostic::get_diagnostics(path, document, self.get_config(path).is_lint_enabled());
+        self.client
+            .publish_diagnostics(PublishDiagnosticsParams {
+                uri,
+                diagnostics,
+                version: Some(document.version),
+            })
+            .unwrap();
+    }
+
+    /// the workspace folder which contains the file, the nested one is preferred
+    fn get_folder(&self, path: &Path) -> Option<&ProjectFolder> {
+        self.get_folder_index(path)
+            .map(|index| &self.folders[index])
+    }
+
+    fn get_folder_index(&self, path: &Path) -> Option<usize> {
+        self.folders
+            .iter()
+            .enumerate()
+            .filter(|(_, folder)| path.starts_with(&folder.root))
+            .max_by_key(|(_, folder)| folder.root.components().count())
+            .map(|(index, _)| index)
+    }
+
+    /// the settings of the workspace folder which contains the file
+    fn get_config(&self, path: &Path) -> &Config {
+        self.get_folder(path)
+            .map(|folder| &folder.config)
+            .unwrap_or(&self.config)
+    }
+
+    /// the files of the workspace folder which contains the file
+    fn get_project_files(&self, path: &Path) -> Vec<PathBuf> {
+        let root = self.get_folder(path).map(|folder| folder.root.as_path());
+        block_on(scansubs::get_cached_project_files(root))
+    }
+
+    /// the file itself, its included files and the files of the project
+    fn get_related_files(&self, uri: &Url) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        if let Ok(path) = uri.to_file_path() {
+            // NOTE: the includes of the opened buffer may be not saved
+            let mut includes = match block_on(BUFFERS_CACHE.lock()).get(uri) {
+                Some(document) => scansubs::get_include_files(&path, document),
+                None => scansubs::scan_include_files(&path),
+            };
+            files.append(&mut includes);
+            files.append(&mut self.get_project_files(&path));
+            files.push(path);
+        }
+        files
+    }
+
+    /// NOTE: the folders added by initialize are scanned in initialized, the progress can not be
+    /// created before it
+    fn add_folder(&mut self, root: PathBuf, scan: bool) {
+        if self.folders.iter().any(|folder| folder.root == root) {
             return;
+        }
+        let mut folder = ProjectFolder {
+            scan_handle: scan
+                .then(|| schedule_scan_all(root.display().to_string(), self.client.clone())),
+            discovered_build_dir: PathBuf::new(),
+            selected_build_dir: None,
+            selected_preset: None,
+            config: self.config.clone(),
+            root,
         };
-        let gammererror = checkerror(
-            Path::new(uri.path()),
-            &context,
-            tree.root_node(),
-            use_cmake_lint,
-        );
-        if let Some(diagnoses) = gammererror {
-            let mut pusheddiagnoses = vec![];
-            for (start, end, message, severity) in diagnoses.inner {
-                let pointx = lsp_types::Position::new(start.row as u32, start.column as u32);
-                let pointy = lsp_types::Position::new(end.row as u32, end.column as u32);
-                let range = Range {
-                    start: pointx,
-                    end: pointy,
-                };
-                let diagnose = Diagnostic {
-                    range,
-                    severity,
-                    code: None,
-                    code_description: None,
-                    source: None,
-                    message,
-                    related_information: None,
-                    tags: None,
-                    data: None,
-                };
-                pusheddiagnoses.push(diagnose);
+        folder.discovered_build_dir = folder.discover_build_dir();
+        // NOTE: read the cache even if the client cannot watch it, it is used by inlay hints
+        folder.load_build_cache();
+        self.folders.push(folder);
+    }
+
+    fn remove_folder(&mut self, root: &Path) {
+        let Some(index) = self.folders.iter().position(|folder| folder.root == root) else {
+            return;
+        };
+        let folder = self.folders.remove(index);
+        if let Some(handle) = folder.scan_handle {
+            handle.cancel();
+        }
+        block_on(scansubs::remove_folder(&folder.root));
+        let files = configure::clear(&folder.root);
+        self.update_file_diagnostics(files);
+        filewatcher::clear_cache(&folder.root);
+        fileapi::clear_targets(&folder.root);
+        // NOTE: the nested folders are removed from the tree with it
+        for index in 0..self.folders.len() {
+            if self.folders[index].root.starts_with(root) {
+                self.rescan_folder(index);
             }
-            self.client
-                .publish_diagnostics(PublishDiagnosticsParams {
-                    uri,
-                    diagnostics: pusheddiagnoses,
-                    version: Some(1),
-                })
-                .unwrap();
+        }
+    }
+
+    /// find the build directory again, the selected one is kept
+    fn rediscover_build_dir(&mut self, index: usize) {
+        let folder = &mut self.folders[index];
+        let build_dir = folder.discover_build_dir();
+        if build_dir == folder.discovered_build_dir {
+            return;
+        }
+        folder.discovered_build_dir = build_dir;
+        if folder.selected_build_dir.is_none() {
+            folder.load_build_cache();
+        }
+    }
+
+    /// select the build directory of the folder, the discovered one is used again with an
+    /// empty path. without the path, the configured build directories are returned
+    fn select_build_dir(&mut self, index: usize, dir: Option<&str>) -> serde_json::Value {
+        let folder = &mut self.folders[index];
+        let Some(dir) = dir else {
+            let candidates = build_dir::get_candidates(&folder.root, &folder.config);
+            return candidates
+                .iter()
+                .map(|dir| serde_json::Value::from(dir.display().to_string()))
+                .collect();
+        };
+        folder.selected_build_dir = if dir.is_empty() {
+            None
+        } else {
+            Some(folder.root.join(dir))
+        };
+        folder.load_build_cache();
+        let build_dir = folder.build_dir();
+        self.update_diagnostics();
+        self.refresh_inlay_hints();
+        self.client
+            .log_message(LogMessageParams {
+                typ: MessageType::INFO,
+                message: format!("build directory changed to {}", build_dir.display()),
+            })
+            .unwrap();
+        build_dir.display().to_string().into()
+    }
+
+    /// select the configure preset of the folder, its binaryDir becomes the build directory and
+    /// the one in the settings is used again with an empty name. without the name, the visible
+    /// configure presets are returned
+    fn select_preset(
+        &mut self,
+        index: usize,
+        name: Option<&str>,
+    ) -> Result<serde_json::Value, ResponseError> {
+        let folder = &mut self.folders[index];
+        let configure_presets = presets::load_configure_presets(&folder.root);
+        let Some(name) = name else {
+            return Ok(configure_presets
+                .iter()
+                .filter(|preset| !preset.hidden)
+                .map(|preset| serde_json::Value::from(preset.name.clone()))
+                .collect());
+        };
+        if !name.is_empty() && !configure_presets.iter().any(|preset| preset.name == name) {
+            return Err(ResponseError::new(
+                ErrorCode::INVALID_PARAMS,
+                format!("unknown configure preset {name}"),
+            ));
+        }
+        folder.selected_preset = (!name.is_empty()).then(|| name.to_string());
+        // NOTE: the build directory follows the preset
+        folder.selected_build_dir = None;
+        folder.discovered_build_dir = folder.discover_build_dir();
+        folder.load_build_cache();
+        let build_dir = folder.build_dir();
+        self.update_diagnostics();
+        self.refresh_inlay_hints();
+        self.client
+            .log_message(LogMessageParams {
+                typ: MessageType::INFO,
+                message: format!(
+                    "preset changed to {name}, the build directory is {}",
+                    build_dir.display()
+                ),
+            })
+            .unwrap();
+        Ok(build_dir.display().to_string().into())
+    }
+
+    fn refresh_inlay_hints(&self) {
+        if !self.support_inlay_hint_refresh {
+            return;
+        }
+        let mut client = self.client.clone();
+        tokio::spawn(async move {
+            let _ = client.inlay_hint_refresh(()).await;
+        });
+    }
+
+    fn rescan_folder(&mut self, index: usize) {
+        let folder = &mut self.folders[index];
+        if let Some(handle) = folder.scan_handle.as_ref() {
+            handle.cancel();
+        }
+        folder.scan_handle.replace(schedule_scan_all(
+            folder.root.display().to_string(),
+            self.client.clone(),
+        ));
+    }
+
+    /// request the settings by workspace/configuration, the one without scope is used for
+    /// the files out of the workspace folders. they are applied when the response is received
+    fn pull_configuration(&self) {
+        if !self.support_configuration {
+            return;
+        }
+        let roots: Vec<PathBuf> = self
+            .folders
+            .iter()
+            .map(|folder| folder.root.clone())
+            .collect();
+        let mut items = vec![ConfigurationItem {
+            scope_uri: None,
+            section: Some(CONFIG_SECTION.to_string()),
+        }];
+        items.extend(roots.iter().map(|root| ConfigurationItem {
+            scope_uri: Url::from_directory_path(root).ok(),
+            section: Some(CONFIG_SECTION.to_string()),
+        }));
+        let mut client = self.client.clone();
+        tokio::spawn(async move {
+            let Ok(settings) = client.configuration(ConfigurationParams { items }).await else {
+                return;
+            };
+            let scopes = std::iter::once(None).chain(roots.into_iter().map(Some));
+            let configs = scopes
+                .zip(settings)
+                .filter_map(|(root, settings)| Config::from_settings(settings).map(|c| (root, c)))
+                .collect();
+            let _ = client.emit(ConfigurationChanged(configs));
+        });
+    }
+
+    /// apply the new settings of the folders, the diagnostics are published again with them
+    fn apply_config(&mut self, configs: Vec<(Option<PathBuf>, Config)>) {
+        let mut changed = false;
+        for (root, config) in configs {
+            let folder = match root {
+                Some(root) => {
+                    let Some(folder) = self.folders.iter_mut().find(|folder| folder.root == root)
+                    else {
+                        continue;
+                    };
+                    Some(folder)
+                }
+                None => None,
+            };
+            let current = match &folder {
+                Some(folder) => &folder.config,
+                None => &self.config,
+            };
+            if config == *current {
+                continue;
+            }
+            changed = true;
+            if current.is_scan_cmake_in_package() != config.is_scan_cmake_in_package() {
+                block_on(complete::COMPLETE_CACHE.lock()).clear();
+            }
+            match folder {
+                Some(folder) => {
+                    let old = std::mem::replace(&mut folder.config, config);
+                    if old.is_configure_on_save() && !folder.config.is_configure_on_save() {
+                        // NOTE: the results are published again by update_diagnostics below
+                        configure::clear(&folder.root);
+                    }
+                    if old.build_dir != folder.config.build_dir
+                        || old.preset != folder.config.preset
+                    {
+                        folder.discovered_build_dir = folder.discover_build_dir();
+                        folder.load_build_cache();
+                    }
+                }
+                None => self.config = config,
+            }
+        }
+        if !changed {
+            return;
+        }
+        self.update_diagnostics();
+        self.client
+            .log_message(LogMessageParams {
+                typ: MessageType::INFO,
+                message: "configuration changed".into(),
+            })
+            .unwrap();
+    }
+
+    pub fn on_configure_finished(
+        &mut self,
+        ConfigureFinished(files): ConfigureFinished,
+    ) -> ControlFlow<async_lsp::Result<()>> {
+        self.update_file_diagnostics(files);
+        ControlFlow::Continue(())
+    }
+
+    /// configure the folder of the saved file in the background if it is enabled, the build
+    /// directory is only touched without the scratch setting
+    fn schedule_configure(&self, path: &Path) {
+        let Some(folder) = self.get_folder(path) else {
+            return;
+        };
+        if !folder.config.is_configure_on_save() {
+            return;
+        }
+        let in_scratch = folder.config.is_configure_in_scratch();
+        let build_dir = if in_scratch {
+            configure::scratch_dir(&folder.root)
         } else {
+            folder.build_dir()
+        };
+        configure::schedule_configure(
+            self.client.clone(),
+            folder.root.clone(),
+            build_dir,
+            in_scratch,
+            folder.config.configure_debounce(),
+        );
+    }
+
+    /// publish the diagnostics of the files again, the ones which are not opened are read from
+    /// disk and published without version
+    fn update_file_diagnostics(&mut self, files: Vec<PathBuf>) {
+        if files.is_empty() {
+            return;
+        }
+        if client_support_pull_diagnostics() {
+            self.update_diagnostics();
+            return;
+        }
+        diagnostic::invalidate_results();
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        for path in files {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if let Some(document) = storemap.get(&uri) {
+                block_on(self.publish_diagnostics(uri, document));
+                continue;
+            }
+            let Some(document) = get_or_read(&storemap, &path) else {
+                continue;
+            };
+            let lint = self.get_config(&path).is_lint_enabled();
             self.client
                 .publish_diagnostics(PublishDiagnosticsParams {
                     uri,
-                    diagnostics: vec![],
+                    diagnostics: diagnostic::get_diagnostics(&path, &document, lint),
                     version: None,
                 })
                 .unwrap();
         }
     }
 
+    pub fn on_configuration_changed(
+        &mut self,
+        ConfigurationChanged(configs): ConfigurationChanged,
+    ) -> ControlFlow<async_lsp::Result<()>> {
+        self.apply_config(configs);
+        ControlFlow::Continue(())
+    }
+
     fn update_diagnostics(&mut self) {
+        diagnostic::invalidate_results();
+        if client_support_pull_diagnostics() {
+            if self.support_diagnostic_refresh {
+                let mut client = self.client.clone();
+                tokio::spawn(async move {
+                    let _ = client.workspace_diagnostic_refresh(()).await;
+                });
+            }
+            return;
+        }
         let storemap = block_on(BUFFERS_CACHE.lock());
-        for (uri, context) in storemap.iter() {
-            block_on(self.publish_diagnostics(uri.clone(), context.to_string(), true));
+        for (uri, document) in storemap.iter() {
+            block_on(self.publish_diagnostics(uri.clone(), document));
         }
     }
 }
 
+impl ProjectFolder {
+    /// find the build directory, the selected preset overrides the build_dir and the preset in
+    /// the settings
+    fn discover_build_dir(&self) -> PathBuf {
+        match &self.selected_preset {
+            Some(preset) => build_dir::discover_build_dir(
+                &self.root,
+                &Config {
+                    build_dir: None,
+                    preset: Some(preset.clone()),
+                    ..self.config.clone()
+                },
+            ),
+            None => build_dir::discover_build_dir(&self.root, &self.config),
+        }
+    }
+
+    /// the active build directory of the folder
+    fn build_dir(&self) -> PathBuf {
+        self.selected_build_dir
+            .clone()
+            .unwrap_or_else(|| self.discovered_build_dir.clone())
+    }
+
+    /// read the CMakeCache.txt and the reply of the file api in the build directory
+    fn load_build_cache(&self) {
+        let build_dir = self.build_dir();
+        let path = build_dir.join("CMakeCache.txt");
+        if path.exists() {
+            filewatcher::refresh_cache(&self.root, path);
+        } else {
+            filewatcher::clear_cache(&self.root);
+        }
+        fileapi::refresh_targets(&self.root, &build_dir);
+    }
+}
+
 impl LanguageServer for Backend {
     type Error = ResponseError;
     type NotifyResult = ControlFlow<async_lsp::Result<()>>;
@@ -139,34 +550,62 @@ impl LanguageServer for Backend {
             .and_then(|value| serde_json::from_value(value).unwrap_or(None))
             .unwrap_or_default();
 
-        self.init_info.scan_cmake_in_package = initial_config.is_scan_cmake_in_package();
-
-        if let Some(workspace) = initial.capabilities.workspace {
-            if let Some(watch_file) = workspace.did_change_watched_files {
-                if let (Some(true), Some(true)) = (
-                    watch_file.dynamic_registration,
-                    watch_file.relative_pattern_support,
-                ) {
-                    #[allow(deprecated)]
-                    if let Some(ref uri) = initial.root_uri {
-                        let path = std::path::Path::new(uri.path())
-                            .join("build")
-                            .join("CMakeCache.txt");
-                        if path.exists() {
-                            filewatcher::refresh_error_packages(path);
-                        }
-                    }
-                }
-            }
-        }
+        self.support_configuration = initial
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.configuration)
+            .unwrap_or(false);
+        self.support_inlay_hint_refresh = initial
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.inlay_hint.as_ref())
+            .and_then(|inlay_hint| inlay_hint.refresh_support)
+            .unwrap_or(false);
+        self.support_diagnostic_refresh = initial
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.diagnostic.as_ref())
+            .and_then(|diagnostic| diagnostic.refresh_support)
+            .unwrap_or(false);
+        let semantic_tokens_provider = if initial_config.is_semantic_token() {
+            Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+                SemanticTokensOptions {
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                    legend: semantic_token::legend(),
+                    range: Some(true),
+                    full: Some(SemanticTokensFullOptions::Bool(true)),
+                },
+            ))
+        } else {
+            None
+        };
 
+        self.config = initial_config;
         #[allow(deprecated)]
-        if let Some(uri) = initial.root_uri.clone() {
-            self.scan_handle
-                .replace(schedule_scan_all(uri.path().to_string()));
-            self.root_path.replace(uri.path().into());
+        let roots: Vec<Url> = match initial.workspace_folders {
+            Some(folders) if !folders.is_empty() => {
+                folders.into_iter().map(|folder| folder.uri).collect()
+            }
+            _ => initial.root_uri.into_iter().collect(),
+        };
+        for uri in roots {
+            let Ok(root) = uri.to_file_path() else {
+                continue;
+            };
+            self.add_folder(root, false);
         }
 
+        progress::set_support_progress(
+            initial
+                .capabilities
+                .window
+                .as_ref()
+                .and_then(|window| window.work_done_progress)
+                .unwrap_or(false),
+        );
         set_client_text_document(initial.capabilities.text_document);
 
         let version: String = env!("CARGO_PKG_VERSION").to_string();
@@ -181,20 +620,25 @@ impl LanguageServer for Backend {
                     text_document_sync: Some(TextDocumentSyncCapability::Options(
                         TextDocumentSyncOptions {
                             open_close: Some(true),
-                            change: Some(TextDocumentSyncKind::FULL),
+                            change: Some(TextDocumentSyncKind::INCREMENTAL),
                             will_save: Some(false),
                             will_save_wait_until: Some(false),
                             save: Some(TextDocumentSyncSaveOptions::Supported(true)),
                         },
                     )),
                     completion_provider: Some(CompletionOptions {
-                        resolve_provider: Some(false),
-                        trigger_characters: None,
+                        resolve_provider: Some(true),
+                        trigger_characters: Some(vec![
+                            "(".to_string(),
+                            " ".to_string(),
+                            "{".to_string(),
+                        ]),
                         work_done_progress_options: Default::default(),
                         all_commit_characters: None,
                         completion_item: None,
                     }),
                     document_symbol_provider: Some(OneOf::Left(true)),
+                    workspace_symbol_provider: Some(OneOf::Left(true)),
                     definition_provider: Some(OneOf::Left(true)),
                     document_formatting_provider: Some(OneOf::Left(true)),
                     document_range_formatting_provider: Some(OneOf::Left(true)),
@@ -206,8 +650,51 @@ impl LanguageServer for Backend {
                         }),
                         file_operations: None,
                     }),
-                    semantic_tokens_provider: None,
+                    semantic_tokens_provider,
                     references_provider: Some(OneOf::Left(true)),
+                    rename_provider: Some(OneOf::Right(RenameOptions {
+                        prepare_provider: Some(true),
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    })),
+                    linked_editing_range_provider: Some(
+                        LinkedEditingRangeServerCapabilities::Simple(true),
+                    ),
+                    diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
+                        DiagnosticOptions {
+                            identifier: Some("neocmakelsp".to_string()),
+                            inter_file_dependencies: true,
+                            workspace_diagnostics: true,
+                            work_done_progress_options: WorkDoneProgressOptions::default(),
+                        },
+                    )),
+                    code_lens_provider: Some(CodeLensOptions {
+                        resolve_provider: Some(false),
+                    }),
+                    execute_command_provider: Some(ExecuteCommandOptions {
+                        commands: command::get_commands(),
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    }),
+                    selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                    call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+                    document_link_provider: Some(DocumentLinkOptions {
+                        resolve_provider: Some(false),
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    }),
+                    inlay_hint_provider: Some(OneOf::Left(true)),
+                    document_highlight_provider: Some(OneOf::Left(true)),
+                    folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                    signature_help_provider: Some(SignatureHelpOptions {
+                        trigger_characters: Some(vec!["(".to_string(), " ".to_string()]),
+                        retrigger_characters: None,
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    }),
+                    code_action_provider: Some(CodeActionProviderCapability::Options(
+                        CodeActionOptions {
+                            code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+                            work_done_progress_options: WorkDoneProgressOptions::default(),
+                            resolve_provider: None,
+                        },
+                    )),
                     ..ServerCapabilities::default()
                 },
             })
@@ -221,9 +708,23 @@ impl LanguageServer for Backend {
                     glob_pattern: GlobPattern::String("**/CMakeCache.txt".to_string()),
                     kind: Some(lsp_types::WatchKind::all()),
                 },
+                FileSystemWatcher {
+                    glob_pattern: GlobPattern::String(
+                        "**/.cmake/api/v1/reply/index-*.json".to_string(),
+                    ),
+                    kind: Some(lsp_types::WatchKind::Create | lsp_types::WatchKind::Change),
+                },
+                FileSystemWatcher {
+                    glob_pattern: GlobPattern::String("**/CMake*Presets.json".to_string()),
+                    kind: Some(lsp_types::WatchKind::all()),
+                },
                 FileSystemWatcher {
                     glob_pattern: GlobPattern::String("**/CMakeLists.txt".to_string()),
-                    kind: Some(lsp_types::WatchKind::Create | lsp_types::WatchKind::Delete),
+                    kind: Some(lsp_types::WatchKind::all()),
+                },
+                FileSystemWatcher {
+                    glob_pattern: GlobPattern::String("**/*.cmake".to_string()),
+                    kind: Some(lsp_types::WatchKind::all()),
                 },
             ],
         };
@@ -235,9 +736,9 @@ impl LanguageServer for Backend {
         };
 
         // TODO: block ???
-        let _ = self.client.register_capability(RegistrationParams {
+        drop(self.client.register_capability(RegistrationParams {
             registrations: vec![cmakecache_watcher],
-        });
+        }));
 
         self.client
             .show_message(ShowMessageParams {
@@ -245,13 +746,68 @@ impl LanguageServer for Backend {
                 message: "initialized!".into(),
             })
             .unwrap();
+        self.pull_configuration();
+        for index in 0..self.folders.len() {
+            self.rescan_folder(index);
+        }
+        // NOTE: the indexes are built in the background, so the first requests are not blocked
+        indexing::schedule_indexing(self.client.clone());
+        ControlFlow::Continue(())
+    }
+
+    fn work_done_progress_cancel(
+        &mut self,
+        params: WorkDoneProgressCancelParams,
+    ) -> ControlFlow<async_lsp::Result<()>> {
+        progress::cancel(&params.token);
         ControlFlow::Continue(())
     }
 
     fn did_change_configuration(
         &mut self,
-        _: DidChangeConfigurationParams,
+        params: DidChangeConfigurationParams,
     ) -> ControlFlow<async_lsp::Result<()>> {
+        // NOTE: the clients which support workspace/configuration usually send null here
+        match Config::from_settings(params.settings) {
+            Some(config) if !self.support_configuration => {
+                let mut configs = vec![(None, config.clone())];
+                configs.extend(
+                    self.folders
+                        .iter()
+                        .map(|folder| (Some(folder.root.clone()), config.clone())),
+                );
+                self.apply_config(configs);
+            }
+            _ => self.pull_configuration(),
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn did_change_workspace_folders(
+        &mut self,
+        params: DidChangeWorkspaceFoldersParams,
+    ) -> ControlFlow<async_lsp::Result<()>> {
+        for folder in params.event.removed {
+            let Ok(root) = folder.uri.to_file_path() else {
+                continue;
+            };
+            self.remove_folder(&root);
+        }
+        for folder in params.event.added {
+            let Ok(root) = folder.uri.to_file_path() else {
+                continue;
+            };
+            self.add_folder(root, true);
+        }
+        self.client
+            .log_message(LogMessageParams {
+                typ: MessageType::INFO,
+                message: "workspace folders changed".into(),
+            })
+            .unwrap();
+        // NOTE: the settings of the new folders are pulled
+        self.pull_configuration();
+        self.update_diagnostics();
         ControlFlow::Continue(())
     }
 
@@ -260,19 +816,59 @@ impl LanguageServer for Backend {
         params: DidChangeWatchedFilesParams,
     ) -> ControlFlow<Result<(), async_lsp::Error>> {
         for change in params.changes {
-            if let Some("CMakeLists.txt") = change.uri.path().split('/').last() {
-                let Some(ref path) = self.root_path else {
-                    continue;
-                };
-
-                if self.scan_handle.is_some() {
-                    let _ = block_on(self.scan_handle.as_mut().unwrap());
+            let path = &uri_path(&change.uri);
+            let file_name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            if file_name == "CMakeLists.txt" || file_name.ends_with(".cmake") {
+                // NOTE: the included files and the symbols may be changed
+                block_on(scansubs::clear_project_files());
+                block_on(workspace_symbol::remove_symbol_cache(path));
+                crate::document::forget_document(path);
+                diagnostic::invalidate_results();
+                if file_name == "CMakeLists.txt" && change.typ != FileChangeType::CHANGED {
+                    if let Some(index) = self.get_folder_index(path) {
+                        self.rescan_folder(index);
+                    }
                 }
-                self.scan_handle.replace(scansubs::schedule_scan_all(
-                    path.to_str().unwrap().to_string(),
-                ));
                 continue;
             }
+            // NOTE: the binaryDir of the preset may be changed
+            if presets::is_presets_file(path) {
+                if let Some(index) = self.get_folder_index(path) {
+                    self.rediscover_build_dir(index);
+                }
+                continue;
+            }
+            // NOTE: cmake writes a new index when the reply of the file api is changed
+            if path
+                .parent()
+                .is_some_and(|dir| dir.ends_with(".cmake/api/v1/reply"))
+            {
+                if let Some(folder) = self
+                    .folders
+                    .iter()
+                    .find(|folder| path.starts_with(folder.build_dir()))
+                {
+                    fileapi::refresh_targets(&folder.root, &folder.build_dir());
+                }
+                continue;
+            }
+            // NOTE: another build directory may be found when a cache is created or deleted
+            if change.typ != FileChangeType::CHANGED {
+                if let Some(index) = self.get_folder_index(path) {
+                    self.rediscover_build_dir(index);
+                }
+            }
+            // NOTE: only the cache in the build directory of the folders is used
+            let Some(folder) = self
+                .folders
+                .iter()
+                .find(|folder| folder.build_dir().join("CMakeCache.txt") == *path)
+            else {
+                continue;
+            };
             self.client
                 .log_message(LogMessageParams {
                     typ: MessageType::INFO,
@@ -280,13 +876,13 @@ impl LanguageServer for Backend {
                 })
                 .unwrap();
             if let FileChangeType::DELETED = change.typ {
-                filewatcher::clear_error_packages();
+                filewatcher::clear_cache(&folder.root);
             } else {
-                let path = change.uri.path();
-                filewatcher::refresh_error_packages(path);
+                filewatcher::refresh_cache(&folder.root, path);
             }
         }
         self.update_diagnostics();
+        self.refresh_inlay_hints();
         self.client
             .log_message(LogMessageParams {
                 typ: MessageType::INFO,
@@ -300,13 +896,11 @@ impl LanguageServer for Backend {
         &mut self,
         input: DidOpenTextDocumentParams,
     ) -> ControlFlow<Result<(), async_lsp::Error>> {
-        let mut parse = Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let uri = input.text_document.uri.clone();
-        let context = input.text_document.text.clone();
-        let mut storemap = block_on(BUFFERS_CACHE.lock());
-        storemap.entry(uri.clone()).or_insert(context.clone());
-        block_on(self.publish_diagnostics(uri, context, true));
+        let uri = input.text_document.uri;
+        let document = Document::new(input.text_document.text, input.text_document.version);
+        block_on(self.publish_diagnostics(uri.clone(), &document));
+        let mut storemap = block_on(get_buffers(&uri).lock());
+        storemap.insert(uri, document);
         self.client
             .log_message(LogMessageParams {
                 typ: MessageType::INFO,
@@ -321,17 +915,24 @@ impl LanguageServer for Backend {
         &mut self,
         input: DidChangeTextDocumentParams,
     ) -> ControlFlow<Result<(), async_lsp::Error>> {
-        // create a parse
-        let uri = input.text_document.uri.clone();
-        let context = input.content_changes[0].text.clone();
-        let mut storemap = block_on(BUFFERS_CACHE.lock());
-        storemap.insert(uri.clone(), context.clone());
-        self.client
-            .log_message(LogMessageParams {
-                typ: MessageType::INFO,
-                message: format!("{input:?}"),
-            })
-            .unwrap();
+        let uri = input.text_document.uri;
+        let version = input.text_document.version;
+        let mut storemap = block_on(get_buffers(&uri).lock());
+        match storemap.get_mut(&uri) {
+            Some(document) => document.apply_changes(input.content_changes, version),
+            None => {
+                // NOTE: we do not know the origin text, only full text can be accepted
+                let Some(change) = input
+                    .content_changes
+                    .into_iter()
+                    .rev()
+                    .find(|change| change.range.is_none())
+                else {
+                    return ControlFlow::Continue(());
+                };
+                storemap.insert(uri, Document::new(change.text, version));
+            }
+        }
         ControlFlow::Continue(())
     }
 
@@ -341,22 +942,42 @@ impl LanguageServer for Backend {
     ) -> ControlFlow<Result<(), async_lsp::Error>> {
         let uri = params.text_document.uri;
 
-        let has_root = self.root_path.is_some();
+        let is_presets = presets::is_presets_file(&uri_path(&uri));
+        let has_root = self.get_folder(&uri_path(&uri)).is_some() && !is_presets;
         if has_root {
-            block_on(scansubs::scan_dir(uri.path()));
+            block_on(scansubs::scan_dir(uri_path(&uri)));
         };
+        if is_presets {
+            if let Some(index) = self.get_folder_index(&uri_path(&uri)) {
+                self.rediscover_build_dir(index);
+            }
+        }
 
+        let mut storemap = block_on(get_buffers(&uri).lock());
         if let Some(context) = params.text {
-            let mut storemap = block_on(BUFFERS_CACHE.lock());
-            storemap.insert(uri.clone(), context.clone());
+            match storemap.get_mut(&uri) {
+                Some(document) => {
+                    let version = document.version;
+                    document.set_text(context, version);
+                }
+                None => {
+                    storemap.insert(uri.clone(), Document::new(context, 0));
+                }
+            }
         };
 
-        let storemap = block_on(BUFFERS_CACHE.lock());
-        if let Some(context) = storemap.get(&uri) {
+        if !is_presets {
+            self.schedule_configure(&uri_path(&uri));
+        }
+        if let Some(document) = storemap.get(&uri) {
             if has_root {
-                block_on(complete::update_cache(uri.path(), context));
+                block_on(complete::update_cache(uri_path(&uri), &document.text));
+                block_on(workspace_symbol::update_symbol_cache(
+                    uri_path(&uri),
+                    &document.text,
+                ));
             }
-            block_on(self.publish_diagnostics(uri, context.to_string(), true));
+            block_on(self.publish_diagnostics(uri, document));
         }
 
         self.client
@@ -375,21 +996,55 @@ impl LanguageServer for Backend {
     ) -> BoxFuture<'static, Result<Option<Hover>, Self::Error>> {
         let position = params.text_document_position_params.position;
         let uri = params.text_document_position_params.text_document.uri;
-        let storemap = block_on(BUFFERS_CACHE.lock());
         self.client
             .log_message(LogMessageParams {
                 typ: MessageType::INFO,
                 message: "Hovered!".into(),
             })
             .unwrap();
+        if presets::is_presets_file(&uri_path(&uri)) {
+            let storemap = block_on(PRESETS_BUFFERS.lock());
+            let result = storemap
+                .get(&uri)
+                .and_then(|document| presets::get_hover(&uri_path(&uri), &document.text, position));
+            return Box::pin(async move {
+                Ok(result.map(|context| Hover {
+                    contents: HoverContents::Scalar(MarkedString::String(context)),
+                    range: None,
+                }))
+            });
+        }
+        let storemap = block_on(BUFFERS_CACHE.lock());
 
         match storemap.get(&uri) {
-            Some(context) => {
-                let mut parse = Parser::new();
-                parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-                let thetree = parse.parse(context.clone(), None);
-                let tree = thetree.unwrap();
-                let output = treehelper::get_cmake_doc(position, tree.root_node(), context);
+            Some(document) => {
+                // NOTE: the targets known by the file api are the real ones, they are used first
+                let output =
+                    treehelper::get_position_string(position, document.root_node(), &document.text)
+                        .and_then(|name| {
+                            let path = &uri_path(&uri);
+                            if let Some(doc) = fileapi::get_target_doc(path, &name) {
+                                return Some(doc);
+                            }
+                            // NOTE: the value in the cache is shown before the document
+                            let entry = filewatcher::get_cache_entry(path, &name)?;
+                            let doc = treehelper::get_cmake_doc(
+                                position,
+                                document.root_node(),
+                                &document.text,
+                            );
+                            Some(match doc {
+                                Some(doc) => format!("{}\n{doc}", entry.document()),
+                                None => entry.document(),
+                            })
+                        })
+                        .or_else(|| {
+                            treehelper::get_cmake_doc(
+                                position,
+                                document.root_node(),
+                                &document.text,
+                            )
+                        });
                 match output {
                     Some(context) => Box::pin(async move {
                         Ok(Some(Hover {
@@ -418,7 +1073,12 @@ impl LanguageServer for Backend {
             })
             .unwrap();
 
-        match getformat(Path::new(input.text_document.uri.path())) {
+        let path = &uri_path(&input.text_document.uri);
+        let config = self.get_config(path);
+        if !config.is_format_enabled() || presets::is_presets_file(path) {
+            return Box::pin(async move { Ok(None) });
+        }
+        match getformat(path, config.format_program()) {
             Ok(result) => Box::pin(async move { Ok(result) }),
             Err(err) => {
                 Box::pin(async move { Err(ResponseError::new(ErrorCode::INTERNAL_ERROR, err)) })
@@ -437,14 +1097,23 @@ impl LanguageServer for Backend {
             })
             .unwrap();
 
+        let path = &uri_path(&input.text_document.uri);
+        let config = self.get_config(path);
+        if !config.is_format_enabled() || presets::is_presets_file(path) {
+            return Box::pin(async move { Ok(None) });
+        }
         let storemap = block_on(BUFFERS_CACHE.lock());
         match storemap.get(&input.text_document.uri) {
-            Some(context) => match format_range(context, input.range) {
-                Ok(result) => Box::pin(async move { Ok(result) }),
-                Err(err) => {
-                    Box::pin(async move { Err(ResponseError::new(ErrorCode::INTERNAL_ERROR, err)) })
+            Some(document) => {
+                match format_range(&document.text, input.range, config.format_program()) {
+                    Ok(result) => Box::pin(async move { Ok(result) }),
+                    Err(err) => {
+                        Box::pin(
+                            async move { Err(ResponseError::new(ErrorCode::INTERNAL_ERROR, err)) },
+                        )
+                    }
                 }
-            },
+            }
             _ => Box::pin(async move {
                 Err(ResponseError::new(
                     ErrorCode::INTERNAL_ERROR,
@@ -480,17 +1149,42 @@ impl LanguageServer for Backend {
             .unwrap();
         let location = input.text_document_position.position;
         let uri = input.text_document_position.text_document.uri;
+        if presets::is_presets_file(&uri_path(&uri)) {
+            let storemap = block_on(PRESETS_BUFFERS.lock());
+            let result = storemap.get(&uri).and_then(|document| {
+                presets::get_completion(&uri_path(&uri), &document.text, location)
+            });
+            return Box::pin(async move { Ok(result.map(CompletionResponse::Array)) });
+        }
         let storemap = BUFFERS_CACHE.lock();
         let urlconent = block_on(storemap).get(&uri).cloned();
 
+        // NOTE: the space only triggers the keywords in the arguments, not every word
+        let by_space = input
+            .context
+            .as_ref()
+            .and_then(|context| context.trigger_character.as_deref())
+            == Some(" ");
         match urlconent {
-            Some(context) => {
+            Some(document)
+                if by_space
+                    && signature_help::get_command_context(
+                        document.root_node(),
+                        &document.text,
+                        location,
+                    )
+                    .is_none() =>
+            {
+                Box::pin(async move { Ok(None) })
+            }
+            Some(document) => {
                 let completion = complete::getcomplete(
-                    &context,
+                    &document.text,
+                    document.root_node(),
                     location,
                     &self.client,
-                    uri.path(),
-                    self.init_info.scan_cmake_in_package,
+                    &uri_path(&uri).to_string_lossy(),
+                    self.get_config(&uri_path(&uri)).is_scan_cmake_in_package(),
                 );
                 Box::pin(async move { Ok(completion) })
             }
@@ -498,6 +1192,14 @@ impl LanguageServer for Backend {
         }
     }
 
+    fn completion_item_resolve(
+        &mut self,
+        item: CompletionItem,
+    ) -> BoxFuture<'static, Result<CompletionItem, Self::Error>> {
+        let result = complete::resolve_completion(item);
+        Box::pin(async move { Ok(result) })
+    }
+
     fn references(
         &mut self,
         input: ReferenceParams,
@@ -507,14 +1209,13 @@ impl LanguageServer for Backend {
         let location = input.text_document_position.position;
         let storemap = block_on(BUFFERS_CACHE.lock());
         let result = match storemap.get(&uri) {
-            Some(context) => {
-                let mut parse = Parser::new();
-                parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
+            Some(document) => {
                 //notify_send(context, Type::Error);
                 block_on(jump::godef(
                     location,
-                    context,
-                    uri.path().to_string(),
+                    &document.text,
+                    document.root_node(),
+                    uri_path(&uri).display().to_string(),
                     &self.client,
                     false,
                 ))
@@ -530,21 +1231,25 @@ impl LanguageServer for Backend {
     ) -> BoxFuture<'static, Result<Option<GotoDefinitionResponse>, ResponseError>> {
         let uri = input.text_document_position_params.text_document.uri;
         let location = input.text_document_position_params.position;
+        if presets::is_presets_file(&uri_path(&uri)) {
+            let storemap = block_on(PRESETS_BUFFERS.lock());
+            let result = storemap.get(&uri).and_then(|document| {
+                presets::get_definition(&uri_path(&uri), &document.text, location)
+            });
+            return Box::pin(async move { Ok(result.map(GotoDefinitionResponse::Link)) });
+        }
         let storemap = block_on(BUFFERS_CACHE.lock());
         let result = match storemap.get(&uri) {
-            Some(context) => {
-                let mut parse = Parser::new();
-                parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-                let thetree = parse.parse(context.clone(), None);
-                let tree = thetree.unwrap();
+            Some(document) => {
                 let origin_selection_range =
-                    treehelper::get_position_range(location, tree.root_node());
+                    treehelper::get_position_range(location, document.root_node());
 
                 //notify_send(context, Type::Error);
                 block_on(jump::godef(
                     location,
-                    context,
-                    uri.path().to_string(),
+                    &document.text,
+                    document.root_node(),
+                    uri_path(&uri).display().to_string(),
                     &self.client,
                     true,
                 ))
@@ -573,6 +1278,462 @@ impl LanguageServer for Backend {
         Box::pin(async move { Ok(result) })
     }
 
+    fn prepare_rename(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> BoxFuture<'static, Result<Option<PrepareRenameResponse>, Self::Error>> {
+        let uri = params.text_document.uri;
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).and_then(|document| {
+            rename::get_rename_symbol(params.position, document.root_node(), &document.text).map(
+                |symbol| PrepareRenameResponse::RangeWithPlaceholder {
+                    range: symbol.range,
+                    placeholder: symbol.name,
+                },
+            )
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn rename(
+        &mut self,
+        params: RenameParams,
+    ) -> BoxFuture<'static, Result<Option<WorkspaceEdit>, Self::Error>> {
+        let uri = params.text_document_position.text_document.uri;
+        let location = params.text_document_position.position;
+        let files = self.get_related_files(&uri);
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let Some(document) = storemap.get(&uri) else {
+            return Box::pin(async move { Ok(None) });
+        };
+        let Some(symbol) =
+            rename::get_rename_symbol(location, document.root_node(), &document.text)
+        else {
+            return Box::pin(async move {
+                Err(ResponseError::new(
+                    ErrorCode::INVALID_REQUEST,
+                    "no symbol can be renamed here".to_owned(),
+                ))
+            });
+        };
+        if !rename::is_valid_name(&params.new_name, symbol.kind) {
+            let message = format!("\"{}\" is not a valid name", params.new_name);
+            return Box::pin(
+                async move { Err(ResponseError::new(ErrorCode::INVALID_PARAMS, message)) },
+            );
+        }
+        let result = rename::rename(&symbol, &params.new_name, &files, &storemap);
+        Box::pin(async move { Ok(Some(result)) })
+    }
+
+    fn semantic_tokens_full(
+        &mut self,
+        params: SemanticTokensParams,
+    ) -> BoxFuture<'static, Result<Option<SemanticTokensResult>, Self::Error>> {
+        let uri = params.text_document.uri;
+        let user_macros = block_on(semantic_token::get_user_macros(uri_path(&uri)));
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).map(|document| {
+            SemanticTokensResult::Tokens(semantic_token::get_semantic_tokens(
+                &document.text,
+                document.root_node(),
+                user_macros,
+                None,
+            ))
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn semantic_tokens_range(
+        &mut self,
+        params: SemanticTokensRangeParams,
+    ) -> BoxFuture<'static, Result<Option<SemanticTokensRangeResult>, Self::Error>> {
+        let uri = params.text_document.uri;
+        let user_macros = block_on(semantic_token::get_user_macros(uri_path(&uri)));
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).map(|document| {
+            SemanticTokensRangeResult::Tokens(semantic_token::get_semantic_tokens(
+                &document.text,
+                document.root_node(),
+                user_macros,
+                Some(params.range),
+            ))
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn linked_editing_range(
+        &mut self,
+        params: LinkedEditingRangeParams,
+    ) -> BoxFuture<'static, Result<Option<LinkedEditingRanges>, Self::Error>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).and_then(|document| {
+            linked_editing::get_linked_editing_ranges(
+                document.root_node(),
+                &document.text,
+                position,
+            )
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn selection_range(
+        &mut self,
+        params: SelectionRangeParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<SelectionRange>>, Self::Error>> {
+        let uri = params.text_document.uri;
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).map(|document| {
+            selection_range::get_selection_ranges(document.root_node(), &params.positions)
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn document_diagnostic(
+        &mut self,
+        params: DocumentDiagnosticParams,
+    ) -> BoxFuture<'static, Result<DocumentDiagnosticReportResult, Self::Error>> {
+        let uri = params.text_document.uri;
+        let storemap = block_on(get_buffers(&uri).lock());
+        let Some(document) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| get_or_read(&storemap, &path))
+        else {
+            return Box::pin(async move {
+                Err(ResponseError::new(
+                    ErrorCode::INVALID_PARAMS,
+                    format!("cannot read {uri}"),
+                ))
+            });
+        };
+        let report = diagnostic::document_report(
+            &uri_path(&uri),
+            &document,
+            self.get_config(&uri_path(&uri)).is_lint_enabled(),
+            params.previous_result_id.as_deref(),
+        );
+        Box::pin(async move { Ok(DocumentDiagnosticReportResult::Report(report)) })
+    }
+
+    fn workspace_diagnostic(
+        &mut self,
+        params: WorkspaceDiagnosticParams,
+    ) -> BoxFuture<'static, Result<WorkspaceDiagnosticReportResult, Self::Error>> {
+        let mut files = block_on(scansubs::get_cached_project_files(None));
+        let buffers = block_on(BUFFERS_CACHE.lock()).clone();
+        for uri in buffers.keys() {
+            if let Ok(path) = uri.to_file_path() {
+                if !files.contains(&path) {
+                    files.push(path);
+                }
+            }
+        }
+        // NOTE: every folder has its own settings, the files are checked by groups
+        let (linted, unlinted): (Vec<PathBuf>, Vec<PathBuf>) = files
+            .into_iter()
+            .partition(|path| self.get_config(path).is_lint_enabled());
+        let presets_buffers = block_on(PRESETS_BUFFERS.lock()).clone();
+        let presets_files: Vec<PathBuf> = presets_buffers
+            .keys()
+            .filter_map(|uri| uri.to_file_path().ok())
+            .collect();
+        // NOTE: the files are read and cmake-lint is run, so they are checked out of the main loop
+        let previous = params.previous_result_ids;
+        let task = tokio::task::spawn_blocking(move || {
+            let mut report = diagnostic::workspace_report(&linted, &buffers, true, &previous);
+            report
+                .items
+                .extend(diagnostic::workspace_report(&unlinted, &buffers, false, &previous).items);
+            report.items.extend(
+                diagnostic::workspace_report(&presets_files, &presets_buffers, false, &previous)
+                    .items,
+            );
+            report
+        });
+        Box::pin(async move {
+            let report = task
+                .await
+                .map_err(|err| ResponseError::new(ErrorCode::INTERNAL_ERROR, err.to_string()))?;
+            Ok(WorkspaceDiagnosticReportResult::Report(report))
+        })
+    }
+
+    fn code_lens(
+        &mut self,
+        params: CodeLensParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<CodeLens>>, Self::Error>> {
+        let uri = params.text_document.uri;
+        if presets::is_presets_file(&uri_path(&uri)) {
+            let storemap = block_on(PRESETS_BUFFERS.lock());
+            let result = storemap
+                .get(&uri)
+                .map(|document| presets::get_code_lenses(&uri, &document.text));
+            return Box::pin(async move { Ok(result) });
+        }
+        let files = self.get_related_files(&uri);
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let documents = call_hierarchy::load_documents(&files, &storemap);
+        let result = storemap
+            .get(&uri)
+            .map(|document| code_lens::get_code_lenses(&uri, document, &documents));
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn execute_command(
+        &mut self,
+        params: ExecuteCommandParams,
+    ) -> BoxFuture<'static, Result<Option<serde_json::Value>, Self::Error>> {
+        // NOTE: the uri of the document is the second argument, the first folder is used without it
+        let index = match params
+            .arguments
+            .get(1)
+            .and_then(|argument| argument.as_str())
+            .and_then(|uri| Url::parse(uri).ok())
+        {
+            Some(uri) => self.get_folder_index(&uri_path(&uri)),
+            None => (!self.folders.is_empty()).then_some(0),
+        };
+        let Some(index) = index else {
+            return Box::pin(async move {
+                Err(ResponseError::new(
+                    ErrorCode::INVALID_REQUEST,
+                    "no workspace is opened".to_owned(),
+                ))
+            });
+        };
+        if params.command == command::SELECT_BUILD_DIR {
+            let dir = params
+                .arguments
+                .first()
+                .and_then(|argument| argument.as_str());
+            let result = self.select_build_dir(index, dir);
+            return Box::pin(async move { Ok(Some(result)) });
+        }
+        if params.command == command::SELECT_PRESET {
+            let name = params
+                .arguments
+                .first()
+                .and_then(|argument| argument.as_str());
+            let result = self.select_preset(index, name).map(Some);
+            return Box::pin(async move { result });
+        }
+        let root_path = self.folders[index].root.clone();
+        let build_dir = self.folders[index].build_dir();
+        let Some(name) = params
+            .arguments
+            .first()
+            .and_then(|argument| argument.as_str())
+            .map(|name| name.to_string())
+        else {
+            let message = format!("{} needs the name as its argument", params.command);
+            return Box::pin(
+                async move { Err(ResponseError::new(ErrorCode::INVALID_PARAMS, message)) },
+            );
+        };
+        let result = match params.command.as_str() {
+            command::BUILD_TARGET => command::spawn_with_log(
+                &self.client,
+                "cmake",
+                &command::build_target_args(&build_dir, &name),
+                &root_path,
+            ),
+            command::RUN_TEST => command::spawn_with_log(
+                &self.client,
+                "ctest",
+                &command::run_test_args(&name),
+                &build_dir,
+            ),
+            _ => {
+                let message = format!("unknown command {}", params.command);
+                return Box::pin(async move {
+                    Err(ResponseError::new(ErrorCode::INVALID_PARAMS, message))
+                });
+            }
+        };
+        Box::pin(async move {
+            result
+                .map(|_| None)
+                .map_err(|e| ResponseError::new(ErrorCode::INTERNAL_ERROR, e.to_string()))
+        })
+    }
+
+    fn prepare_call_hierarchy(
+        &mut self,
+        params: CallHierarchyPrepareParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<CallHierarchyItem>>, Self::Error>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let location = params.text_document_position_params.position;
+        let files = self.get_related_files(&uri);
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let indexes = call_hierarchy::load_index(&files, &storemap);
+        let result = storemap
+            .get(&uri)
+            .and_then(|document| call_hierarchy::prepare(location, document, &indexes));
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn incoming_calls(
+        &mut self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<CallHierarchyIncomingCall>>, Self::Error>> {
+        let files = self.get_related_files(&params.item.uri);
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let indexes = call_hierarchy::load_index(&files, &storemap);
+        let result = call_hierarchy::incoming_calls(&params.item, &indexes);
+        Box::pin(async move { Ok(Some(result)) })
+    }
+
+    fn outgoing_calls(
+        &mut self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<CallHierarchyOutgoingCall>>, Self::Error>> {
+        let files = self.get_related_files(&params.item.uri);
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let indexes = call_hierarchy::load_index(&files, &storemap);
+        let result = call_hierarchy::outgoing_calls(&params.item, &indexes);
+        Box::pin(async move { Ok(Some(result)) })
+    }
+
+    fn document_link(
+        &mut self,
+        params: DocumentLinkParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<DocumentLink>>, Self::Error>> {
+        let uri = params.text_document.uri;
+        let Ok(local_path) = uri.to_file_path() else {
+            return Box::pin(async move { Ok(None) });
+        };
+        let mut dirs = filewatcher::with_cache_entries(&local_path, |cache| {
+            inlay_hint::get_dir_values(&local_path, cache)
+        });
+        if let Some(folder) = self.get_folder(&local_path) {
+            let root_path = folder.root.display().to_string();
+            dirs.entry("CMAKE_SOURCE_DIR".to_string())
+                .or_insert_with(|| root_path.clone());
+            dirs.entry("PROJECT_SOURCE_DIR".to_string())
+                .or_insert(root_path);
+        }
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).map(|document| {
+            document_link::get_document_links(
+                &document.text,
+                document.root_node(),
+                &local_path,
+                &dirs,
+            )
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn inlay_hint(
+        &mut self,
+        params: InlayHintParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<InlayHint>>, Self::Error>> {
+        let uri = params.text_document.uri;
+        let Ok(local_path) = uri.to_file_path() else {
+            return Box::pin(async move { Ok(None) });
+        };
+        let files = self.get_related_files(&uri);
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let Some(document) = storemap.get(&uri) else {
+            return Box::pin(async move { Ok(None) });
+        };
+        let mut functions = (*inlay_hint::get_user_functions(&local_path, document)).clone();
+        for path in files.iter().filter(|path| **path != local_path) {
+            let Some(file_functions) = crate::document::with_document(&storemap, path, |file| {
+                inlay_hint::get_user_functions(path, file)
+            }) else {
+                continue;
+            };
+            for (name, parameters) in file_functions.iter() {
+                functions
+                    .entry(name.clone())
+                    .or_insert_with(|| parameters.clone());
+            }
+        }
+        let result = filewatcher::with_cache_entries(&local_path, |cache| {
+            let context = inlay_hint::HintContext::new(&local_path, cache, &functions);
+            inlay_hint::get_inlay_hints(
+                &document.text,
+                document.root_node(),
+                params.range,
+                &context,
+            )
+        });
+        Box::pin(async move { Ok(Some(result)) })
+    }
+
+    fn document_highlight(
+        &mut self,
+        params: DocumentHighlightParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<DocumentHighlight>>, Self::Error>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let location = params.text_document_position_params.position;
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).and_then(|document| {
+            document_highlight::get_document_highlights(
+                location,
+                document.root_node(),
+                &document.text,
+            )
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn folding_range(
+        &mut self,
+        params: FoldingRangeParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<FoldingRange>>, Self::Error>> {
+        let uri = params.text_document.uri;
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).map(|document| {
+            folding_range::get_folding_ranges(&document.text, document.root_node())
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn signature_help(
+        &mut self,
+        params: SignatureHelpParams,
+    ) -> BoxFuture<'static, Result<Option<SignatureHelp>, Self::Error>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let location = params.text_document_position_params.position;
+        let mut files = Vec::new();
+        if let Ok(path) = uri.to_file_path() {
+            files.append(&mut scansubs::scan_include_files(&path));
+            files.append(&mut self.get_project_files(&path));
+        }
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).and_then(|document| {
+            signature_help::get_signature_help(document, location, &files, &storemap)
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn code_action(
+        &mut self,
+        params: CodeActionParams,
+    ) -> BoxFuture<'static, Result<Option<CodeActionResponse>, Self::Error>> {
+        let uri = params.text_document.uri;
+        let storemap = block_on(BUFFERS_CACHE.lock());
+        let result = storemap.get(&uri).map(|document| {
+            code_action::get_code_actions(&uri, document, &params.context.diagnostics)
+        });
+        Box::pin(async move { Ok(result) })
+    }
+
+    fn symbol(
+        &mut self,
+        params: WorkspaceSymbolParams,
+    ) -> BoxFuture<'static, Result<Option<WorkspaceSymbolResponse>, Self::Error>> {
+        Box::pin(async move {
+            let symbols = workspace_symbol::get_workspace_symbols(&params.query).await;
+            Ok(Some(WorkspaceSymbolResponse::Flat(symbols)))
+        })
+    }
+
     fn document_symbol(
         &mut self,
         input: DocumentSymbolParams,
@@ -580,7 +1741,11 @@ impl LanguageServer for Backend {
         let uri = input.text_document.uri.clone();
         let storemap = block_on(BUFFERS_CACHE.lock());
         let result = match storemap.get(&uri) {
-            Some(context) => block_on(ast::getast(&mut self.client, context)),
+            Some(document) => block_on(ast::getast(
+                &mut self.client,
+                &document.text,
+                document.root_node(),
+            )),
             None => None,
         };
 
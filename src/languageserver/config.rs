@@ -1,15 +1,116 @@
 use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-#[derive(Deserialize, PartialEq, Eq, Debug)]
+/// the section of the settings in workspace/configuration
+pub const CONFIG_SECTION: &str = "neocmake";
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct Config {
     pub scan_cmake_in_package: Option<bool>,
     pub semantic_token: Option<bool>,
+    pub format: Option<FormatConfig>,
+    pub lint: Option<LintConfig>,
+    /// the build directory, relative to the root of the workspace
+    pub build_dir: Option<String>,
+    /// the configure preset, its binaryDir is used as the build directory
+    pub preset: Option<String>,
+    pub configure_on_save: Option<ConfigureConfig>,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct FormatConfig {
+    pub enable: Option<bool>,
+    /// the program used to format, default is cmake-format
+    pub program: Option<String>,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct LintConfig {
+    /// run cmake-lint when publishing the diagnostics
+    pub enable: Option<bool>,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct ConfigureConfig {
+    /// run cmake to configure the project after saving, default is false
+    pub enable: Option<bool>,
+    /// configure in a temporary directory instead of the build directory
+    pub scratch: Option<bool>,
+    /// wait for more saves before running, in milliseconds
+    pub debounce: Option<u64>,
 }
 
 impl Config {
     pub fn is_scan_cmake_in_package(&self) -> bool {
         self.scan_cmake_in_package.unwrap_or(true)
     }
+
+    pub fn is_semantic_token(&self) -> bool {
+        self.semantic_token.unwrap_or(false)
+    }
+
+    pub fn is_format_enabled(&self) -> bool {
+        self.format
+            .as_ref()
+            .and_then(|format| format.enable)
+            .unwrap_or(true)
+    }
+
+    pub fn format_program(&self) -> &str {
+        self.format
+            .as_ref()
+            .and_then(|format| format.program.as_deref())
+            .unwrap_or("cmake-format")
+    }
+
+    pub fn is_lint_enabled(&self) -> bool {
+        self.lint
+            .as_ref()
+            .and_then(|lint| lint.enable)
+            .unwrap_or(true)
+    }
+
+    pub fn is_configure_on_save(&self) -> bool {
+        self.configure_on_save
+            .as_ref()
+            .and_then(|configure| configure.enable)
+            .unwrap_or(false)
+    }
+
+    pub fn is_configure_in_scratch(&self) -> bool {
+        self.configure_on_save
+            .as_ref()
+            .and_then(|configure| configure.scratch)
+            .unwrap_or(false)
+    }
+
+    pub fn configure_debounce(&self) -> Duration {
+        Duration::from_millis(
+            self.configure_on_save
+                .as_ref()
+                .and_then(|configure| configure.debounce)
+                .unwrap_or(1000),
+        )
+    }
+
+    pub fn get_build_dir(&self, root_path: &Path) -> PathBuf {
+        root_path.join(self.build_dir.as_deref().unwrap_or("build"))
+    }
+
+    /// read the settings, they may be nested in the section
+    pub fn from_settings(settings: serde_json::Value) -> Option<Self> {
+        let settings = match settings {
+            serde_json::Value::Object(mut map) if map.contains_key(CONFIG_SECTION) => {
+                map.remove(CONFIG_SECTION)?
+            }
+            settings => settings,
+        };
+        if !settings.is_object() {
+            return None;
+        }
+        serde_json::from_value(settings).ok()
+    }
 }
 
 impl Default for Config {
@@ -17,6 +118,49 @@ impl Default for Config {
         Config {
             scan_cmake_in_package: Some(true),
             semantic_token: Some(false),
+            format: None,
+            lint: None,
+            build_dir: None,
+            preset: None,
+            configure_on_save: None,
         }
     }
 }
+
+#[test]
+fn tst_config_from_settings() {
+    let config = Config::from_settings(serde_json::json!({
+        "neocmake": {
+            "scan_cmake_in_package": false,
+            "lint": { "enable": false },
+            "format": { "program": "gersemi" },
+            "build_dir": "out/build",
+            "configure_on_save": { "enable": true, "debounce": 200 }
+        }
+    }))
+    .unwrap();
+    assert!(!config.is_scan_cmake_in_package());
+    assert!(!config.is_lint_enabled());
+    assert!(config.is_format_enabled());
+    assert_eq!(config.format_program(), "gersemi");
+    assert!(config.is_configure_on_save());
+    assert!(!config.is_configure_in_scratch());
+    assert_eq!(config.configure_debounce(), Duration::from_millis(200));
+    assert_eq!(
+        config.get_build_dir(Path::new("/tmp/project")),
+        PathBuf::from("/tmp/project/out/build")
+    );
+    assert_eq!(
+        Config::from_settings(serde_json::json!({ "semantic_token": true })),
+        Some(Config {
+            scan_cmake_in_package: None,
+            semantic_token: Some(true),
+            format: None,
+            lint: None,
+            build_dir: None,
+            preset: None,
+            configure_on_save: None,
+        })
+    );
+    assert_eq!(Config::from_settings(serde_json::Value::Null), None);
+}
@@ -0,0 +1,138 @@
+/// provide linkedEditingRange, the name of function()/macro() is edited with the one of
+/// endfunction()/endmacro(), and the condition of if() with the legacy else()/endif()
+use async_lsp::lsp_types;
+use lsp_types::{LinkedEditingRanges, Position, Range};
+use tree_sitter::Node;
+
+use crate::utils::treehelper::{point_to_position, position_to_point};
+
+const PAIRED_COMMANDS: [&str; 7] = [
+    "function_command",
+    "endfunction_command",
+    "macro_command",
+    "endmacro_command",
+    "if_command",
+    "else_command",
+    "endif_command",
+];
+
+fn get_argument_list(command: Node) -> Option<Node> {
+    let mut course = command.walk();
+    let argument_list = command
+        .children(&mut course)
+        .find(|child| child.kind() == "argument_list");
+    argument_list
+}
+
+/// the range from the first argument to the last one
+fn get_arguments_range(command: Node, only_first: bool) -> Option<Range> {
+    let argument_list = get_argument_list(command)?;
+    let first = argument_list.named_child(0)?;
+    let last = if only_first {
+        first
+    } else {
+        argument_list.named_child(argument_list.named_child_count() - 1)?
+    };
+    Some(Range {
+        start: point_to_position(first.start_position()),
+        end: point_to_position(last.end_position()),
+    })
+}
+
+fn get_range_content(source: &[&str], range: Range) -> Option<String> {
+    if range.start.line != range.end.line {
+        return None;
+    }
+    let line = source.get(range.start.line as usize)?;
+    line.get(range.start.character as usize..range.end.character as usize)
+        .map(|content| content.to_string())
+}
+
+fn contains(range: &Range, position: Position) -> bool {
+    range.start <= position && position <= range.end
+}
+
+pub fn get_linked_editing_ranges(
+    root: Node,
+    source: &str,
+    position: Position,
+) -> Option<LinkedEditingRanges> {
+    let newsource: Vec<&str> = source.lines().collect();
+    let point = position_to_point(position);
+    let mut command = root.descendant_for_point_range(point, point)?;
+    while !PAIRED_COMMANDS.contains(&command.kind()) {
+        command = command.parent()?;
+    }
+    let block = command.parent()?;
+    let (commands, only_first) = match block.kind() {
+        "function_def" | "macro_def" => (vec![block.child(0)?, block.child(2)?], true),
+        "if_condition" => {
+            let mut course = block.walk();
+            let commands = block
+                .children(&mut course)
+                .filter(|child| {
+                    matches!(
+                        child.kind(),
+                        "if_command" | "else_command" | "endif_command"
+                    )
+                })
+                .collect();
+            (commands, false)
+        }
+        _ => return None,
+    };
+    let ranges: Vec<Range> = commands
+        .into_iter()
+        .filter_map(|command| get_arguments_range(command, only_first))
+        .collect();
+    if ranges.len() < 2 || !ranges.iter().any(|range| contains(range, position)) {
+        return None;
+    }
+    // NOTE: only the ranges with the same content can be edited together
+    let content = get_range_content(&newsource, ranges[0])?;
+    if ranges
+        .iter()
+        .any(|range| get_range_content(&newsource, *range).as_ref() != Some(&content))
+    {
+        return None;
+    }
+    Some(LinkedEditingRanges {
+        ranges,
+        word_pattern: None,
+    })
+}
+
+#[test]
+fn tst_linked_editing_ranges() {
+    use crate::document::Document;
+    let source = r#"function(foo arg)
+endfunction(foo)
+macro(bar)
+endmacro()
+if(WIN32 AND MSVC)
+elseif(UNIX)
+else(WIN32 AND MSVC)
+endif(WIN32 AND MSVC)
+if(APPLE)
+endif(IOS)
+"#;
+    let document = Document::new(source.to_string(), 0);
+    let ranges = |line, character| {
+        get_linked_editing_ranges(document.root_node(), source, Position::new(line, character)).map(
+            |linked| {
+                linked
+                    .ranges
+                    .into_iter()
+                    .map(|range| (range.start.line, range.start.character, range.end.character))
+                    .collect::<Vec<_>>()
+            },
+        )
+    };
+    assert_eq!(ranges(1, 13), Some(vec![(0, 9, 12), (1, 12, 15)]));
+    // the parameters are not linked
+    assert_eq!(ranges(0, 14), None);
+    assert_eq!(ranges(2, 7), None);
+    assert_eq!(ranges(4, 5), Some(vec![(4, 3, 17), (6, 5, 19), (7, 6, 20)]));
+    assert_eq!(ranges(5, 8), None);
+    assert_eq!(ranges(8, 4), None);
+}
@@ -2,15 +2,37 @@ use clap::Parser;
 use std::path::PathBuf;
 
 mod ast;
+mod build_dir;
+mod call_hierarchy;
+mod code_action;
+mod code_lens;
+mod command;
 mod complete;
+mod configure;
 mod consts;
+mod diagnostic;
+mod document;
+mod document_highlight;
+mod document_link;
+mod fileapi;
 mod filewatcher;
+mod folding_range;
 mod formatting;
 mod grammar;
+mod indexing;
+mod inlay_hint;
 mod jump;
 mod languageserver;
+mod linked_editing;
+mod presets;
+mod progress;
+mod rename;
 mod scansubs;
+mod selection_range;
+mod semantic_token;
+mod signature_help;
 mod utils;
+mod workspace_symbol;
 
 use futures::{AsyncRead, AsyncWrite};
 
@@ -24,22 +46,38 @@ use async_lsp::ClientSocket;
 use tower::ServiceBuilder;
 use tracing::Level;
 
-#[derive(Debug)]
-struct BackendInitInfo {
-    pub scan_cmake_in_package: bool,
-}
-
 /// Beckend
 #[derive(Debug)]
 struct Backend {
     /// client
     client: ClientSocket,
 
-    /// Storage the message of buffers
-    init_info: BackendInitInfo,
-    root_path: Option<PathBuf>,
+    /// the settings from initialization_options and workspace/configuration
+    config: languageserver::config::Config,
+    /// whether the client supports workspace/configuration
+    support_configuration: bool,
+    /// whether the client supports workspace/diagnostic/refresh
+    support_diagnostic_refresh: bool,
+    /// whether the client supports workspace/inlayHint/refresh
+    support_inlay_hint_refresh: bool,
+    /// the workspace folders, every one has its own scan, build directory and settings
+    folders: Vec<ProjectFolder>,
+}
+
+/// a workspace folder
+#[derive(Debug)]
+struct ProjectFolder {
+    root: PathBuf,
+    /// the settings of the folder, the ones of the server are used before they are pulled
+    config: languageserver::config::Config,
+    /// the build directory found by build_dir::discover_build_dir
+    discovered_build_dir: PathBuf,
+    /// the build directory selected by the command, it is used before the discovered one
+    selected_build_dir: Option<PathBuf>,
+    /// the configure preset selected by the command, it is used before the one in the settings
+    selected_preset: Option<String>,
 
-    scan_handle: Option<tokio::task::JoinHandle<()>>,
+    scan_handle: Option<scansubs::ScanHandle>,
 }
 
 async fn start_server(input: impl AsyncRead, output: impl AsyncWrite) {
@@ -50,14 +88,19 @@ async fn start_server(input: impl AsyncRead, output: impl AsyncWrite) {
             .layer(CatchUnwindLayer::default())
             .layer(ConcurrencyLayer::default())
             .layer(ClientProcessMonitorLayer::new(client.clone()))
-            .service(Router::from_language_server(Backend {
-                client,
-                init_info: BackendInitInfo {
-                    scan_cmake_in_package: false,
-                },
-                root_path: None,
-                scan_handle: None,
-            }))
+            .service({
+                let mut router = Router::from_language_server(Backend {
+                    client,
+                    config: Default::default(),
+                    support_configuration: false,
+                    support_diagnostic_refresh: false,
+                    support_inlay_hint_refresh: false,
+                    folders: Vec::new(),
+                });
+                router.event(Backend::on_configuration_changed);
+                router.event(Backend::on_configure_finished);
+                router
+            })
     });
 
     tracing_subscriber::fmt()
@@ -116,6 +159,6 @@ async fn main() {
 #[test]
 fn test_parse_args() {
     let cli = parse_args(vec!["neocmakelsp", "--stdio"]);
-    assert_eq!(cli.stdio, true);
+    assert!(cli.stdio);
     // assert_eq!(cli.verbose.log_level_filter(), log::LevelFilter::Info);
 }
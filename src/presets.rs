@@ -0,0 +1,820 @@
+/// read the configure presets in CMakePresets.json and CMakeUserPresets.json, and serve the
+/// presets files themselves: diagnostics, completion of the preset names, goto definition across
+/// the included files and hover of the merged values
+mod json;
+mod schema;
+
+use async_lsp::lsp_types;
+use lsp_types::{
+    CodeLens, Command, CompletionItem, CompletionItemKind, CompletionTextEdit, Diagnostic,
+    DiagnosticSeverity, Documentation, LocationLink, Position, Range, TextEdit, Url,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::command::SELECT_PRESET;
+use json::{Kind, Segment, Value};
+
+pub const PRESETS_FILES: [&str; 2] = ["CMakePresets.json", "CMakeUserPresets.json"];
+
+/// a configure preset of the project, it is read by the same loader as the presets files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigurePreset {
+    pub name: String,
+    pub hidden: bool,
+    /// the inherited binaryDir with the macros expanded
+    pub binary_dir: Option<PathBuf>,
+}
+
+/// the presets of the project, CMakeUserPresets.json includes CMakePresets.json
+fn load_project_presets(root: &Path) -> Vec<Preset> {
+    for name in PRESETS_FILES.into_iter().rev() {
+        let path = root.join(name);
+        if let Some(value) = read_json(&path) {
+            return load_presets(&path, &value);
+        }
+    }
+    Vec::new()
+}
+
+/// the configure presets of the project, the included files are read too
+pub fn load_configure_presets(root: &Path) -> Vec<ConfigurePreset> {
+    let presets = load_project_presets(root);
+    presets
+        .iter()
+        .filter(|preset| preset.kind == PresetKind::Configure)
+        .map(|preset| ConfigurePreset {
+            name: preset.name.clone(),
+            hidden: preset.is_hidden(),
+            binary_dir: get_binary_dir(root, &presets, preset),
+        })
+        .collect()
+}
+
+/// expand the macros of the presets, the unknown ones are kept
+fn expand_macros(value: &str, root: &Path, preset: &str, generator: Option<&str>) -> String {
+    let mut value = value
+        .replace("${sourceDir}", &root.display().to_string())
+        .replace(
+            "${sourceParentDir}",
+            &root.parent().unwrap_or(root).display().to_string(),
+        )
+        .replace(
+            "${sourceDirName}",
+            &root
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        )
+        .replace("${presetName}", preset)
+        .replace("${generator}", generator.unwrap_or_default())
+        .replace("${hostSystemName}", std::env::consts::OS)
+        .replace("${pathListSep}", if cfg!(windows) { ";" } else { ":" });
+    // NOTE: the search goes on after the inserted value, the value may contain $env{} too
+    let mut from = 0;
+    while let Some(start) = value[from..].find("$env{").map(|index| from + index) {
+        let Some(end) = value[start..].find('}') else {
+            break;
+        };
+        let name = &value[start + 5..start + end];
+        let env = std::env::var(name).unwrap_or_default();
+        value.replace_range(start..start + end + 1, &env);
+        from = start + env.len();
+    }
+    value.replace("${dollar}", "$")
+}
+
+/// the binary directory of the preset, relative paths are from the root
+fn get_binary_dir(root: &Path, presets: &[Preset], preset: &Preset) -> Option<PathBuf> {
+    let binary_dir =
+        inherited_value(presets, preset, "binaryDir", &mut HashSet::new())?.as_str()?;
+    let generator =
+        inherited_value(presets, preset, "generator", &mut HashSet::new()).and_then(Value::as_str);
+    Some(root.join(expand_macros(binary_dir, root, &preset.name, generator)))
+}
+
+#[test]
+fn tst_expand_env() {
+    std::env::set_var("NEOCMAKELSP_SELF_ENV", "$env{NEOCMAKELSP_SELF_ENV}");
+    let root = Path::new("/tmp/project");
+    assert_eq!(
+        expand_macros(
+            "$env{NEOCMAKELSP_SELF_ENV}/$env{NEOCMAKELSP_NO_ENV}b",
+            root,
+            "",
+            None
+        ),
+        "$env{NEOCMAKELSP_SELF_ENV}/b"
+    );
+}
+
+#[test]
+fn tst_binary_dir() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().to_path_buf();
+    std::fs::write(
+        root.join("CMakePresets.json"),
+        r#"{ "version": 3, "configurePresets": [
+            { "name": "base", "hidden": true, "generator": "Ninja",
+              "binaryDir": "${sourceDir}/out/build/${presetName}" },
+            { "name": "debug", "inherits": "base" },
+            { "name": "loop", "inherits": "loop" }
+        ] }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("CMakeUserPresets.json"),
+        r#"{ "version": 3, "configurePresets": [
+            { "name": "relative", "binaryDir": "build-${generator}", "inherits": ["base"] }
+        ] }"#,
+    )
+    .unwrap();
+    let binary_dir = |name: &str| {
+        load_configure_presets(&root)
+            .into_iter()
+            .find(|preset| preset.name == name)
+            .unwrap()
+            .binary_dir
+    };
+    assert_eq!(binary_dir("debug"), Some(root.join("out/build/debug")));
+    assert_eq!(binary_dir("relative"), Some(root.join("build-Ninja")));
+    assert_eq!(binary_dir("loop"), None);
+    assert!(load_configure_presets(&root)
+        .iter()
+        .any(|preset| preset.name == "base" && preset.hidden));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresetKind {
+    Configure,
+    Build,
+    Test,
+    Package,
+    Workflow,
+}
+
+impl PresetKind {
+    pub const ALL: [PresetKind; 5] = [
+        PresetKind::Configure,
+        PresetKind::Build,
+        PresetKind::Test,
+        PresetKind::Package,
+        PresetKind::Workflow,
+    ];
+
+    /// the field of the presets in the file
+    pub fn field(self) -> &'static str {
+        match self {
+            PresetKind::Configure => "configurePresets",
+            PresetKind::Build => "buildPresets",
+            PresetKind::Test => "testPresets",
+            PresetKind::Package => "packagePresets",
+            PresetKind::Workflow => "workflowPresets",
+        }
+    }
+
+    /// the name of the kind, it is also the type of the steps in the workflow presets
+    pub fn label(self) -> &'static str {
+        match self {
+            PresetKind::Configure => "configure",
+            PresetKind::Build => "build",
+            PresetKind::Test => "test",
+            PresetKind::Package => "package",
+            PresetKind::Workflow => "workflow",
+        }
+    }
+
+    fn from_field(field: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.field() == field)
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.label() == label)
+    }
+}
+
+/// a preset with its position, it may be in an included file
+#[derive(Debug, Clone)]
+struct Preset {
+    kind: PresetKind,
+    name: String,
+    uri: Url,
+    name_range: Range,
+    value: Value,
+}
+
+impl Preset {
+    fn is_hidden(&self) -> bool {
+        self.value
+            .get("hidden")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// the strings in inherits, a single string is the same as an array with it
+    fn parents(&self) -> Vec<&Value> {
+        match self.value.get("inherits") {
+            Some(value) if value.as_str().is_some() => vec![value],
+            Some(value) => value
+                .as_array()
+                .unwrap_or_default()
+                .iter()
+                .filter(|value| value.as_str().is_some())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn document(&self) -> Option<String> {
+        let document: Vec<&str> = ["displayName", "description"]
+            .iter()
+            .filter_map(|field| self.value.get(field).and_then(Value::as_str))
+            .collect();
+        (!document.is_empty()).then(|| document.join("\n"))
+    }
+}
+
+pub fn is_presets_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| PRESETS_FILES.contains(&name))
+}
+
+fn collect_presets(uri: &Url, root: &Value, presets: &mut Vec<Preset>) {
+    for kind in PresetKind::ALL {
+        for value in root
+            .get(kind.field())
+            .and_then(Value::as_array)
+            .unwrap_or_default()
+        {
+            let Some(name) = value.get("name") else {
+                continue;
+            };
+            let Some(name_text) = name.as_str() else {
+                continue;
+            };
+            presets.push(Preset {
+                kind,
+                name: name_text.to_string(),
+                uri: uri.clone(),
+                name_range: name.range,
+                value: value.clone(),
+            });
+        }
+    }
+}
+
+/// the files included by the presets file, CMakeUserPresets.json includes CMakePresets.json
+/// implicitly
+fn get_includes(path: &Path, root: &Value) -> Vec<PathBuf> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut includes: Vec<PathBuf> = root
+        .get("include")
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(Value::as_str)
+        .map(|include| dir.join(include))
+        .collect();
+    if path
+        .file_name()
+        .is_some_and(|name| name == PRESETS_FILES[1])
+    {
+        includes.push(dir.join(PRESETS_FILES[0]));
+    }
+    includes
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|context| json::parse(&context).ok())
+}
+
+/// the presets of the file and the files it includes, only the file itself is not read from disk
+fn load_presets(path: &Path, root: &Value) -> Vec<Preset> {
+    let mut presets = Vec::new();
+    if let Ok(uri) = Url::from_file_path(path) {
+        collect_presets(&uri, root, &mut presets);
+    }
+    let mut visited = HashSet::from([path.to_path_buf()]);
+    let mut includes = get_includes(path, root);
+    while let Some(include) = includes.pop() {
+        if !visited.insert(include.clone()) {
+            continue;
+        }
+        let Some(value) = read_json(&include) else {
+            continue;
+        };
+        if let Ok(uri) = Url::from_file_path(&include) {
+            collect_presets(&uri, &value, &mut presets);
+        }
+        includes.append(&mut get_includes(&include, &value));
+    }
+    presets
+}
+
+fn find_preset<'a>(presets: &'a [Preset], kind: PresetKind, name: &str) -> Option<&'a Preset> {
+    presets
+        .iter()
+        .find(|preset| preset.kind == kind && preset.name == name)
+}
+
+/// the names of the presets in the cycle which starts from the preset, like a -> b -> a
+fn find_cycle(presets: &[Preset], start: &Preset) -> Option<Vec<String>> {
+    fn visit(
+        presets: &[Preset],
+        preset: &Preset,
+        start: &Preset,
+        chain: &mut Vec<String>,
+        visited: &mut HashSet<String>,
+    ) -> bool {
+        for parent in preset.parents().into_iter().filter_map(Value::as_str) {
+            chain.push(parent.to_string());
+            if parent == start.name {
+                return true;
+            }
+            if visited.insert(parent.to_string()) {
+                if let Some(parent) = find_preset(presets, start.kind, parent) {
+                    if visit(presets, parent, start, chain, visited) {
+                        return true;
+                    }
+                }
+            }
+            chain.pop();
+        }
+        false
+    }
+    let mut chain = vec![start.name.clone()];
+    visit(presets, start, start, &mut chain, &mut HashSet::new()).then_some(chain)
+}
+
+/// the value of the field in the preset or the ones it inherits from, the first parent wins
+fn inherited_value<'a>(
+    presets: &'a [Preset],
+    preset: &'a Preset,
+    field: &str,
+    visited: &mut HashSet<String>,
+) -> Option<&'a Value> {
+    if !visited.insert(preset.name.clone()) {
+        return None;
+    }
+    if let Some(value) = preset.value.get(field) {
+        return Some(value);
+    }
+    preset
+        .parents()
+        .into_iter()
+        .filter_map(Value::as_str)
+        .filter_map(|parent| find_preset(presets, preset.kind, parent))
+        .find_map(|parent| inherited_value(presets, parent, field, visited))
+}
+
+/// the map in the field merged with the inherited ones, the own values override the inherited
+/// ones and the first parent wins. the null values are kept, they unset the inherited ones
+fn merged_map(
+    presets: &[Preset],
+    preset: &Preset,
+    field: &str,
+    visited: &mut HashSet<String>,
+) -> BTreeMap<String, Value> {
+    let mut map = BTreeMap::new();
+    if !visited.insert(preset.name.clone()) {
+        return map;
+    }
+    for member in preset
+        .value
+        .get(field)
+        .and_then(Value::members)
+        .unwrap_or_default()
+    {
+        map.insert(member.key.clone(), member.value.clone());
+    }
+    for parent in preset
+        .parents()
+        .into_iter()
+        .filter_map(Value::as_str)
+        .filter_map(|parent| find_preset(presets, preset.kind, parent))
+    {
+        for (key, value) in merged_map(presets, parent, field, visited) {
+            map.entry(key).or_insert(value);
+        }
+    }
+    map
+}
+
+/// the environment of the preset, the presets except the configure ones inherit the environment
+/// of their configure preset by default
+fn merged_environment(presets: &[Preset], preset: &Preset) -> BTreeMap<String, Value> {
+    let mut environment = merged_map(presets, preset, "environment", &mut HashSet::new());
+    if preset.kind == PresetKind::Configure {
+        return environment;
+    }
+    let inherit_configure = inherited_value(
+        presets,
+        preset,
+        "inheritConfigureEnvironment",
+        &mut HashSet::new(),
+    )
+    .and_then(Value::as_bool)
+    .unwrap_or(true);
+    let configure = inherited_value(presets, preset, "configurePreset", &mut HashSet::new())
+        .and_then(Value::as_str)
+        .and_then(|name| find_preset(presets, PresetKind::Configure, name));
+    if let Some(configure) = configure.filter(|_| inherit_configure) {
+        for (key, value) in merged_map(presets, configure, "environment", &mut HashSet::new()) {
+            environment.entry(key).or_insert(value);
+        }
+    }
+    environment
+}
+
+/// the text of the value in the hover, the cache variables may have their types
+fn display_value(value: &Value) -> String {
+    match &value.kind {
+        Kind::String(value) => value.clone(),
+        Kind::Bool(true) => "TRUE".to_string(),
+        Kind::Bool(false) => "FALSE".to_string(),
+        Kind::Number(number) => number.to_string(),
+        Kind::Object(_) => {
+            let inner = value.get("value").map(display_value).unwrap_or_default();
+            match value.get("type").and_then(Value::as_str) {
+                Some(cache_type) => format!("{inner} ({cache_type})"),
+                None => inner,
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+fn preset_document(root: &Path, presets: &[Preset], preset: &Preset) -> String {
+    let mut message = format!("Preset: {}\nType: {}\n", preset.name, preset.kind.label());
+    if let Some(document) = preset.document() {
+        message.push_str(&format!("\n{document}\n"));
+    }
+    let generator =
+        inherited_value(presets, preset, "generator", &mut HashSet::new()).and_then(Value::as_str);
+    let expand =
+        |value: &Value| expand_macros(&display_value(value), root, &preset.name, generator);
+    if preset.kind == PresetKind::Configure {
+        message.push('\n');
+        for field in ["generator", "binaryDir", "toolchainFile", "installDir"] {
+            if let Some(value) = inherited_value(presets, preset, field, &mut HashSet::new()) {
+                message.push_str(&format!("{field}: {}\n", expand(value)));
+            }
+        }
+    } else if let Some(value) =
+        inherited_value(presets, preset, "configurePreset", &mut HashSet::new())
+    {
+        message.push_str(&format!("\nconfigurePreset: {}\n", display_value(value)));
+    }
+    let format_map = |title: &str, map: BTreeMap<String, Value>| {
+        let items: Vec<String> = map
+            .iter()
+            .filter(|(_, value)| value.kind != Kind::Null)
+            .map(|(key, value)| format!("- {key}={}\n", expand(value)))
+            .collect();
+        if items.is_empty() {
+            return String::new();
+        }
+        format!("\n{title}:\n{}", items.concat())
+    };
+    if preset.kind == PresetKind::Configure {
+        message.push_str(&format_map(
+            "Cache variables",
+            merged_map(presets, preset, "cacheVariables", &mut HashSet::new()),
+        ));
+    }
+    message.push_str(&format_map(
+        "Environment",
+        merged_environment(presets, preset),
+    ));
+    message
+}
+
+/// what the string at the position refers to
+enum Reference {
+    Preset(PresetKind),
+    Include,
+}
+
+/// the reference at the position, with the string and the preset which contains it
+fn reference_at(root: &Value, position: Position) -> Option<(Reference, &Value, Option<&Value>)> {
+    let (path, value) = root.path_at(position)?;
+    value.as_str()?;
+    let owner = match path.as_slice() {
+        [Segment::Key(field), Segment::Index(index), ..] => root
+            .get(field)
+            .and_then(Value::as_array)
+            .and_then(|presets| presets.get(*index)),
+        _ => None,
+    };
+    let reference = match path.as_slice() {
+        [Segment::Key(include), Segment::Index(_)] if include == "include" => Reference::Include,
+        [Segment::Key(field), Segment::Index(_), Segment::Key(key), ..] if key == "inherits" => {
+            Reference::Preset(PresetKind::from_field(field)?)
+        }
+        [_, Segment::Index(_), Segment::Key(key)] if key == "configurePreset" => {
+            Reference::Preset(PresetKind::Configure)
+        }
+        [_, Segment::Index(_), Segment::Key(steps), Segment::Index(step), Segment::Key(key)]
+            if steps == "steps" && key == "name" =>
+        {
+            let step_type = owner?
+                .get("steps")?
+                .as_array()?
+                .get(*step)?
+                .get("type")?
+                .as_str()?;
+            Reference::Preset(PresetKind::from_label(step_type)?)
+        }
+        _ => return None,
+    };
+    Some((reference, value, owner))
+}
+
+pub fn get_diagnostics(path: &Path, text: &str) -> Vec<Diagnostic> {
+    let root = match json::parse(text) {
+        Ok(root) => root,
+        Err(error) => {
+            return vec![Diagnostic {
+                range: error.range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("presets".to_string()),
+                message: error.message,
+                ..Default::default()
+            }]
+        }
+    };
+    let mut diagnostics = schema::check(&root);
+    let Some(version) = schema::get_version(&root) else {
+        return diagnostics;
+    };
+    let mut error = |range: Range, message: String| {
+        diagnostics.push(Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("presets".to_string()),
+            message,
+            ..Default::default()
+        });
+    };
+    let dir = path.parent().unwrap_or(Path::new("."));
+    for include in root
+        .get("include")
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+    {
+        if let Some(file) = include.as_str().filter(|file| !dir.join(file).is_file()) {
+            error(
+                include.range,
+                format!("cannot find the included file {file}"),
+            );
+        }
+    }
+    let presets = load_presets(path, &root);
+    let Ok(uri) = Url::from_file_path(path) else {
+        return diagnostics;
+    };
+    let check_reference = |kind: PresetKind, value: &Value| {
+        let name = value.as_str()?;
+        find_preset(&presets, kind, name)
+            .is_none()
+            .then(|| format!("unknown {} preset {name}", kind.label()))
+    };
+    for preset in presets.iter().filter(|preset| preset.uri == uri) {
+        if presets.iter().any(|other| {
+            other.kind == preset.kind
+                && other.name == preset.name
+                && (other.uri != preset.uri || other.name_range != preset.name_range)
+        }) {
+            error(
+                preset.name_range,
+                format!("duplicate {} preset {}", preset.kind.label(), preset.name),
+            );
+        }
+        for parent in preset.parents() {
+            if let Some(message) = check_reference(preset.kind, parent) {
+                error(parent.range, message);
+            }
+        }
+        if let Some(cycle) = find_cycle(&presets, preset) {
+            let range = preset.value.get("inherits").map(|value| value.range);
+            error(
+                range.unwrap_or(preset.name_range),
+                format!("inheritance cycle: {}", cycle.join(" -> ")),
+            );
+        }
+        if let Some(value) = preset.value.get("configurePreset") {
+            if let Some(message) = check_reference(PresetKind::Configure, value) {
+                error(value.range, message);
+            }
+        }
+        for step in preset
+            .value
+            .get("steps")
+            .and_then(Value::as_array)
+            .unwrap_or_default()
+        {
+            let kind = step
+                .get("type")
+                .and_then(Value::as_str)
+                .and_then(PresetKind::from_label)
+                .filter(|kind| *kind != PresetKind::Workflow);
+            match (kind, step.get("name")) {
+                (Some(kind), Some(name)) => {
+                    if let Some(message) = check_reference(kind, name) {
+                        error(name.range, message);
+                    }
+                }
+                _ => error(
+                    step.range,
+                    "the step should have the type and the name of a preset".to_string(),
+                ),
+            }
+        }
+        // NOTE: generator and binaryDir are optional since version 3
+        if version < 3 && preset.kind == PresetKind::Configure && !preset.is_hidden() {
+            for field in ["generator", "binaryDir"] {
+                if inherited_value(&presets, preset, field, &mut HashSet::new()).is_none() {
+                    error(
+                        preset.name_range,
+                        format!("{field} is required before version 3"),
+                    );
+                }
+            }
+        }
+    }
+    diagnostics
+}
+
+/// complete the names of the presets in inherits, configurePreset and the steps of the workflows
+pub fn get_completion(path: &Path, text: &str, position: Position) -> Option<Vec<CompletionItem>> {
+    let root = json::parse(text).ok()?;
+    let (Reference::Preset(kind), value, owner) = reference_at(&root, position)? else {
+        return None;
+    };
+    let owner_name = owner
+        .and_then(|owner| owner.get("name"))
+        .and_then(Value::as_str);
+    let is_inherits = owner
+        .and_then(|owner| owner.get("inherits"))
+        .is_some_and(|inherits| json::contains(&inherits.range, position));
+    let presets = load_presets(path, &root);
+    let items = presets
+        .iter()
+        .filter(|preset| preset.kind == kind)
+        // NOTE: the hidden presets can only be inherited
+        .filter(|preset| is_inherits || !preset.is_hidden())
+        .filter(|preset| !is_inherits || Some(preset.name.as_str()) != owner_name)
+        .map(|preset| CompletionItem {
+            label: preset.name.clone(),
+            kind: Some(CompletionItemKind::REFERENCE),
+            detail: Some(format!("{} preset", kind.label())),
+            documentation: preset.document().map(Documentation::String),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range: value.inner_range(),
+                new_text: preset.name.clone(),
+            })),
+            ..Default::default()
+        })
+        .collect();
+    Some(items)
+}
+
+/// goto the preset of the name, or the included file
+pub fn get_definition(path: &Path, text: &str, position: Position) -> Option<Vec<LocationLink>> {
+    let root = json::parse(text).ok()?;
+    let (reference, value, _) = reference_at(&root, position)?;
+    let name = value.as_str()?;
+    let (target_uri, target_range) = match reference {
+        Reference::Include => {
+            let dir = path.parent()?;
+            let file = dir.join(name);
+            if !file.is_file() {
+                return None;
+            }
+            (Url::from_file_path(file).ok()?, Range::default())
+        }
+        Reference::Preset(kind) => {
+            let presets = load_presets(path, &root);
+            let preset = find_preset(&presets, kind, name)?;
+            (preset.uri.clone(), preset.name_range)
+        }
+    };
+    Some(vec![LocationLink {
+        origin_selection_range: Some(value.range),
+        target_uri,
+        target_range,
+        target_selection_range: target_range,
+    }])
+}
+
+/// show the preset at the position, with the values merged from the ones it inherits from
+pub fn get_hover(path: &Path, text: &str, position: Position) -> Option<String> {
+    let root = json::parse(text).ok()?;
+    let (segments, value) = root.path_at(position)?;
+    let name = value.as_str()?;
+    let kind = match (segments.as_slice(), reference_at(&root, position)) {
+        ([Segment::Key(field), Segment::Index(_), Segment::Key(key)], _) if key == "name" => {
+            PresetKind::from_field(field)?
+        }
+        (_, Some((Reference::Preset(kind), _, _))) => kind,
+        _ => return None,
+    };
+    let presets = load_presets(path, &root);
+    let preset = find_preset(&presets, kind, name)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    Some(preset_document(dir, &presets, preset))
+}
+
+/// the lenses to select the visible configure presets
+pub fn get_code_lenses(uri: &Url, text: &str) -> Vec<CodeLens> {
+    let Some(root) = json::parse(text).ok() else {
+        return Vec::new();
+    };
+    let mut presets = Vec::new();
+    collect_presets(uri, &root, &mut presets);
+    presets
+        .iter()
+        .filter(|preset| preset.kind == PresetKind::Configure && !preset.is_hidden())
+        .map(|preset| CodeLens {
+            range: preset.name_range,
+            command: Some(Command {
+                title: "select preset".to_string(),
+                command: SELECT_PRESET.to_string(),
+                arguments: Some(vec![preset.name.clone().into(), uri.to_string().into()]),
+            }),
+            data: None,
+        })
+        .collect()
+}
+
+#[test]
+fn tst_presets_document() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path().to_path_buf();
+    std::fs::write(
+        dir.join("base.json"),
+        r#"{
+  "version": 6,
+  "configurePresets": [
+    { "name": "base", "hidden": true, "generator": "Ninja",
+      "cacheVariables": { "CMAKE_BUILD_TYPE": "Debug", "USE_FOO": true },
+      "environment": { "CC": "gcc" } }
+  ]
+}"#,
+    )
+    .unwrap();
+    let path = dir.join("CMakePresets.json");
+    let text = r#"{
+  "version": 6,
+  "include": ["base.json"],
+  "configurePresets": [
+    { "name": "debug", "inherits": "base",
+      "cacheVariables": { "CMAKE_BUILD_TYPE": { "type": "STRING", "value": "RelWithDebInfo" } } },
+    { "name": "a", "inherits": ["b"] },
+    { "name": "b", "inherits": "a", "environment": { "CC": "clang" } }
+  ],
+  "buildPresets": [
+    { "name": "build", "configurePreset": "" },
+    { "name": "other", "configurePreset": "missing" }
+  ]
+}"#;
+
+    let messages: Vec<String> = get_diagnostics(&path, text)
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "inheritance cycle: a -> b -> a",
+            "inheritance cycle: b -> a -> b",
+            "unknown configure preset ",
+            "unknown configure preset missing",
+        ]
+    );
+
+    let items = get_completion(&path, text, Position::new(10, 43)).unwrap();
+    let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+    assert_eq!(labels, ["debug", "a", "b"]);
+    let items = get_completion(&path, text, Position::new(4, 37)).unwrap();
+    let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+    assert_eq!(labels, ["a", "b", "base"]);
+
+    let links = get_definition(&path, text, Position::new(4, 37)).unwrap();
+    assert_eq!(
+        links[0].target_uri,
+        Url::from_file_path(dir.join("base.json")).unwrap()
+    );
+    assert_eq!(links[0].target_range.start, Position::new(3, 14));
+
+    let hover = get_hover(&path, text, Position::new(4, 15)).unwrap();
+    assert!(hover.contains("generator: Ninja\n"));
+    assert!(hover.contains("- CMAKE_BUILD_TYPE=RelWithDebInfo (STRING)\n- USE_FOO=TRUE\n"));
+    assert!(hover.contains("- CC=gcc\n"));
+
+    let lenses = get_code_lenses(&Url::from_file_path(&path).unwrap(), text);
+    assert_eq!(lenses.len(), 3);
+}
@@ -0,0 +1,426 @@
+/// a small json parser which keeps the range of every value and key, the presets files are
+/// checked and navigated by them. the character of the positions is counted in utf-16
+use async_lsp::lsp_types::{Position, Range};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<Member>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub kind: Kind,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub key: String,
+    pub key_range: Range,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub range: Range,
+}
+
+/// a step of the path from the root to a value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+pub fn contains(range: &Range, position: Position) -> bool {
+    range.start <= position && position <= range.end
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.members()?
+            .iter()
+            .find(|member| member.key == key)
+            .map(|member| &member.value)
+    }
+
+    pub fn members(&self) -> Option<&[Member]> {
+        match &self.kind {
+            Kind::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match &self.kind {
+            Kind::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            Kind::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.kind {
+            Kind::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            Kind::Null => "null",
+            Kind::Bool(_) => "boolean",
+            Kind::Number(_) => "number",
+            Kind::String(_) => "string",
+            Kind::Array(_) => "array",
+            Kind::Object(_) => "object",
+        }
+    }
+
+    /// the range of the string without the quotes
+    pub fn inner_range(&self) -> Range {
+        match self.kind {
+            Kind::String(_) if self.range.start.line == self.range.end.line => Range {
+                start: Position::new(self.range.start.line, self.range.start.character + 1),
+                end: Position::new(
+                    self.range.end.line,
+                    self.range.end.character.saturating_sub(1),
+                ),
+            },
+            _ => self.range,
+        }
+    }
+
+    /// the path to the innermost value at the position, the keys are treated as their values
+    pub fn path_at(&self, position: Position) -> Option<(Vec<Segment>, &Value)> {
+        if !contains(&self.range, position) {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut current = self;
+        'descend: loop {
+            match &current.kind {
+                Kind::Object(members) => {
+                    for member in members {
+                        if contains(&member.key_range, position)
+                            || contains(&member.value.range, position)
+                        {
+                            path.push(Segment::Key(member.key.clone()));
+                            current = &member.value;
+                            continue 'descend;
+                        }
+                    }
+                }
+                Kind::Array(values) => {
+                    for (index, value) in values.iter().enumerate() {
+                        if contains(&value.range, position) {
+                            path.push(Segment::Index(index));
+                            current = value;
+                            continue 'descend;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            return Some((path, current));
+        }
+    }
+}
+
+/// NOTE: the values are parsed recursively, the deeper ones are rejected to keep the stack safe
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    position: Position,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.character = 0;
+        } else {
+            self.position.character += c.len_utf16() as u32;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            message: message.into(),
+            range: Range {
+                start: self.position,
+                end: self.position,
+            },
+        })
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => self.error(format!("expected '{expected}', found '{c}'")),
+            None => self.error(format!("expected '{expected}', found the end of file")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        let start = self.position;
+        let kind = match self.peek() {
+            Some(c @ ('{' | '[')) => {
+                if self.depth >= MAX_DEPTH {
+                    return self.error("the value is nested too deeply");
+                }
+                self.depth += 1;
+                let kind = if c == '{' {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                kind?
+            }
+            Some('"') => Kind::String(self.string()?),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number()?,
+            Some(c) if c.is_ascii_alphabetic() => self.literal()?,
+            Some(c) => return self.error(format!("unexpected '{c}'")),
+            None => return self.error("unexpected end of file"),
+        };
+        Ok(Value {
+            kind,
+            range: Range {
+                start,
+                end: self.position,
+            },
+        })
+    }
+
+    fn object(&mut self) -> Result<Kind, ParseError> {
+        self.bump();
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Kind::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return self.error("expected a string as the key");
+            }
+            let start = self.position;
+            let key = self.string()?;
+            let key_range = Range {
+                start,
+                end: self.position,
+            };
+            self.expect(':')?;
+            let value = self.value()?;
+            members.push(Member {
+                key,
+                key_range,
+                value,
+            });
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.bump(),
+                Some('}') => {
+                    self.bump();
+                    return Ok(Kind::Object(members));
+                }
+                _ => return self.error("expected ',' or '}'"),
+            };
+        }
+    }
+
+    fn array(&mut self) -> Result<Kind, ParseError> {
+        self.bump();
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(Kind::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.bump(),
+                Some(']') => {
+                    self.bump();
+                    return Ok(Kind::Array(values));
+                }
+                _ => return self.error("expected ',' or ']'"),
+            };
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            self.unicode_escape(&mut value);
+                            continue;
+                        }
+                        Some(c @ ('"' | '\\' | '/')) => c,
+                        _ => return self.error("invalid escape in the string"),
+                    };
+                    value.push(escaped);
+                }
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn hex_code(&mut self) -> Option<u32> {
+        let code: String = (0..4).filter_map(|_| self.bump()).collect();
+        u32::from_str_radix(&code, 16).ok()
+    }
+
+    /// the characters out of the basic plane are escaped as the utf-16 surrogate pairs, like
+    /// \uD83D\uDE00, the lone surrogates become the replacement character
+    fn unicode_escape(&mut self, value: &mut String) {
+        let Some(high) = self.hex_code() else {
+            value.push(char::REPLACEMENT_CHARACTER);
+            return;
+        };
+        if !(0xD800..0xDC00).contains(&high) {
+            value.push(char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER));
+            return;
+        }
+        let mut ahead = self.chars.clone();
+        if ahead.next() != Some('\\') || ahead.next() != Some('u') {
+            value.push(char::REPLACEMENT_CHARACTER);
+            return;
+        }
+        self.bump();
+        self.bump();
+        match self.hex_code() {
+            Some(low) if (0xDC00..0xE000).contains(&low) => {
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            low => {
+                value.push(char::REPLACEMENT_CHARACTER);
+                value.push(
+                    low.and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER),
+                );
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Kind, ParseError> {
+        let mut number = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            number.push(c);
+            self.bump();
+        }
+        match number.parse() {
+            Ok(number) => Ok(Kind::Number(number)),
+            Err(_) => self.error(format!("invalid number {number}")),
+        }
+    }
+
+    fn literal(&mut self) -> Result<Kind, ParseError> {
+        let mut word = String::new();
+        while let Some(c) = self.peek().filter(char::is_ascii_alphabetic) {
+            word.push(c);
+            self.bump();
+        }
+        match word.as_str() {
+            "true" => Ok(Kind::Bool(true)),
+            "false" => Ok(Kind::Bool(false)),
+            "null" => Ok(Kind::Null),
+            _ => self.error(format!("unexpected {word}")),
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Value, ParseError> {
+    let mut parser = Parser {
+        chars: text.chars().peekable(),
+        position: Position::default(),
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return parser.error("unexpected content after the value");
+    }
+    Ok(value)
+}
+
+#[test]
+fn tst_parse_json() {
+    let value = parse("{\n  \"name\": \"dé\",\n  \"list\": [1, true, null]\n}").unwrap();
+    let name = value.get("name").unwrap();
+    assert_eq!(name.as_str(), Some("dé"));
+    assert_eq!(
+        name.range,
+        Range {
+            start: Position::new(1, 10),
+            end: Position::new(1, 14),
+        }
+    );
+    let (path, found) = value.path_at(Position::new(2, 14)).unwrap();
+    assert_eq!(
+        path,
+        vec![Segment::Key("list".to_string()), Segment::Index(1)]
+    );
+    assert_eq!(found.as_bool(), Some(true));
+
+    let error = parse("{\n  \"name\": \"a\"\n  \"other\": 1\n}").unwrap_err();
+    assert_eq!(error.range.start, Position::new(2, 2));
+
+    let value = parse(r#""\uD83D\uDE00 \u00e9 \uD83D""#).unwrap();
+    assert_eq!(value.as_str(), Some("😀 é \u{FFFD}"));
+
+    let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+    assert!(parse(&nested).is_ok());
+    let nested = format!("{}{}", "[".repeat(10000), "]".repeat(10000));
+    assert!(parse(&nested).is_err());
+}
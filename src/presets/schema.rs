@@ -0,0 +1,322 @@
+/// check the presets file against the schema of its version, like the fields which are unknown
+/// or added by a later version, and the types of the common fields
+use async_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Range};
+
+use super::json::{Kind, Value};
+use super::PresetKind;
+
+pub const MIN_VERSION: u64 = 1;
+pub const MAX_VERSION: u64 = 9;
+
+/// the fields of the root and the version they are added in
+const ROOT_FIELDS: &[(&str, u64)] = &[
+    ("version", 1),
+    ("cmakeMinimumRequired", 1),
+    ("vendor", 1),
+    ("configurePresets", 1),
+    ("buildPresets", 2),
+    ("testPresets", 2),
+    ("include", 4),
+    ("packagePresets", 6),
+    ("workflowPresets", 6),
+    ("$schema", 8),
+];
+
+/// the fields shared by the presets except the workflow ones
+const COMMON_FIELDS: &[(&str, u64)] = &[
+    ("name", 1),
+    ("hidden", 1),
+    ("inherits", 1),
+    ("vendor", 1),
+    ("displayName", 1),
+    ("description", 1),
+    ("environment", 1),
+    ("condition", 3),
+];
+
+const CONFIGURE_FIELDS: &[(&str, u64)] = &[
+    ("generator", 1),
+    ("architecture", 1),
+    ("toolset", 1),
+    ("binaryDir", 1),
+    ("cmakeExecutable", 1),
+    ("cacheVariables", 1),
+    ("warnings", 1),
+    ("errors", 1),
+    ("debug", 1),
+    ("installDir", 3),
+    ("toolchainFile", 3),
+    ("trace", 7),
+];
+
+const BUILD_FIELDS: &[(&str, u64)] = &[
+    ("configurePreset", 2),
+    ("inheritConfigureEnvironment", 2),
+    ("jobs", 2),
+    ("targets", 2),
+    ("configuration", 2),
+    ("cleanFirst", 2),
+    ("verbose", 2),
+    ("nativeToolOptions", 2),
+    ("resolvePackageReferences", 5),
+];
+
+const TEST_FIELDS: &[(&str, u64)] = &[
+    ("configurePreset", 2),
+    ("inheritConfigureEnvironment", 2),
+    ("configuration", 2),
+    ("overwriteConfigurationFile", 2),
+    ("output", 2),
+    ("filter", 2),
+    ("execution", 2),
+];
+
+const PACKAGE_FIELDS: &[(&str, u64)] = &[
+    ("configurePreset", 6),
+    ("inheritConfigureEnvironment", 6),
+    ("generators", 6),
+    ("configurations", 6),
+    ("variables", 6),
+    ("configFile", 6),
+    ("output", 6),
+    ("packageName", 6),
+    ("packageVersion", 6),
+    ("packageDirectory", 6),
+    ("vendorName", 6),
+];
+
+const WORKFLOW_FIELDS: &[(&str, u64)] = &[
+    ("name", 6),
+    ("vendor", 6),
+    ("displayName", 6),
+    ("description", 6),
+    ("steps", 6),
+];
+
+fn preset_fields(kind: PresetKind) -> Vec<(&'static str, u64)> {
+    let fields = match kind {
+        PresetKind::Configure => CONFIGURE_FIELDS,
+        PresetKind::Build => BUILD_FIELDS,
+        PresetKind::Test => TEST_FIELDS,
+        PresetKind::Package => PACKAGE_FIELDS,
+        PresetKind::Workflow => return WORKFLOW_FIELDS.to_vec(),
+    };
+    COMMON_FIELDS.iter().chain(fields).copied().collect()
+}
+
+fn new_diagnostic(range: Range, severity: DiagnosticSeverity, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some("presets".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+fn error(range: Range, message: String) -> Diagnostic {
+    new_diagnostic(range, DiagnosticSeverity::ERROR, message)
+}
+
+/// the version of the file, None if it is missing or not supported
+pub fn get_version(root: &Value) -> Option<u64> {
+    match root.get("version")?.kind {
+        Kind::Number(version)
+            if version.fract() == 0.0
+                && (MIN_VERSION as f64..=MAX_VERSION as f64).contains(&version) =>
+        {
+            Some(version as u64)
+        }
+        _ => None,
+    }
+}
+
+fn check_fields(
+    object: &Value,
+    fields: &[(&str, u64)],
+    version: u64,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for member in object.members().unwrap_or_default() {
+        match fields.iter().find(|(field, _)| *field == member.key) {
+            Some((field, since)) if *since > version => diagnostics.push(error(
+                member.key_range,
+                format!("{field} requires version {since}, the file has version {version}"),
+            )),
+            Some(_) => {}
+            None => diagnostics.push(new_diagnostic(
+                member.key_range,
+                DiagnosticSeverity::WARNING,
+                format!("unknown field {}", member.key),
+            )),
+        }
+    }
+}
+
+/// check the type of the field, the expected types are named like the ones of json
+fn check_type(object: &Value, field: &str, expected: &[&str], diagnostics: &mut Vec<Diagnostic>) {
+    let Some(value) = object.get(field) else {
+        return;
+    };
+    if !expected.contains(&value.type_name()) {
+        diagnostics.push(error(
+            value.range,
+            format!(
+                "{field} should be {}, found {}",
+                expected.join(" or "),
+                value.type_name()
+            ),
+        ));
+    }
+}
+
+fn check_string_array(object: &Value, field: &str, diagnostics: &mut Vec<Diagnostic>) {
+    for value in object
+        .get(field)
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+    {
+        if value.as_str().is_none() {
+            diagnostics.push(error(
+                value.range,
+                format!("the items of {field} should be string"),
+            ));
+        }
+    }
+}
+
+fn check_preset(preset: &Value, kind: PresetKind, version: u64, diagnostics: &mut Vec<Diagnostic>) {
+    if preset.members().is_none() {
+        diagnostics.push(error(
+            preset.range,
+            format!("the {} preset should be object", kind.label()),
+        ));
+        return;
+    }
+    check_fields(preset, &preset_fields(kind), version, diagnostics);
+    match preset.get("name") {
+        Some(name) if name.as_str().is_some_and(|name| !name.is_empty()) => {}
+        Some(name) => diagnostics.push(error(
+            name.range,
+            "name should be a non-empty string".to_string(),
+        )),
+        None => diagnostics.push(error(preset.range, "name is required".to_string())),
+    }
+    check_type(preset, "hidden", &["boolean"], diagnostics);
+    check_type(preset, "inherits", &["string", "array"], diagnostics);
+    check_string_array(preset, "inherits", diagnostics);
+    check_type(preset, "displayName", &["string"], diagnostics);
+    check_type(preset, "description", &["string"], diagnostics);
+    check_type(preset, "environment", &["object"], diagnostics);
+    check_type(preset, "configurePreset", &["string"], diagnostics);
+    check_type(preset, "binaryDir", &["string"], diagnostics);
+    check_type(preset, "generator", &["string"], diagnostics);
+    check_type(preset, "cacheVariables", &["object"], diagnostics);
+    for member in preset
+        .get("cacheVariables")
+        .and_then(Value::members)
+        .unwrap_or_default()
+    {
+        let value = &member.value;
+        let valid = match &value.kind {
+            Kind::Null | Kind::Bool(_) | Kind::String(_) => true,
+            Kind::Object(_) => value.get("value").is_some(),
+            _ => false,
+        };
+        if !valid {
+            diagnostics.push(error(
+                value.range,
+                format!(
+                    "the cache variable {} should be null, boolean, string or an object with value",
+                    member.key
+                ),
+            ));
+        }
+    }
+    check_type(preset, "steps", &["array"], diagnostics);
+}
+
+/// check the fields of the file, the references between the presets are not checked here
+pub fn check(root: &Value) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if root.members().is_none() {
+        diagnostics.push(error(
+            root.range,
+            "the presets file should be object".to_string(),
+        ));
+        return diagnostics;
+    }
+    let version = match root.get("version") {
+        None => {
+            diagnostics.push(error(root.range, "version is required".to_string()));
+            return diagnostics;
+        }
+        Some(value) => match get_version(root) {
+            Some(version) => version,
+            None => {
+                diagnostics.push(error(
+                    value.range,
+                    format!("the version should be an integer from {MIN_VERSION} to {MAX_VERSION}"),
+                ));
+                return diagnostics;
+            }
+        },
+    };
+    check_fields(root, ROOT_FIELDS, version, &mut diagnostics);
+    check_type(root, "include", &["array"], &mut diagnostics);
+    check_string_array(root, "include", &mut diagnostics);
+    for kind in PresetKind::ALL {
+        let Some(presets) = root.get(kind.field()) else {
+            continue;
+        };
+        let Some(presets) = presets.as_array() else {
+            diagnostics.push(error(
+                presets.range,
+                format!("{} should be array", kind.field()),
+            ));
+            continue;
+        };
+        for preset in presets {
+            check_preset(preset, kind, version, &mut diagnostics);
+        }
+    }
+    diagnostics
+}
+
+#[test]
+fn tst_check_schema() {
+    let messages = |text: &str| -> Vec<String> {
+        check(&super::json::parse(text).unwrap())
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    };
+    assert_eq!(
+        messages(r#"{ "configurePresets": [] }"#),
+        ["version is required"]
+    );
+    assert_eq!(
+        messages(r#"{ "version": 10 }"#),
+        ["the version should be an integer from 1 to 9"]
+    );
+    assert_eq!(
+        messages(
+            r#"{ "version": 3, "include": ["a.json"], "configurePresets": [
+                { "name": "a", "hidden": "yes", "trace": {}, "foo": 1 },
+                { "displayName": "b" }
+            ] }"#
+        ),
+        [
+            "include requires version 4, the file has version 3",
+            "trace requires version 7, the file has version 3",
+            "unknown field foo",
+            "hidden should be boolean, found string",
+            "name is required",
+        ]
+    );
+    assert!(messages(
+        r#"{ "version": 6, "buildPresets": [{ "name": "b", "configurePreset": "a" }],
+             "workflowPresets": [{ "name": "w", "steps": [] }] }"#
+    )
+    .is_empty());
+}
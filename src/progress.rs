@@ -0,0 +1,122 @@
+/// report window/workDoneProgress for the long tasks, like scanning the workspace and indexing
+/// the packages. the progress can be cancelled by window/workDoneProgress/cancel
+use async_lsp::lsp_types;
+use async_lsp::{ClientSocket, LanguageClient};
+use lsp_types::{
+    NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress, WorkDoneProgressBegin,
+    WorkDoneProgressCreateParams, WorkDoneProgressEnd, WorkDoneProgressReport,
+};
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+static SUPPORT_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+/// the tokens of the progresses which are cancelled by the client
+static CANCELLED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+pub fn set_support_progress(support: bool) {
+    SUPPORT_PROGRESS.store(support, Ordering::Relaxed);
+}
+
+fn token_key(token: &NumberOrString) -> String {
+    match token {
+        NumberOrString::Number(number) => number.to_string(),
+        NumberOrString::String(string) => string.clone(),
+    }
+}
+
+/// mark the progress as cancelled, the task stops at its next check
+pub fn cancel(token: &NumberOrString) {
+    if let Ok(mut cancelled) = CANCELLED.lock() {
+        cancelled.insert(token_key(token));
+    }
+}
+
+/// a progress created by the server, nothing is sent if the client does not support it
+pub struct WorkDone {
+    client: ClientSocket,
+    token: NumberOrString,
+    supported: bool,
+}
+
+impl WorkDone {
+    pub async fn begin(client: &ClientSocket, title: &str) -> Self {
+        let token = NumberOrString::String(format!(
+            "neocmakelsp/{}",
+            NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut client = client.clone();
+        // NOTE: the progress can only be reported after the client creates it
+        let supported = SUPPORT_PROGRESS.load(Ordering::Relaxed)
+            && client
+                .work_done_progress_create(WorkDoneProgressCreateParams {
+                    token: token.clone(),
+                })
+                .await
+                .is_ok();
+        let progress = WorkDone {
+            client,
+            token,
+            supported,
+        };
+        progress.send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.to_string(),
+            cancellable: Some(true),
+            message: None,
+            percentage: Some(0),
+        }));
+        progress
+    }
+
+    fn send(&self, value: WorkDoneProgress) {
+        if !self.supported {
+            return;
+        }
+        let mut client = self.client.clone();
+        let _ = client.progress(ProgressParams {
+            token: self.token.clone(),
+            value: ProgressParamsValue::WorkDone(value),
+        });
+    }
+
+    pub fn report(&self, message: String, percentage: Option<u32>) {
+        self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: Some(true),
+            message: Some(message),
+            percentage,
+        }));
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        CANCELLED
+            .lock()
+            .is_ok_and(|cancelled| cancelled.contains(&token_key(&self.token)))
+    }
+
+    pub fn end(self, message: String) {
+        if let Ok(mut cancelled) = CANCELLED.lock() {
+            cancelled.remove(&token_key(&self.token));
+        }
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd {
+            message: Some(message),
+        }));
+    }
+}
+
+#[test]
+fn tst_cancel_progress() {
+    let client = ClientSocket::new_closed();
+    let progress = futures::executor::block_on(WorkDone::begin(&client, "Scanning"));
+    let other = futures::executor::block_on(WorkDone::begin(&client, "Indexing"));
+    assert!(!progress.is_cancelled());
+    cancel(&progress.token);
+    assert!(progress.is_cancelled());
+    assert!(!other.is_cancelled());
+    let token = progress.token.clone();
+    progress.end("cancelled".to_string());
+    assert!(!CANCELLED.lock().unwrap().contains(&token_key(&token)));
+}
@@ -0,0 +1,306 @@
+/// rename the variables, functions, macros and targets in the whole project
+use async_lsp::lsp_types;
+use lsp_types::{Position, Range, TextEdit, Url, WorkspaceEdit};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tree_sitter::Node;
+
+use crate::complete::is_command_keyword;
+use crate::document::{get_or_read, Document};
+use crate::semantic_token::{
+    is_buildin_command, is_buildin_variable, ARGUMENT_KEYWORDS, CONDITION_KEYWORDS,
+};
+use crate::utils::get_node_content;
+use crate::utils::treehelper::{point_to_position, position_to_point};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameKind {
+    /// user defined function or macro, it is case insensitive
+    Command,
+    /// variables and targets
+    Symbol,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameSymbol {
+    pub name: String,
+    pub kind: RenameKind,
+    pub range: Range,
+}
+
+/// find the symbol under the cursor which can be renamed
+pub fn get_rename_symbol(location: Position, root: Node, source: &str) -> Option<RenameSymbol> {
+    let newsource: Vec<&str> = source.lines().collect();
+    let point = position_to_point(location);
+    let symbol = root
+        .descendant_for_point_range(point, point)
+        .and_then(|node| get_node_symbol(node, &newsource));
+    if symbol.is_some() || point.column == 0 {
+        return symbol;
+    }
+    // NOTE: the cursor may be just at the end of the word
+    let mut point = point;
+    point.column -= 1;
+    root.descendant_for_point_range(point, point)
+        .and_then(|node| get_node_symbol(node, &newsource))
+}
+
+fn get_node_symbol(node: Node, source: &Vec<&str>) -> Option<RenameSymbol> {
+    if node.start_position().row != node.end_position().row || node.child_count() != 0 {
+        return None;
+    }
+    let name = get_node_content(source, &node);
+    let range = Range {
+        start: point_to_position(node.start_position()),
+        end: point_to_position(node.end_position()),
+    };
+    let kind = match node.kind() {
+        "identifier" => {
+            if is_buildin_command(&name.to_lowercase()) {
+                return None;
+            }
+            RenameKind::Command
+        }
+        "variable" => RenameKind::Symbol,
+        "unquoted_argument" => {
+            if is_command_name_argument(node) {
+                RenameKind::Command
+            } else {
+                RenameKind::Symbol
+            }
+        }
+        // NOTE: only the plain text in the quotes, like the target in add_library("foo")
+        "quoted_element" => RenameKind::Symbol,
+        _ => return None,
+    };
+    if kind == RenameKind::Symbol
+        && (ARGUMENT_KEYWORDS.contains(&name.as_str())
+            || CONDITION_KEYWORDS.contains(&name.as_str())
+            || is_buildin_variable(&name)
+            || name.starts_with("CMAKE_")
+            || !is_valid_name(&name, kind)
+            || (node.kind() != "variable" && is_keyword_argument(node, source, &name)))
+    {
+        return None;
+    }
+    Some(RenameSymbol { name, kind, range })
+}
+
+/// whether the argument is the name in function(name), macro(name) or their end commands
+pub fn is_command_name_argument(node: Node) -> bool {
+    let Some(argument) = node.parent() else {
+        return false;
+    };
+    let Some(argument_list) = argument.parent() else {
+        return false;
+    };
+    let Some(command) = argument_list.parent() else {
+        return false;
+    };
+    matches!(
+        command.kind(),
+        "function_command" | "macro_command" | "endfunction_command" | "endmacro_command"
+    ) && argument_list.named_child(0) == Some(argument)
+}
+
+/// whether the argument is a keyword of the builtin command it is passed to, like NAME in
+/// get_filename_component(out file NAME)
+fn is_keyword_argument(node: Node, source: &Vec<&str>, name: &str) -> bool {
+    let mut command = node;
+    while !command.kind().ends_with("_command") {
+        let Some(parent) = command.parent() else {
+            return false;
+        };
+        command = parent;
+    }
+    match command.child(0) {
+        Some(identifier) if command.kind() == "normal_command" => {
+            is_command_keyword(&get_node_content(source, &identifier), name)
+        }
+        _ => is_command_keyword(command.kind().trim_end_matches("_command"), name),
+    }
+}
+
+/// check the new name is a valid name for the kind
+pub fn is_valid_name(name: &str, kind: RenameKind) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    match kind {
+        RenameKind::Command => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        RenameKind::Symbol => {
+            chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '+'))
+        }
+    }
+}
+
+/// get the ranges of the symbol in one document
+pub fn get_symbol_ranges(root: Node, source: &str, symbol: &RenameSymbol) -> Vec<Range> {
+    let newsource: Vec<&str> = source.lines().collect();
+    let mut ranges = Vec::new();
+    collect_ranges(root, &newsource, symbol, &mut ranges);
+    ranges
+}
+
+/// every occurrence is classified like the symbol under the cursor, so the keywords, builtins
+/// and the names in other roles are skipped
+fn collect_ranges(node: Node, source: &Vec<&str>, symbol: &RenameSymbol, output: &mut Vec<Range>) {
+    let mut course = node.walk();
+    for child in node.children(&mut course) {
+        if child.child_count() != 0 {
+            collect_ranges(child, source, symbol, output);
+            continue;
+        }
+        let same_name = match symbol.kind {
+            RenameKind::Command => {
+                get_node_content(source, &child).eq_ignore_ascii_case(&symbol.name)
+            }
+            RenameKind::Symbol => get_node_content(source, &child) == symbol.name,
+        };
+        if !same_name {
+            continue;
+        }
+        if let Some(found) = get_node_symbol(child, source) {
+            if found.kind == symbol.kind {
+                output.push(found.range);
+            }
+        }
+    }
+}
+
+/// rename the symbol in all the files
+pub fn rename(
+    symbol: &RenameSymbol,
+    new_name: &str,
+    files: &[PathBuf],
+    buffers: &HashMap<Url, Document>,
+) -> WorkspaceEdit {
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    for path in files {
+        let Ok(uri) = Url::from_file_path(path) else {
+            continue;
+        };
+        if changes.contains_key(&uri) {
+            continue;
+        }
+        let Some(document) = get_or_read(buffers, path) else {
+            continue;
+        };
+        let edits: Vec<TextEdit> = get_symbol_ranges(document.root_node(), &document.text, symbol)
+            .into_iter()
+            .map(|range| TextEdit {
+                range,
+                new_text: new_name.to_string(),
+            })
+            .collect();
+        if !edits.is_empty() {
+            changes.insert(uri, edits);
+        }
+    }
+    WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"function(add_foo name)
+  add_library(${name} foo.cpp)
+endfunction(add_foo)
+set(FOO_LIB foo)
+add_foo(${FOO_LIB})
+ADD_FOO(bar)
+target_link_libraries(app PUBLIC foo)
+if(FOO_LIB)
+  message("${FOO_LIB}")
+endif()
+"#;
+
+    #[test]
+    fn tst_rename_command() {
+        let document = Document::new(SOURCE.to_string(), 0);
+        let symbol = get_rename_symbol(Position::new(4, 2), document.root_node(), SOURCE).unwrap();
+        assert_eq!(symbol.kind, RenameKind::Command);
+        assert_eq!(symbol.name, "add_foo");
+        let ranges = get_symbol_ranges(document.root_node(), SOURCE, &symbol);
+        let lines: Vec<u32> = ranges.iter().map(|range| range.start.line).collect();
+        assert_eq!(lines, vec![0, 2, 4, 5]);
+    }
+
+    #[test]
+    fn tst_rename_variable() {
+        let document = Document::new(SOURCE.to_string(), 0);
+        // at the end of FOO_LIB
+        let symbol = get_rename_symbol(Position::new(3, 11), document.root_node(), SOURCE).unwrap();
+        assert_eq!(symbol.kind, RenameKind::Symbol);
+        assert_eq!(symbol.name, "FOO_LIB");
+        let ranges = get_symbol_ranges(document.root_node(), SOURCE, &symbol);
+        let lines: Vec<u32> = ranges.iter().map(|range| range.start.line).collect();
+        assert_eq!(lines, vec![3, 4, 7, 8]);
+
+        // the parameter of function
+        let symbol = get_rename_symbol(Position::new(0, 19), document.root_node(), SOURCE).unwrap();
+        assert_eq!(symbol.name, "name");
+        let ranges = get_symbol_ranges(document.root_node(), SOURCE, &symbol);
+        assert_eq!(ranges.len(), 2);
+    }
+
+    #[test]
+    fn tst_rename_target() {
+        let document = Document::new(SOURCE.to_string(), 0);
+        let symbol = get_rename_symbol(Position::new(6, 34), document.root_node(), SOURCE).unwrap();
+        assert_eq!(symbol.name, "foo");
+        let ranges = get_symbol_ranges(document.root_node(), SOURCE, &symbol);
+        let lines: Vec<u32> = ranges.iter().map(|range| range.start.line).collect();
+        assert_eq!(lines, vec![3, 6]);
+        // keywords can not be renamed
+        assert!(get_rename_symbol(Position::new(6, 27), document.root_node(), SOURCE).is_none());
+    }
+
+    #[test]
+    fn tst_rename_occurrences() {
+        let source = r#"set(NAME foo)
+get_filename_component(out ${NAME} NAME)
+add_library("foo" foo.cpp)
+message(STATUS "foo ${NAME}")
+"#;
+        let document = Document::new(source.to_string(), 0);
+        let symbol = get_rename_symbol(Position::new(0, 5), document.root_node(), source).unwrap();
+        assert_eq!(symbol.name, "NAME");
+        let ranges = get_symbol_ranges(document.root_node(), source, &symbol);
+        let lines: Vec<u32> = ranges.iter().map(|range| range.start.line).collect();
+        // the NAME keyword of get_filename_component is kept
+        assert_eq!(lines, vec![0, 1, 3]);
+
+        let symbol = get_rename_symbol(Position::new(2, 14), document.root_node(), source).unwrap();
+        assert_eq!(symbol.name, "foo");
+        let ranges = get_symbol_ranges(document.root_node(), source, &symbol);
+        assert_eq!(
+            ranges,
+            vec![
+                Range {
+                    start: Position::new(0, 9),
+                    end: Position::new(0, 12),
+                },
+                Range {
+                    start: Position::new(2, 13),
+                    end: Position::new(2, 16),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tst_valid_name() {
+        assert!(is_valid_name("foo_bar", RenameKind::Command));
+        assert!(!is_valid_name("foo::bar", RenameKind::Command));
+        assert!(is_valid_name("foo::bar", RenameKind::Symbol));
+        assert!(!is_valid_name("${foo}", RenameKind::Symbol));
+        assert!(!is_valid_name("", RenameKind::Symbol));
+    }
+}
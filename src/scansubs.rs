@@ -1,16 +1,20 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt,
     path::{Path, PathBuf},
 };
 
-use futures::{executor::block_on, Future};
+use async_lsp::ClientSocket;
+use futures::executor::block_on;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+use crate::document::{read_document, Document};
+use crate::progress::WorkDone;
 
 /// NOTE: key is be included path, value is the top CMakeLists
 /// This is used to find who is on the top of the CMakeLists
@@ -20,34 +24,107 @@ pub type TreeKey = HashMap<PathBuf, PathBuf>;
 // Cache the data of the struct
 pub static TREE_MAP: Lazy<Arc<Mutex<TreeKey>>> = Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
-pub fn schedule_scan_all(root: String) -> tokio::task::JoinHandle<()> {
+pub type ProjectFilesKV = HashMap<Option<PathBuf>, Vec<PathBuf>>;
+
+/// NOTE: the result of get_project_files for each root, it is cleared when TREE_MAP or the
+/// watched cmake files are changed
+static PROJECT_FILES: Lazy<Arc<Mutex<ProjectFilesKV>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+/// the roots of the scanned workspace folders, their top CMakeLists are in the project even
+/// without any add_subdirectory
+static FOLDERS: Lazy<Arc<Mutex<HashSet<PathBuf>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashSet::new())));
+
+/// bumped when PROJECT_FILES is cleared, the files found before it are not cached
+static PROJECT_FILES_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// the scan of a folder in the background, it is cancelled instead of joined, so the main loop
+/// is never blocked by it
+#[derive(Debug)]
+pub struct ScanHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ScanHandle {
+    /// stop the scan, nothing is added to TREE_MAP by it after this returns
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+pub fn schedule_scan_all(root: String, client: ClientSocket) -> ScanHandle {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let scan_cancelled = cancelled.clone();
     tokio::task::spawn_blocking(move || {
-        block_on(scan_all(root));
-    })
+        block_on(async {
+            let progress = WorkDone::begin(&client, &format!("Scanning {root}")).await;
+            let count = scan_all(&root, Some(&progress), &scan_cancelled).await;
+            progress.end(format!("{count} CMakeLists scanned"));
+        });
+    });
+    ScanHandle { cancelled }
 }
 
-pub async fn scan_all<P: AsRef<Path>>(project_root: P) {
+/// scan the CMakeLists of the project, it stops when the progress or the scan is cancelled.
+/// the count of the scanned files is returned
+pub async fn scan_all<P: AsRef<Path>>(
+    project_root: P,
+    progress: Option<&WorkDone>,
+    cancelled: &AtomicBool,
+) -> usize {
+    FOLDERS
+        .lock()
+        .await
+        .insert(project_root.as_ref().to_path_buf());
     let root_cmake = project_root.as_ref().join("CMakeLists.txt");
     let mut to_scan: Vec<PathBuf> = vec![root_cmake];
+    let mut count = 0;
     while !to_scan.is_empty() {
         let mut next_to_scan = Vec::new();
         for scan_cmake in to_scan.iter() {
-            let mut out = scan_dir(scan_cmake).await;
+            if cancelled.load(Ordering::SeqCst)
+                || progress.is_some_and(|progress| progress.is_cancelled())
+            {
+                return count;
+            }
+            let mut out = scan_dir_inner(scan_cmake);
+            if !insert_tree(scan_cmake, &out, Some(cancelled)).await {
+                return count;
+            }
             next_to_scan.append(&mut out);
+            count += 1;
+            if let Some(progress) = progress {
+                progress.report(format!("{}", scan_cmake.display()), None);
+            }
         }
         to_scan = next_to_scan;
     }
+    count
 }
 
 pub async fn scan_dir<P: AsRef<Path>>(path: P) -> Vec<PathBuf> {
     let bufs = scan_dir_inner(path.as_ref());
-    let mut tree = TREE_MAP.lock().await;
-    for subpath in bufs.iter() {
-        tree.insert(subpath.to_path_buf(), path.as_ref().into());
-    }
+    insert_tree(path.as_ref(), &bufs, None).await;
     bufs
 }
 
+/// add the subdirectories of the CMakeLists to TREE_MAP, false if the scan is cancelled
+async fn insert_tree(path: &Path, subpaths: &[PathBuf], cancelled: Option<&AtomicBool>) -> bool {
+    {
+        let mut tree = TREE_MAP.lock().await;
+        // NOTE: checked with the lock, so remove_folder after the cancel sees all the entries
+        if cancelled.is_some_and(|cancelled| cancelled.load(Ordering::SeqCst)) {
+            return false;
+        }
+        for subpath in subpaths {
+            tree.insert(subpath.to_path_buf(), path.into());
+        }
+    }
+    clear_project_files().await;
+    true
+}
+
 pub fn scan_dir_inner<P: AsRef<Path>>(path: P) -> Vec<PathBuf> {
     let Ok(source) = std::fs::read_to_string(path.as_ref()) else {
         return Vec::new();
@@ -103,6 +180,142 @@ fn scan_node<P: AsRef<Path>>(source: &Vec<&str>, tree: tree_sitter::Node, path:
     bufs
 }
 
+/// remove the CMakeLists of the workspace folder from TREE_MAP
+pub async fn remove_folder<P: AsRef<Path>>(root: P) {
+    TREE_MAP
+        .lock()
+        .await
+        .retain(|sub, _| !sub.starts_with(root.as_ref()));
+    FOLDERS.lock().await.remove(root.as_ref());
+    clear_project_files().await;
+}
+
+/// forget the cached project files, call it when a cmake file is created, changed or deleted
+pub async fn clear_project_files() {
+    PROJECT_FILES_GENERATION.fetch_add(1, Ordering::SeqCst);
+    PROJECT_FILES.lock().await.clear();
+}
+
+/// the same as get_project_files, but the files are cached until clear_project_files
+pub async fn get_cached_project_files(root: Option<&Path>) -> Vec<PathBuf> {
+    let key = root.map(Path::to_path_buf);
+    if let Some(files) = PROJECT_FILES.lock().await.get(&key) {
+        return files.clone();
+    }
+    let generation = PROJECT_FILES_GENERATION.load(Ordering::SeqCst);
+    let files = get_project_files(root).await;
+    let mut cache = PROJECT_FILES.lock().await;
+    if generation == PROJECT_FILES_GENERATION.load(Ordering::SeqCst) {
+        cache.insert(key, files.clone());
+    }
+    files
+}
+
+/// get the top CMakeLists of the folders, all the CMakeLists in TREE_MAP, and the local cmake
+/// files included by them
+/// when the root is given, only the CMakeLists of the workspace folder are used
+pub async fn get_project_files(root: Option<&Path>) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    for folder in FOLDERS.lock().await.iter() {
+        if root.is_some_and(|root| !folder.starts_with(root)) {
+            continue;
+        }
+        let path = folder.join("CMakeLists.txt");
+        if path.is_file() && visited.insert(path.clone()) {
+            files.push(path);
+        }
+    }
+    {
+        let tree = TREE_MAP.lock().await;
+        for (sub, top) in tree.iter() {
+            if root.is_some_and(|root| !sub.starts_with(root)) {
+                continue;
+            }
+            for path in [top, sub] {
+                if path.is_file() && visited.insert(path.clone()) {
+                    files.push(path.clone());
+                }
+            }
+        }
+    }
+    files.sort();
+    let mut index = 0;
+    while index < files.len() {
+        for include in scan_include_files(&files[index]) {
+            if visited.insert(include.clone()) {
+                files.push(include);
+            }
+        }
+        index += 1;
+    }
+    files
+}
+
+/// get the local cmake files included by the file, the buildin modules are ignored
+pub fn scan_include_files<P: AsRef<Path>>(path: P) -> Vec<PathBuf> {
+    match read_document(path.as_ref()) {
+        Some(document) => get_include_files(path, &document),
+        None => Vec::new(),
+    }
+}
+
+/// the same as scan_include_files, but the document is given, like the opened buffer
+pub fn get_include_files<P: AsRef<Path>>(path: P, document: &Document) -> Vec<PathBuf> {
+    let newsource: Vec<&str> = document.text.lines().collect();
+    let mut output = Vec::new();
+    scan_include_node(&newsource, document.root_node(), path.as_ref(), &mut output);
+    output
+}
+
+fn scan_include_node(
+    source: &Vec<&str>,
+    tree: tree_sitter::Node,
+    path: &Path,
+    output: &mut Vec<PathBuf>,
+) {
+    let mut course = tree.walk();
+    for node in tree.children(&mut course) {
+        if node.kind() != "normal_command" {
+            scan_include_node(source, node, path, output);
+            continue;
+        }
+        let Some(ids) = node.child(0) else {
+            continue;
+        };
+        let h = ids.start_position().row;
+        let x = ids.start_position().column;
+        let y = ids.end_position().column;
+        if source[h][x..y].to_lowercase() != "include" {
+            continue;
+        }
+        let Some(first_arg) = node.child(2).and_then(|arguments| arguments.child(0)) else {
+            continue;
+        };
+        if first_arg.start_position().row != first_arg.end_position().row {
+            continue;
+        }
+        let h = first_arg.start_position().row;
+        let x = first_arg.start_position().column;
+        let y = first_arg.end_position().column;
+        let name = remove_quotation(&source[h][x..y]);
+        let name = name
+            .strip_prefix("${CMAKE_CURRENT_LIST_DIR}/")
+            .or_else(|| name.strip_prefix("${CMAKE_CURRENT_SOURCE_DIR}/"))
+            .unwrap_or(name);
+        // NOTE: buildin modules do not have extension
+        if name.contains('$') || Path::new(name).extension().is_none() {
+            continue;
+        }
+        // NOTE: components will remove the `.` in the path
+        let include_path: PathBuf = path.parent().unwrap().join(name).components().collect();
+        if include_path.is_file() {
+            output.push(include_path);
+        }
+    }
+}
+
+#[allow(unused)]
 #[derive(Deserialize, Debug, Serialize, Clone)]
 pub struct TreeDir {
     dir: PathBuf,
@@ -128,6 +341,7 @@ impl fmt::Display for TreeDir {
 }
 
 // Path Input is xxx/CMakeLists.txt
+#[allow(unused)]
 pub fn get_treedir(path: &Path) -> Option<TreeDir> {
     let Ok(content) = std::fs::read_to_string(path) else {
         return None;
@@ -154,6 +368,7 @@ pub fn get_treedir(path: &Path) -> Option<TreeDir> {
     Some(top)
 }
 
+#[allow(unused)]
 fn get_subdir_from_tree(
     source: &Vec<&str>,
     tree: tree_sitter::Node,
@@ -204,6 +419,21 @@ fn remove_quotation(input: &str) -> &str {
     }
 }
 
+#[test]
+fn tst_scan_include_files() {
+    let path = std::env::current_dir()
+        .unwrap()
+        .join("test")
+        .join("CMakeLists.txt");
+    let includes = scan_include_files(&path);
+    let names: Vec<&str> = includes
+        .iter()
+        .map(|include| include.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["test1.cmake", "test3.cmake", "DWayland.cmake"]);
+    assert!(includes.iter().all(|include| include.is_file()));
+}
+
 #[test]
 fn tst_quotantion() {
     let a = r#"
@@ -214,3 +444,34 @@ fn tst_quotantion() {
     let b = "sdfds";
     assert_eq!(b, "sdfds");
 }
+
+#[test]
+fn tst_cancel_scan() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path().to_path_buf();
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("CMakeLists.txt"), "add_subdirectory(sub)\n").unwrap();
+    std::fs::write(dir.join("sub").join("CMakeLists.txt"), "").unwrap();
+    let cancelled = AtomicBool::new(true);
+    assert_eq!(block_on(scan_all(&dir, None, &cancelled)), 0);
+    assert!(!block_on(TREE_MAP.lock()).contains_key(&dir.join("sub").join("CMakeLists.txt")));
+    cancelled.store(false, Ordering::SeqCst);
+    assert_eq!(block_on(scan_all(&dir, None, &cancelled)), 2);
+    assert!(block_on(TREE_MAP.lock()).contains_key(&dir.join("sub").join("CMakeLists.txt")));
+    block_on(remove_folder(&dir));
+}
+
+#[test]
+fn tst_project_files_without_subdirectory() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path().to_path_buf();
+    std::fs::write(dir.join("CMakeLists.txt"), "include(helper.cmake)\n").unwrap();
+    std::fs::write(dir.join("helper.cmake"), "").unwrap();
+    block_on(scan_all(&dir, None, &AtomicBool::new(false)));
+    let files = block_on(get_project_files(Some(&dir)));
+    block_on(remove_folder(&dir));
+    assert_eq!(
+        files,
+        [dir.join("CMakeLists.txt"), dir.join("helper.cmake")]
+    );
+}
@@ -0,0 +1,96 @@
+/// provide selectionRange, expand from the token to its parents in the syntax tree
+use async_lsp::lsp_types;
+use lsp_types::{Position, Range, SelectionRange};
+use tree_sitter::Node;
+
+use crate::utils::treehelper::{point_to_position, position_to_point};
+
+pub fn get_selection_ranges(root: Node, positions: &[Position]) -> Vec<SelectionRange> {
+    positions
+        .iter()
+        .map(|position| get_selection_range(root, *position))
+        .collect()
+}
+
+/// NOTE: the body contains the whitespace around it, only select its commands
+fn node_range(node: Node) -> Range {
+    if node.kind() == "body" && node.named_child_count() != 0 {
+        if let (Some(first), Some(last)) = (
+            node.named_child(0),
+            node.named_child(node.named_child_count() - 1),
+        ) {
+            return Range {
+                start: point_to_position(first.start_position()),
+                end: point_to_position(last.end_position()),
+            };
+        }
+    }
+    Range {
+        start: point_to_position(node.start_position()),
+        end: point_to_position(node.end_position()),
+    }
+}
+
+fn get_selection_range(root: Node, position: Position) -> SelectionRange {
+    let point = position_to_point(position);
+    let Some(node) = root.descendant_for_point_range(point, point) else {
+        return SelectionRange {
+            range: Range::new(position, position),
+            parent: None,
+        };
+    };
+    // from the outermost to the innermost, the parents with the same range are skipped
+    let mut ranges: Vec<Range> = Vec::new();
+    let mut current = Some(node);
+    while let Some(node) = current {
+        let range = node_range(node);
+        if ranges.last() != Some(&range) {
+            ranges.push(range);
+        }
+        current = node.parent();
+    }
+    let mut selection: Option<SelectionRange> = None;
+    for range in ranges.into_iter().rev() {
+        selection = Some(SelectionRange {
+            range,
+            parent: selection.map(Box::new),
+        });
+    }
+    selection.unwrap()
+}
+
+#[test]
+fn tst_selection_range() {
+    use crate::document::Document;
+    let source = r#"if(WIN32)
+  set(FOO "a ${BAR}")
+endif()
+"#;
+    let document = Document::new(source.to_string(), 0);
+    let selections = get_selection_ranges(document.root_node(), &[Position::new(1, 16)]);
+    let mut ranges = Vec::new();
+    let mut selection = Some(&selections[0]);
+    while let Some(current) = selection {
+        let range = current.range;
+        ranges.push((
+            range.start.line,
+            range.start.character,
+            range.end.line,
+            range.end.character,
+        ));
+        selection = current.parent.as_deref();
+    }
+    assert_eq!(
+        ranges,
+        vec![
+            (1, 15, 1, 18),
+            (1, 13, 1, 19),
+            (1, 11, 1, 19),
+            (1, 10, 1, 20),
+            (1, 6, 1, 20),
+            (1, 2, 1, 21),
+            (0, 0, 2, 7),
+            (0, 0, 3, 0),
+        ]
+    );
+}
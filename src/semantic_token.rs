@@ -0,0 +1,699 @@
+/// Semantic token provider, it gives better highlight for clients which only have
+/// textmate highlight, like vscode
+use async_lsp::lsp_types;
+use lsp_types::{
+    Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens,
+    SemanticTokensLegend,
+};
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tree_sitter::Node;
+
+use crate::complete::{BUILDIN_COMMAND, BUILDIN_VARIABLE};
+use crate::document::get_or_read;
+use crate::indexing::get_indexed;
+use crate::languageserver::BUFFERS_CACHE;
+use crate::scansubs::{scan_include_files, TREE_MAP};
+use crate::utils::get_node_content;
+
+pub const LEGEND_TYPE: &[SemanticTokenType] = &[
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::STRING,
+    SemanticTokenType::COMMENT,
+];
+
+pub const LEGEND_MODIFIER: &[SemanticTokenModifier] = &[
+    SemanticTokenModifier::DEFAULT_LIBRARY,
+    SemanticTokenModifier::STATIC,
+    SemanticTokenModifier::DECLARATION,
+];
+
+// index in LEGEND_TYPE
+const TYPE_FUNCTION: u32 = 0;
+const TYPE_MACRO: u32 = 1;
+const TYPE_VARIABLE: u32 = 2;
+const TYPE_KEYWORD: u32 = 3;
+const TYPE_OPERATOR: u32 = 4;
+const TYPE_STRING: u32 = 5;
+const TYPE_COMMENT: u32 = 6;
+
+// bit in LEGEND_MODIFIER
+const MODIFIER_BUILDIN: u32 = 1;
+const MODIFIER_CACHE: u32 = 1 << 1;
+const MODIFIER_DECLARATION: u32 = 1 << 2;
+
+/// keywords used in the arguments of the commands
+pub const ARGUMENT_KEYWORDS: &[&str] = &[
+    "PUBLIC",
+    "PRIVATE",
+    "INTERFACE",
+    "COMPONENTS",
+    "OPTIONAL_COMPONENTS",
+    "REQUIRED",
+    "CONFIG",
+    "MODULE",
+    "QUIET",
+    "CACHE",
+    "FORCE",
+    "PARENT_SCOPE",
+    "STATIC",
+    "SHARED",
+    "OBJECT",
+    "IMPORTED",
+    "ALIAS",
+    "GLOBAL",
+    "EXCLUDE_FROM_ALL",
+    "TARGETS",
+    "FILES",
+    "DIRECTORY",
+    "DESTINATION",
+    "COMPONENT",
+    "EXPORT",
+    "RUNTIME",
+    "LIBRARY",
+    "ARCHIVE",
+    "INCLUDES",
+    "PROPERTIES",
+    "APPEND",
+    "BEFORE",
+    "AFTER",
+    "SYSTEM",
+    "COMMAND",
+    "DEPENDS",
+    "WORKING_DIRECTORY",
+    "VERSION",
+    "LANGUAGES",
+    "DESCRIPTION",
+    "HOMEPAGE_URL",
+    "IMPORTED_TARGET",
+];
+
+/// keywords in if() and while()
+pub const CONDITION_KEYWORDS: &[&str] = &[
+    "NOT",
+    "AND",
+    "OR",
+    "DEFINED",
+    "COMMAND",
+    "POLICY",
+    "TARGET",
+    "TEST",
+    "EXISTS",
+    "IS_NEWER_THAN",
+    "IS_DIRECTORY",
+    "IS_SYMLINK",
+    "IS_ABSOLUTE",
+    "IS_READABLE",
+    "IS_WRITABLE",
+    "IS_EXECUTABLE",
+    "MATCHES",
+    "LESS",
+    "GREATER",
+    "EQUAL",
+    "LESS_EQUAL",
+    "GREATER_EQUAL",
+    "STRLESS",
+    "STRGREATER",
+    "STREQUAL",
+    "STRLESS_EQUAL",
+    "STRGREATER_EQUAL",
+    "VERSION_LESS",
+    "VERSION_GREATER",
+    "VERSION_EQUAL",
+    "VERSION_LESS_EQUAL",
+    "VERSION_GREATER_EQUAL",
+    "PATH_EQUAL",
+    "IN_LIST",
+];
+
+/// constants in if(), they are not variables
+const CONDITION_CONSTANTS: &[&str] = &[
+    "ON", "OFF", "TRUE", "FALSE", "YES", "NO", "Y", "N", "IGNORE", "NOTFOUND",
+];
+
+/// NOTE: they are only forced after the builtin commands and variables are indexed, so the main
+/// loop never waits for `cmake --help-*`
+static BUILDIN_COMMAND_NAMES: Lazy<HashSet<String>> = Lazy::new(|| match &*BUILDIN_COMMAND {
+    Ok(items) => items.iter().map(|item| item.label.to_lowercase()).collect(),
+    Err(_) => HashSet::new(),
+});
+
+static BUILDIN_VARIABLE_NAMES: Lazy<HashSet<String>> = Lazy::new(|| match &*BUILDIN_VARIABLE {
+    Ok(items) => items.iter().map(|item| item.label.clone()).collect(),
+    Err(_) => HashSet::new(),
+});
+
+/// whether it is a builtin command, the name is lowercase. false while they are being indexed
+pub fn is_buildin_command(name: &str) -> bool {
+    get_indexed(&BUILDIN_COMMAND).is_some() && BUILDIN_COMMAND_NAMES.contains(name)
+}
+
+/// whether it is a builtin variable, false while they are being indexed
+pub fn is_buildin_variable(name: &str) -> bool {
+    get_indexed(&BUILDIN_VARIABLE).is_some() && BUILDIN_VARIABLE_NAMES.contains(name)
+}
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: LEGEND_TYPE.to_vec(),
+        token_modifiers: LEGEND_MODIFIER.to_vec(),
+    }
+}
+
+/// the macros defined in the parent CMakeLists and their included files
+pub async fn get_user_macros<P: AsRef<Path>>(path: P) -> HashSet<String> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    {
+        let tree_map = TREE_MAP.lock().await;
+        let mut path = path.as_ref().to_path_buf();
+        while let Some(parent) = tree_map.get(&path) {
+            if files.contains(parent) {
+                break;
+            }
+            files.push(parent.clone());
+            path.clone_from(parent);
+        }
+    }
+    for parent in files.clone() {
+        files.append(&mut scan_include_files(parent));
+    }
+    let buffers = BUFFERS_CACHE.lock().await;
+    let mut macros = HashSet::new();
+    for file in files {
+        if let Some(document) = get_or_read(&buffers, &file) {
+            let source: Vec<&str> = document.text.lines().collect();
+            collect_macros(document.root_node(), &source, &mut macros);
+        }
+    }
+    macros
+}
+
+/// the name of the macro_def node, in lowercase
+fn macro_name(node: Node, source: &[&str]) -> Option<String> {
+    let name = node.child(0)?.child(2)?.child(0)?;
+    Some(get_node_content(source, &name).to_lowercase())
+}
+
+fn collect_macros(node: Node, source: &[&str], macros: &mut HashSet<String>) {
+    let mut course = node.walk();
+    for child in node.children(&mut course) {
+        if child.kind() == "macro_def" {
+            macros.extend(macro_name(child, source));
+        }
+        collect_macros(child, source, macros);
+    }
+}
+
+struct RawToken {
+    line: u32,
+    start: u32,
+    length: u32,
+    token_type: u32,
+    modifiers: u32,
+}
+
+struct TokenCollector<'a> {
+    source: Vec<&'a str>,
+    macros: HashSet<String>,
+    cache_variables: HashSet<String>,
+    tokens: Vec<RawToken>,
+}
+
+/// get the semantic tokens of the document, if range is given, only the tokens inside the
+/// lines of the range are returned
+/// user_macros are the macros defined in the parent CMakeLists or included files
+pub fn get_semantic_tokens(
+    source: &str,
+    root: Node,
+    user_macros: HashSet<String>,
+    range: Option<Range>,
+) -> SemanticTokens {
+    let mut collector = TokenCollector {
+        source: source.lines().collect(),
+        macros: user_macros,
+        cache_variables: HashSet::new(),
+        tokens: Vec::new(),
+    };
+    collector.collect_definitions(root);
+    collector.walk(root, None);
+
+    let mut tokens = collector.tokens;
+    tokens.sort_by_key(|token| (token.line, token.start));
+    if let Some(range) = range {
+        tokens.retain(|token| token.line >= range.start.line && token.line <= range.end.line);
+    }
+
+    let mut data = Vec::with_capacity(tokens.len());
+    let mut pre_line = 0;
+    let mut pre_start = 0;
+    let mut pre_end = 0;
+    for token in tokens {
+        let delta_line = token.line - pre_line;
+        // NOTE: tokens must not overlap
+        if delta_line == 0 && !data.is_empty() && token.start < pre_end {
+            continue;
+        }
+        let delta_start = if delta_line == 0 {
+            token.start - pre_start
+        } else {
+            token.start
+        };
+        data.push(SemanticToken {
+            delta_line,
+            delta_start,
+            length: token.length,
+            token_type: token.token_type,
+            token_modifiers_bitset: token.modifiers,
+        });
+        pre_line = token.line;
+        pre_start = token.start;
+        pre_end = token.start + token.length;
+    }
+    SemanticTokens {
+        result_id: None,
+        data,
+    }
+}
+
+impl TokenCollector<'_> {
+    /// find the macros and cache variables defined in this document
+    fn collect_definitions(&mut self, node: Node) {
+        let mut course = node.walk();
+        for child in node.children(&mut course) {
+            match child.kind() {
+                "macro_def" => {
+                    self.macros.extend(macro_name(child, &self.source));
+                    self.collect_definitions(child);
+                }
+                "normal_command" => {
+                    let Some(identifier) = child.child(0) else {
+                        continue;
+                    };
+                    let command_name = get_node_content(&self.source, &identifier).to_lowercase();
+                    let Some(arguments) = child.child(2) else {
+                        continue;
+                    };
+                    let Some(name) = arguments.child(0) else {
+                        continue;
+                    };
+                    let is_cache = match command_name.as_str() {
+                        "option" => true,
+                        "set" => {
+                            let mut argcourse = arguments.walk();
+                            let is_cache = arguments.children(&mut argcourse).any(|argument| {
+                                get_node_content(&self.source, &argument) == "CACHE"
+                            });
+                            is_cache
+                        }
+                        _ => false,
+                    };
+                    if is_cache {
+                        self.cache_variables
+                            .insert(get_node_content(&self.source, &name));
+                    }
+                }
+                _ => self.collect_definitions(child),
+            }
+        }
+    }
+
+    fn push_node(&mut self, node: Node, token_type: u32, modifiers: u32) {
+        let start = node.start_position();
+        let end = node.end_position();
+        for row in start.row..=end.row {
+            let Some(line) = self.source.get(row) else {
+                break;
+            };
+            let start_column = if row == start.row { start.column } else { 0 };
+            let end_column = if row == end.row {
+                end.column.min(line.len())
+            } else {
+                line.len()
+            };
+            self.push(row, start_column, end_column, token_type, modifiers);
+        }
+    }
+
+    /// push the token in one line, the columns are bytes, convert them to utf16
+    fn push(&mut self, row: usize, start: usize, end: usize, token_type: u32, modifiers: u32) {
+        let Some(line) = self.source.get(row) else {
+            return;
+        };
+        if start >= end {
+            return;
+        }
+        let (Some(before), Some(content)) = (line.get(..start), line.get(start..end)) else {
+            return;
+        };
+        self.tokens.push(RawToken {
+            line: row as u32,
+            start: before.encode_utf16().count() as u32,
+            length: content.encode_utf16().count() as u32,
+            token_type,
+            modifiers,
+        });
+    }
+
+    fn variable_modifiers(&self, name: &str) -> u32 {
+        if is_buildin_variable(name) || name.starts_with("CMAKE_") {
+            MODIFIER_BUILDIN
+        } else if self.cache_variables.contains(name) {
+            MODIFIER_CACHE
+        } else {
+            0
+        }
+    }
+
+    /// command is the lowercase name of the command which the node belongs to
+    fn walk(&mut self, node: Node, command: Option<&str>) {
+        let mut course = node.walk();
+        for child in node.children(&mut course) {
+            match child.kind() {
+                "line_comment" | "bracket_comment" => {
+                    self.push_node(child, TYPE_COMMENT, 0);
+                }
+                "bracket_argument" => {
+                    self.push_node(child, TYPE_STRING, 0);
+                }
+                "if" | "elseif" | "else" | "endif" | "foreach" | "endforeach" | "while"
+                | "endwhile" | "function" | "endfunction" | "macro" | "endmacro" | "block"
+                | "endblock" => {
+                    self.push_node(child, TYPE_KEYWORD, 0);
+                }
+                "function_command" | "macro_command" => {
+                    self.walk_definition(child);
+                }
+                "if_command" | "elseif_command" | "while_command" => {
+                    self.walk(child, Some("if"));
+                }
+                "normal_command" => {
+                    self.walk_normal_command(child);
+                }
+                "variable_ref" => {
+                    self.walk_variable_ref(child);
+                }
+                "argument" => {
+                    self.walk_argument(child, command);
+                }
+                _ => self.walk(child, command),
+            }
+        }
+    }
+
+    fn walk_definition(&mut self, node: Node) {
+        let mut course = node.walk();
+        for child in node.children(&mut course) {
+            match child.kind() {
+                "function" | "macro" => self.push_node(child, TYPE_KEYWORD, 0),
+                "argument_list" => {
+                    let token_type = if node.kind() == "macro_command" {
+                        TYPE_MACRO
+                    } else {
+                        TYPE_FUNCTION
+                    };
+                    let mut argcourse = child.walk();
+                    let mut is_name = true;
+                    for argument in child.children(&mut argcourse) {
+                        if argument.kind() != "argument" {
+                            self.walk(argument, None);
+                            continue;
+                        }
+                        if is_name {
+                            self.push_node(argument, token_type, MODIFIER_DECLARATION);
+                            is_name = false;
+                        } else {
+                            // parameters
+                            self.push_node(argument, TYPE_VARIABLE, MODIFIER_DECLARATION);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn walk_normal_command(&mut self, node: Node) {
+        let Some(identifier) = node.child(0) else {
+            return;
+        };
+        let command_name = get_node_content(&self.source, &identifier).to_lowercase();
+        if self.macros.contains(&command_name) {
+            self.push_node(identifier, TYPE_MACRO, 0);
+        } else if is_buildin_command(&command_name) {
+            self.push_node(identifier, TYPE_FUNCTION, MODIFIER_BUILDIN);
+        } else {
+            self.push_node(identifier, TYPE_FUNCTION, 0);
+        }
+        let Some(arguments) = node.child(2) else {
+            return;
+        };
+        if matches!(command_name.as_str(), "set" | "option") {
+            if let Some(name) = arguments.child(0) {
+                if name.kind() == "argument" && !has_variable_ref(name) {
+                    let modifiers = self.variable_modifiers(&get_node_content(&self.source, &name));
+                    self.push_node(name, TYPE_VARIABLE, modifiers | MODIFIER_DECLARATION);
+                }
+            }
+        }
+        self.walk(arguments, Some(&command_name));
+    }
+
+    fn walk_variable_ref(&mut self, node: Node) {
+        let Some(var) = node.child(0) else {
+            return;
+        };
+        let mut course = var.walk();
+        for child in var.children(&mut course) {
+            if child.kind() != "variable" {
+                continue;
+            }
+            if has_variable_ref(child) {
+                // like ${${name}_DIR}, only highlight the inner one
+                self.walk(child, None);
+                continue;
+            }
+            let name = get_node_content(&self.source, &child);
+            let modifiers = match var.kind() {
+                "cache_var" => MODIFIER_CACHE,
+                "env_var" => 0,
+                _ => self.variable_modifiers(&name),
+            };
+            self.push_node(child, TYPE_VARIABLE, modifiers);
+        }
+    }
+
+    fn walk_argument(&mut self, node: Node, command: Option<&str>) {
+        let Some(content) = node.child(0) else {
+            return;
+        };
+        match content.kind() {
+            "bracket_argument" => self.push_node(content, TYPE_STRING, 0),
+            "unquoted_argument" => {
+                self.walk_generator_expression(content);
+                if content.child_count() != 0 {
+                    self.walk(content, command);
+                    return;
+                }
+                let text = get_node_content(&self.source, &content);
+                if ARGUMENT_KEYWORDS.contains(&text.as_str()) {
+                    self.push_node(content, TYPE_KEYWORD, 0);
+                    return;
+                }
+                if command != Some("if") {
+                    return;
+                }
+                if CONDITION_KEYWORDS.contains(&text.as_str()) {
+                    self.push_node(content, TYPE_KEYWORD, 0);
+                } else if is_variable_name(&text)
+                    && !CONDITION_CONSTANTS.contains(&text.to_uppercase().as_str())
+                {
+                    let modifiers = self.variable_modifiers(&text);
+                    self.push_node(content, TYPE_VARIABLE, modifiers);
+                }
+            }
+            "quoted_argument" => {
+                if let Some(element) = content.child(1) {
+                    if element.kind() == "quoted_element" {
+                        self.walk_generator_expression(element);
+                    }
+                }
+                self.walk(content, command);
+            }
+            _ => {}
+        }
+    }
+
+    /// highlight the `$<NAME:` and the `>` of generator expressions, only in one line
+    fn walk_generator_expression(&mut self, node: Node) {
+        let start = node.start_position();
+        if start.row != node.end_position().row {
+            return;
+        }
+        let text = get_node_content(&self.source, &node);
+        let bytes = text.as_bytes();
+        let mut stack = Vec::new();
+        let mut index = 0;
+        while index < bytes.len() {
+            if bytes[index] == b'$' && bytes.get(index + 1) == Some(&b'<') {
+                let mut end = index + 2;
+                while end < bytes.len()
+                    && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_')
+                {
+                    end += 1;
+                }
+                if bytes.get(end) == Some(&b':') {
+                    end += 1;
+                }
+                self.push(
+                    start.row,
+                    start.column + index,
+                    start.column + end,
+                    TYPE_OPERATOR,
+                    0,
+                );
+                stack.push(index);
+                index = end;
+                continue;
+            }
+            if bytes[index] == b'>' && stack.pop().is_some() {
+                self.push(
+                    start.row,
+                    start.column + index,
+                    start.column + index + 1,
+                    TYPE_OPERATOR,
+                    0,
+                );
+            }
+            index += 1;
+        }
+    }
+}
+
+fn has_variable_ref(node: Node) -> bool {
+    if node.kind() == "variable_ref" {
+        return true;
+    }
+    let mut course = node.walk();
+    let result = node.children(&mut course).any(has_variable_ref);
+    result
+}
+
+fn is_variable_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+
+    fn get_tokens(source: &str) -> Vec<SemanticToken> {
+        let mut parse = tree_sitter::Parser::new();
+        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
+        let tree = parse.parse(source, None).unwrap();
+        get_semantic_tokens(source, tree.root_node(), HashSet::new(), None).data
+    }
+
+    #[test]
+    fn tst_bracket_argument() {
+        let source = include_str!("../assert/highlight/bracket_argument.cmake");
+        let tokens = get_tokens(source);
+        let strings: Vec<&SemanticToken> = tokens
+            .iter()
+            .filter(|token| token.token_type == TYPE_STRING)
+            .collect();
+        // the bracket argument is in two lines
+        assert_eq!(strings.len(), 2);
+        assert_eq!(strings[0].delta_start, 2);
+        assert_eq!(strings[0].length, 5);
+        assert_eq!(strings[1].delta_line, 1);
+        assert_eq!(strings[1].length, 4);
+    }
+
+    #[test]
+    fn tst_function_and_macro() {
+        let source = r#"macro(mymacro arg)
+endmacro()
+function(myfunc)
+endfunction()
+mymacro(${arg})
+myfunc(A PUBLIC "$<TARGET_FILE:tgt>")
+# comment
+"#;
+        let tokens = get_tokens(source);
+        let types: Vec<(u32, u32)> = tokens
+            .iter()
+            .map(|token| (token.token_type, token.token_modifiers_bitset))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (TYPE_KEYWORD, 0),
+                (TYPE_MACRO, MODIFIER_DECLARATION),
+                (TYPE_VARIABLE, MODIFIER_DECLARATION),
+                (TYPE_KEYWORD, 0),
+                (TYPE_KEYWORD, 0),
+                (TYPE_FUNCTION, MODIFIER_DECLARATION),
+                (TYPE_KEYWORD, 0),
+                (TYPE_MACRO, 0),
+                (TYPE_VARIABLE, 0),
+                (TYPE_FUNCTION, 0),
+                (TYPE_KEYWORD, 0),
+                (TYPE_OPERATOR, 0),
+                (TYPE_OPERATOR, 0),
+                (TYPE_COMMENT, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn tst_condition_variable() {
+        let source = r#"option(BUILD_TEST "test" ON)
+set(USER_VAR A)
+if(BUILD_TEST AND USER_VAR AND CMAKE_BUILD_TYPE STREQUAL "Debug" AND ON)
+endif()
+"#;
+        let tokens = get_tokens(source);
+        let variables: Vec<u32> = tokens
+            .iter()
+            .filter(|token| token.token_type == TYPE_VARIABLE)
+            .map(|token| token.token_modifiers_bitset)
+            .collect();
+        assert_eq!(
+            variables,
+            vec![
+                MODIFIER_CACHE | MODIFIER_DECLARATION,
+                MODIFIER_DECLARATION,
+                MODIFIER_CACHE,
+                0,
+                MODIFIER_BUILDIN
+            ]
+        );
+    }
+
+    #[test]
+    fn tst_collect_macros() {
+        let source =
+            "function(Func)\nendfunction()\nif(A)\n  macro(My_Macro)\n  endmacro()\nendif()\n";
+        let mut parse = tree_sitter::Parser::new();
+        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
+        let tree = parse.parse(source, None).unwrap();
+        let mut macros = HashSet::new();
+        collect_macros(
+            tree.root_node(),
+            &source.lines().collect::<Vec<_>>(),
+            &mut macros,
+        );
+        assert_eq!(macros, HashSet::from(["my_macro".to_string()]));
+    }
+}
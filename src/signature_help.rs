@@ -0,0 +1,603 @@
+/// provide signatureHelp, the signatures of the builtin commands are parsed from
+/// `cmake --help-commands`, and the user defined ones are built from their definitions
+use async_lsp::lsp_types;
+use lsp_types::{
+    Documentation, ParameterInformation, ParameterLabel, Position, SignatureHelp,
+    SignatureInformation, Url,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tree_sitter::Node;
+
+use crate::document::{get_or_read, position_to_byte, Document};
+use crate::indexing::get_indexed;
+use crate::utils::get_node_content;
+use crate::utils::treehelper::MESSAGE_STORAGE;
+
+/// the command under the cursor and the arguments typed before the cursor
+#[derive(Debug, PartialEq, Eq)]
+pub struct CommandContext {
+    pub name: String,
+    pub arguments: Vec<String>,
+    /// the index of the argument which the cursor is in
+    pub current: usize,
+}
+
+pub fn get_signature_help(
+    document: &Document,
+    location: Position,
+    files: &[PathBuf],
+    buffers: &HashMap<Url, Document>,
+) -> Option<SignatureHelp> {
+    let context = get_command_context(document.root_node(), &document.text, location)?;
+    let lower_name = context.name.to_lowercase();
+    let mut signatures: Vec<SignatureInformation> = get_indexed(&MESSAGE_STORAGE)
+        .and_then(|storage| storage.get(&lower_name))
+        .map(|doc| parse_signatures(&lower_name, doc))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|label| new_signature(label, None))
+        .collect();
+    if signatures.is_empty() {
+        signatures.push(find_user_signature(&lower_name, document, files, buffers)?);
+    }
+    let labels: Vec<&str> = signatures.iter().map(|sig| sig.label.as_str()).collect();
+    let active_signature = get_active_signature(&labels, &context.arguments);
+    let parameters = get_parameters(&signatures[active_signature].label);
+    let active_parameter = get_active_parameter(&parameters, &context.arguments, context.current);
+    Some(SignatureHelp {
+        signatures,
+        active_signature: Some(active_signature as u32),
+        active_parameter: Some(active_parameter as u32),
+    })
+}
+
+fn new_signature(label: String, documentation: Option<String>) -> SignatureInformation {
+    let parameters = get_parameter_ranges(&label)
+        .into_iter()
+        .map(|(start, end)| ParameterInformation {
+            label: ParameterLabel::LabelOffsets([
+                label[..start].encode_utf16().count() as u32,
+                label[..end].encode_utf16().count() as u32,
+            ]),
+            documentation: None,
+        })
+        .collect();
+    SignatureInformation {
+        label,
+        documentation: documentation.map(Documentation::String),
+        parameters: Some(parameters),
+        active_parameter: None,
+    }
+}
+
+/// find the unclosed command before the cursor in the tree, it works with the unfinished
+/// command which is parsed as an error or with a missing paren
+pub fn get_command_context(root: Node, source: &str, location: Position) -> Option<CommandContext> {
+    let (offset, _) = position_to_byte(source, location);
+    let (name, paren) = find_open_paren(root, offset)?;
+    let name = &source[name.byte_range()];
+
+    let mut arguments = Vec::new();
+    let mut current = None;
+    let mut sibling = paren.next_sibling();
+    while let Some(node) = sibling {
+        if node.start_byte() >= offset {
+            break;
+        }
+        let mut course = node.walk();
+        let nodes: Vec<Node> = match node.kind() {
+            "argument" => vec![node],
+            "argument_list" => node
+                .named_children(&mut course)
+                .filter(|argument| argument.kind() == "argument")
+                .collect(),
+            _ => Vec::new(),
+        };
+        for argument in nodes {
+            if argument.start_byte() >= offset {
+                break;
+            }
+            if argument.end_byte() >= offset {
+                // NOTE: the cursor is in the argument, only the typed part is used
+                current = Some(arguments.len());
+                arguments.push(source[argument.start_byte()..offset].to_string());
+                break;
+            }
+            arguments.push(source[argument.byte_range()].to_string());
+        }
+        sibling = node.next_sibling();
+    }
+    Some(CommandContext {
+        name: name.to_string(),
+        current: current.unwrap_or(arguments.len()),
+        arguments,
+    })
+}
+
+/// find the name and the open paren of the innermost command which is not closed before the
+/// offset, the comments and the quoted arguments are single nodes so their parens are ignored
+fn find_open_paren(node: Node, offset: usize) -> Option<(Node, Node)> {
+    let mut open = None;
+    let mut course = node.walk();
+    for child in node.children(&mut course) {
+        if child.start_byte() >= offset {
+            break;
+        }
+        match child.kind() {
+            "(" => {
+                open = child
+                    .prev_sibling()
+                    .filter(|name| {
+                        name.kind() == "identifier"
+                            || node.kind() == format!("{}_command", name.kind())
+                    })
+                    .map(|name| (name, child));
+            }
+            ")" if !child.is_missing() => open = None,
+            _ if child.child_count() != 0 && child.end_byte() >= offset => {
+                if let Some(found) = find_open_paren(child, offset) {
+                    return Some(found);
+                }
+            }
+            _ => {}
+        }
+    }
+    open
+}
+
+/// get the signature forms of the command from its document, they are the indented lines which
+/// start with `name(`. it runs on every keystroke, so the lines are scanned without regex
+pub fn parse_signatures(name: &str, doc: &str) -> Vec<String> {
+    let mut signatures: Vec<String> = Vec::new();
+    let mut line_start = 0;
+    for line in doc.split_inclusive('\n') {
+        let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+        let start = line_start + indent;
+        line_start += line.len();
+        if indent == 0
+            || !doc[start..].starts_with(name)
+            || !doc[start + name.len()..].starts_with('(')
+        {
+            continue;
+        }
+        let open = start + name.len();
+        let mut depth = 0;
+        let mut close = None;
+        for (index, c) in doc[open..].char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(open + index);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(close) = close else {
+            continue;
+        };
+        let signature = doc[start..=close]
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .replace("( ", "(")
+            .replace(" )", ")");
+        if !signatures.contains(&signature) {
+            signatures.push(signature);
+        }
+    }
+    signatures
+}
+
+/// the byte ranges of the parameters in the label
+fn get_parameter_ranges(label: &str) -> Vec<(usize, usize)> {
+    let (Some(open), Some(close)) = (label.find('('), label.rfind(')')) else {
+        return Vec::new();
+    };
+    let mut output = Vec::new();
+    if open < close {
+        split_parameters(label, open + 1, close, &mut output);
+    }
+    output
+}
+
+pub fn get_parameters(label: &str) -> Vec<&str> {
+    get_parameter_ranges(label)
+        .into_iter()
+        .map(|(start, end)| &label[start..end])
+        .collect()
+}
+
+fn split_parameters(label: &str, start: usize, end: usize, output: &mut Vec<(usize, usize)>) {
+    let mut depth = 0;
+    let mut item_start = None;
+    for (index, c) in label[start..end].char_indices() {
+        let index = index + start;
+        match c {
+            '[' | '(' | '<' | '{' => {
+                item_start.get_or_insert(index);
+                depth += 1;
+            }
+            ']' | ')' | '>' | '}' => depth -= 1,
+            c if c.is_whitespace() && depth <= 0 => {
+                if let Some(item_start) = item_start.take() {
+                    push_parameter(label, item_start, index, output);
+                }
+            }
+            _ => {
+                item_start.get_or_insert(index);
+            }
+        }
+    }
+    if let Some(item_start) = item_start {
+        push_parameter(label, item_start, end, output);
+    }
+}
+
+/// NOTE: the optional group which contains other groups is split, so the keyword
+/// sections in it can be highlighted
+fn push_parameter(label: &str, start: usize, end: usize, output: &mut Vec<(usize, usize)>) {
+    if label[start..end].starts_with('[') {
+        let mut depth = 0;
+        for (index, c) in label[start..end].char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let close = start + index;
+                        if label[start + 1..close].contains('[') {
+                            split_parameters(label, start + 1, close, output);
+                            return;
+                        }
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    output.push((start, end));
+}
+
+pub fn is_keyword(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_uppercase())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+pub fn get_keywords(parameter: &str) -> Vec<&str> {
+    parameter
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|word| is_keyword(word))
+        .collect()
+}
+
+/// choose the signature which matches the typed keywords best
+fn get_active_signature(labels: &[&str], arguments: &[String]) -> usize {
+    let mut active = 0;
+    let mut best_score = 0;
+    for (index, label) in labels.iter().enumerate() {
+        let parameters = get_parameters(label);
+        let mut score = 0;
+        if let (Some(first_argument), Some(first_parameter)) =
+            (arguments.first(), parameters.first())
+        {
+            if get_keywords(first_parameter).first() == Some(&first_argument.as_str()) {
+                score += 10;
+            }
+        }
+        let keywords: Vec<&str> = parameters.iter().flat_map(|p| get_keywords(p)).collect();
+        score += arguments
+            .iter()
+            .filter(|argument| is_keyword(argument) && keywords.contains(&argument.as_str()))
+            .count();
+        if score > best_score {
+            best_score = score;
+            active = index;
+        }
+    }
+    active
+}
+
+/// the last typed keyword decides the active section, or use the position of the argument
+fn get_active_parameter(parameters: &[&str], arguments: &[String], current: usize) -> usize {
+    for argument in arguments.iter().take(current + 1).rev() {
+        if !is_keyword(argument) {
+            continue;
+        }
+        let mut found = None;
+        for (index, parameter) in parameters.iter().enumerate() {
+            let keywords = get_keywords(parameter);
+            if keywords.first() == Some(&argument.as_str()) {
+                found = Some(index);
+                break;
+            }
+            if found.is_none() && keywords.contains(&argument.as_str()) {
+                found = Some(index);
+            }
+        }
+        if let Some(index) = found {
+            return index;
+        }
+    }
+    current.min(parameters.len().saturating_sub(1))
+}
+
+fn find_user_signature(
+    name: &str,
+    document: &Document,
+    files: &[PathBuf],
+    buffers: &HashMap<Url, Document>,
+) -> Option<SignatureInformation> {
+    if let Some(label) = find_definition(document.root_node(), &document.text, name) {
+        return Some(new_signature(label, None));
+    }
+    for path in files {
+        let Some(document) = get_or_read(buffers, path) else {
+            continue;
+        };
+        if let Some(label) = find_definition(document.root_node(), &document.text, name) {
+            return Some(new_signature(
+                label,
+                Some(format!("from: {}", path.display())),
+            ));
+        }
+    }
+    None
+}
+
+fn find_definition(root: Node, source: &str, name: &str) -> Option<String> {
+    let newsource: Vec<&str> = source.lines().collect();
+    find_definition_inner(root, &newsource, name)
+}
+
+fn find_definition_inner(input: Node, source: &Vec<&str>, name: &str) -> Option<String> {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        match child.kind() {
+            "function_def" | "macro_def" => {
+                let arguments = child
+                    .child(0)
+                    .and_then(|command| command.child(2))
+                    .map(|argument_list| get_arguments(argument_list, source))
+                    .unwrap_or_default();
+                if arguments
+                    .first()
+                    .is_some_and(|first| first.eq_ignore_ascii_case(name))
+                {
+                    return Some(get_definition_label(child, source, &arguments));
+                }
+            }
+            "normal_command" | "line_comment" | "bracket_comment" => {}
+            _ => {
+                if let Some(label) = find_definition_inner(child, source, name) {
+                    return Some(label);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// get the content of the arguments, the quotes are removed
+pub fn get_arguments(argument_list: Node, source: &Vec<&str>) -> Vec<String> {
+    let mut course = argument_list.walk();
+    argument_list
+        .named_children(&mut course)
+        .filter(|argument| argument.kind() == "argument")
+        .map(|argument| {
+            get_node_content(source, &argument)
+                .trim_matches('"')
+                .to_string()
+        })
+        .collect()
+}
+
+/// build the label from the parameters and the keywords of cmake_parse_arguments
+fn get_definition_label(definition: Node, source: &Vec<&str>, arguments: &[String]) -> String {
+    let mut parameters: Vec<String> = arguments[1..].to_vec();
+    let mut variables: HashMap<String, Vec<String>> = HashMap::new();
+    let mut parse_arguments = None;
+    collect_parse_arguments(definition, source, &mut variables, &mut parse_arguments);
+    if let Some(parse_arguments) = parse_arguments {
+        let skip = if parse_arguments.first().map(|s| s.as_str()) == Some("PARSE_ARGV") {
+            3
+        } else {
+            1
+        };
+        let expand = |argument: Option<&String>| -> Vec<String> {
+            let Some(argument) = argument else {
+                return Vec::new();
+            };
+            let values = match argument
+                .strip_prefix("${")
+                .and_then(|name| name.strip_suffix('}'))
+            {
+                Some(name) => variables.get(name).cloned().unwrap_or_default(),
+                None => vec![argument.clone()],
+            };
+            values
+                .iter()
+                .flat_map(|value| value.split(';'))
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+                .collect()
+        };
+        for option in expand(parse_arguments.get(skip)) {
+            parameters.push(format!("[{option}]"));
+        }
+        for keyword in expand(parse_arguments.get(skip + 1)) {
+            parameters.push(format!("[{keyword} <value>]"));
+        }
+        for keyword in expand(parse_arguments.get(skip + 2)) {
+            parameters.push(format!("[{keyword} <value>...]"));
+        }
+    }
+    format!("{}({})", arguments[0], parameters.join(" "))
+}
+
+fn collect_parse_arguments(
+    input: Node,
+    source: &Vec<&str>,
+    variables: &mut HashMap<String, Vec<String>>,
+    parse_arguments: &mut Option<Vec<String>>,
+) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        if child.kind() != "normal_command" {
+            collect_parse_arguments(child, source, variables, parse_arguments);
+            continue;
+        }
+        let Some(identifier) = child.child(0) else {
+            continue;
+        };
+        let Some(arguments) = child
+            .child(2)
+            .map(|argument_list| get_arguments(argument_list, source))
+        else {
+            continue;
+        };
+        match get_node_content(source, &identifier)
+            .to_lowercase()
+            .as_str()
+        {
+            "set" if !arguments.is_empty() => {
+                variables.insert(arguments[0].clone(), arguments[1..].to_vec());
+            }
+            "cmake_parse_arguments" if parse_arguments.is_none() => {
+                *parse_arguments = Some(arguments);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSTALL_DOC: &str = r#"
+Synopsis
+^^^^^^^^
+
+  install(TARGETS <target>... [...])
+  install(FILES <file>... [...])
+
+Targets
+^^^^^^^
+
+  install(TARGETS <target>... [EXPORT <export-name>]
+          [[ARCHIVE|LIBRARY|RUNTIME|FRAMEWORK]
+           [DESTINATION <dir>]
+           [COMPONENT <component>]
+          ] [...])
+"#;
+
+    fn strings(input: &[&str]) -> Vec<String> {
+        input.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn tst_command_context() {
+        let get_context = |source: &str, location| {
+            let document = Document::new(source.to_string(), 0);
+            get_command_context(document.root_node(), source, location)
+        };
+        let source = "set(A \"b c\")\ninstall(TARGETS foo\n  RUNTIME DESTINATION ";
+        let context = get_context(source, Position::new(2, 22)).unwrap();
+        assert_eq!(
+            context,
+            CommandContext {
+                name: "install".to_string(),
+                arguments: strings(&["TARGETS", "foo", "RUNTIME", "DESTINATION"]),
+                current: 4,
+            }
+        );
+        let context = get_context(source, Position::new(1, 10)).unwrap();
+        assert_eq!(context.arguments, strings(&["TA"]));
+        assert_eq!(context.current, 0);
+        assert!(get_context(source, Position::new(0, 12)).is_none());
+        // the paren in the quote or the comment is ignored
+        let source = "message(\"(\" # )\n  ";
+        let context = get_context(source, Position::new(1, 2)).unwrap();
+        assert_eq!(context.name, "message");
+        // the bracket comment is not an argument, the character is counted in utf-16
+        let source = "set(é #[[ ) ]] b c)\nmessage(STATUS)\n";
+        let context = get_context(source, Position::new(0, 17)).unwrap();
+        assert_eq!(
+            context,
+            CommandContext {
+                name: "set".to_string(),
+                arguments: strings(&["é", "b"]),
+                current: 2,
+            }
+        );
+        // the unfinished command in the middle of the file
+        let source = "if(A)\n  string(REGEX \nendif()\n";
+        let context = get_context(source, Position::new(1, 15)).unwrap();
+        assert_eq!(context.name, "string");
+        assert_eq!(context.arguments, strings(&["REGEX"]));
+    }
+
+    #[test]
+    fn tst_builtin_signature() {
+        let labels = parse_signatures("install", INSTALL_DOC);
+        assert_eq!(labels.len(), 3);
+        assert_eq!(labels[1], "install(FILES <file>... [...])");
+        let parameters = get_parameters(&labels[2]);
+        assert_eq!(
+            parameters,
+            vec![
+                "TARGETS",
+                "<target>...",
+                "[EXPORT <export-name>]",
+                "[ARCHIVE|LIBRARY|RUNTIME|FRAMEWORK]",
+                "[DESTINATION <dir>]",
+                "[COMPONENT <component>]",
+                "[...]",
+            ]
+        );
+        let labels: Vec<&str> = labels.iter().map(|label| label.as_str()).collect();
+        let arguments = strings(&["FILES", "a.txt"]);
+        assert_eq!(get_active_signature(&labels, &arguments), 1);
+        let arguments = strings(&["TARGETS", "foo", "RUNTIME", "DESTINATION"]);
+        assert_eq!(get_active_signature(&labels, &arguments), 2);
+        assert_eq!(get_active_parameter(&parameters, &arguments, 4), 4);
+        assert_eq!(get_active_parameter(&parameters, &arguments, 2), 3);
+        assert_eq!(get_active_parameter(&parameters, &arguments[..2], 1), 0);
+    }
+
+    #[test]
+    fn tst_user_signature() {
+        let source = r#"function(add_foo name target)
+  set(options SHARED STATIC)
+  cmake_parse_arguments(ARG "${options}" "DESTINATION" "SOURCES;DEPENDS" ${ARGN})
+endfunction()
+macro(my_macro)
+endmacro()
+add_foo(bar baz SOURCES "#;
+        let document = Document::new(source.to_string(), 0);
+        let help =
+            get_signature_help(&document, Position::new(6, 24), &[], &HashMap::new()).unwrap();
+        let signature = &help.signatures[0];
+        assert_eq!(
+            signature.label,
+            "add_foo(name target [SHARED] [STATIC] [DESTINATION <value>] [SOURCES <value>...] [DEPENDS <value>...])"
+        );
+        assert_eq!(help.active_parameter, Some(5));
+        assert_eq!(
+            signature.parameters.as_ref().unwrap()[0].label,
+            ParameterLabel::LabelOffsets([8, 12])
+        );
+        assert_eq!(
+            find_definition(document.root_node(), source, "MY_MACRO"),
+            Some("my_macro()".to_string())
+        );
+    }
+}
@@ -100,7 +100,7 @@ fn test_command_execution() {
         assert!(!out.is_empty());
         assert!(err.is_empty());
     } else {
-        assert!(false);
+        unreachable!();
     };
 
     if let Ok(result) = execute_command("ls", &["-a", "-l", "/target_dir_does_not_exist"]) {
@@ -109,12 +109,12 @@ fn test_command_execution() {
         assert!(out.is_empty());
         assert!(!err.is_empty());
     } else {
-        assert!(false);
+        unreachable!();
     };
 
     if let Err(err) = execute_command("command_not_exist", &["-a", "-l"]) {
         println!("{}", err);
     } else {
-        assert!(false);
+        unreachable!();
     };
 }
@@ -17,6 +17,7 @@ const SPECIALCOMMANDS: [&str; 3] = [
 #[cfg(unix)]
 use super::packagepkgconfig::PKG_CONFIG_PACKAGES_WITHKEY;
 use super::CMAKE_PACKAGES_WITHKEY;
+use crate::indexing::get_indexed;
 /// convert Point to Position
 /// treesitter to lsp_types
 #[inline]
@@ -45,7 +46,7 @@ pub fn get_cmake_doc(location: Position, root: Node, source: &str) -> Option<Str
         #[cfg(unix)]
         (Some(message), PositionType::FindPkgConfig) => {
             let message = message.split('_').collect::<Vec<&str>>()[0];
-            let value = PKG_CONFIG_PACKAGES_WITHKEY.get(message);
+            let value = get_indexed(&PKG_CONFIG_PACKAGES_WITHKEY)?.get(message);
             value.map(|context| {
                 format!(
                     "
@@ -61,9 +62,10 @@ Packagepath: {}
             PositionType::FindPackage | PositionType::TargetInclude | PositionType::TargetLink,
         ) => {
             let message = message.split('_').collect::<Vec<&str>>()[0];
-            let mut value = CMAKE_PACKAGES_WITHKEY.get(message);
+            let packages = get_indexed(&CMAKE_PACKAGES_WITHKEY)?;
+            let mut value = packages.get(message);
             if value.is_none() {
-                value = CMAKE_PACKAGES_WITHKEY.get(&message.to_lowercase());
+                value = packages.get(&message.to_lowercase());
             }
             value.map(|context| {
                 format!(
@@ -79,9 +81,10 @@ PackageVersion: {}
             })
         }
         (Some(message), _) => {
-            let mut value = MESSAGE_STORAGE.get(&message);
+            let storage = get_indexed(&MESSAGE_STORAGE)?;
+            let mut value = storage.get(&message);
             if value.is_none() {
-                value = MESSAGE_STORAGE.get(&message.to_lowercase());
+                value = storage.get(&message.to_lowercase());
             }
             value.map(|context| context.to_string())
         }
@@ -0,0 +1,256 @@
+/// provide workspace/symbol, index the functions, macros, options, cache variables and
+/// targets of all the files in the project
+use async_lsp::lsp_types;
+use lsp_types::{Location, SymbolInformation, SymbolKind, Url};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tree_sitter::Node;
+
+use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+use crate::scansubs::get_cached_project_files;
+use crate::utils::get_node_content;
+use crate::utils::treehelper::point_to_position;
+
+const TARGET_COMMANDS: [&str; 3] = ["add_library", "add_executable", "add_custom_target"];
+
+pub type SymbolKV = HashMap<PathBuf, Vec<SymbolInformation>>;
+
+/// NOTE: the symbols of each file, it is updated when the file is saved and removed when the
+/// file is changed outside
+pub static SYMBOL_CACHE: Lazy<Arc<Mutex<SymbolKV>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+pub async fn update_symbol_cache<P: AsRef<Path>>(path: P, context: &str) -> Vec<SymbolInformation> {
+    let mut parse = tree_sitter::Parser::new();
+    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
+    let tree = parse.parse(context, None).unwrap();
+    let Ok(uri) = Url::from_file_path(path.as_ref()) else {
+        return Vec::new();
+    };
+    let mut symbols = Vec::new();
+    get_file_symbols(
+        tree.root_node(),
+        &context.lines().collect(),
+        &uri,
+        &mut symbols,
+    );
+    let mut cache = SYMBOL_CACHE.lock().await;
+    cache.insert(path.as_ref().to_path_buf(), symbols.clone());
+    symbols
+}
+
+/// forget the symbols of the file, they are read again in the next query
+pub async fn remove_symbol_cache<P: AsRef<Path>>(path: P) {
+    SYMBOL_CACHE.lock().await.remove(path.as_ref());
+}
+
+/// search the symbols in the project, the query is matched fuzzily
+pub async fn get_workspace_symbols(query: &str) -> Vec<SymbolInformation> {
+    let mut symbols = Vec::new();
+    for path in get_cached_project_files(None).await {
+        // NOTE: give the chance to abort the request when it is cancelled by $/cancelRequest
+        tokio::task::yield_now().await;
+        let cached = SYMBOL_CACHE.lock().await.get(&path).cloned();
+        let mut file_symbols = match cached {
+            Some(file_symbols) => file_symbols,
+            None => {
+                let Ok(context) = tokio::fs::read_to_string(&path).await else {
+                    continue;
+                };
+                update_symbol_cache(&path, &context).await
+            }
+        };
+        symbols.append(&mut file_symbols);
+    }
+    if query.is_empty() {
+        return symbols;
+    }
+    let mut scored: Vec<(i64, SymbolInformation)> = symbols
+        .into_iter()
+        .filter_map(|symbol| fuzzy_score(query, &symbol.name).map(|score| (score, symbol)))
+        .collect();
+    scored.sort_by(|(score_a, symbol_a), (score_b, symbol_b)| {
+        score_b
+            .cmp(score_a)
+            .then_with(|| symbol_a.name.cmp(&symbol_b.name))
+    });
+    scored.into_iter().map(|(_, symbol)| symbol).collect()
+}
+
+#[allow(deprecated)]
+fn new_symbol(
+    name: String,
+    kind: SymbolKind,
+    node: Node,
+    uri: &Url,
+    command: &str,
+) -> SymbolInformation {
+    SymbolInformation {
+        name,
+        kind,
+        tags: None,
+        deprecated: None,
+        location: Location {
+            uri: uri.clone(),
+            range: lsp_types::Range {
+                start: point_to_position(node.start_position()),
+                end: point_to_position(node.end_position()),
+            },
+        },
+        container_name: Some(command.to_string()),
+    }
+}
+
+fn get_file_symbols(
+    input: Node,
+    source: &Vec<&str>,
+    uri: &Url,
+    symbols: &mut Vec<SymbolInformation>,
+) {
+    let mut course = input.walk();
+    for child in input.children(&mut course) {
+        match child.kind() {
+            "function_def" | "macro_def" => {
+                let command = if child.kind() == "function_def" {
+                    "function"
+                } else {
+                    "macro"
+                };
+                if let Some(name) = child
+                    .child(0)
+                    .and_then(|ids| ids.child(2))
+                    .and_then(|argumentlists| argumentlists.child(0))
+                {
+                    let name = get_node_content(source, &name);
+                    symbols.push(new_symbol(name, SymbolKind::FUNCTION, child, uri, command));
+                }
+                get_file_symbols(child, source, uri, symbols);
+            }
+            "normal_command" => {
+                let Some(ids) = child.child(0) else {
+                    continue;
+                };
+                let command = get_node_content(source, &ids).to_lowercase();
+                let Some(argumentlists) = child.child(2) else {
+                    continue;
+                };
+                let Some(name) = argumentlists.child(0) else {
+                    continue;
+                };
+                if name.start_position().row != name.end_position().row {
+                    continue;
+                }
+                let name = get_node_content(source, &name);
+                if name.contains('$') {
+                    continue;
+                }
+                let kind = match command.as_str() {
+                    "option" => SymbolKind::BOOLEAN,
+                    "set" => {
+                        let mut argcourse = argumentlists.walk();
+                        let is_cache = argumentlists
+                            .children(&mut argcourse)
+                            .any(|argument| get_node_content(source, &argument) == "CACHE");
+                        if !is_cache {
+                            continue;
+                        }
+                        SymbolKind::VARIABLE
+                    }
+                    command if TARGET_COMMANDS.contains(&command) => SymbolKind::OBJECT,
+                    _ => continue,
+                };
+                symbols.push(new_symbol(name, kind, child, uri, &command));
+            }
+            _ => get_file_symbols(child, source, uri, symbols),
+        }
+    }
+}
+
+/// match the query as a subsequence of the name, ignore case
+/// the higher score means better match, None means not matched
+pub fn fuzzy_score(query: &str, name: &str) -> Option<i64> {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut query_index = 0;
+    let mut last_match: Option<usize> = None;
+    for (index, c) in name.iter().enumerate() {
+        if query_index == query.len() {
+            break;
+        }
+        if *c != query[query_index] {
+            continue;
+        }
+        score += 1;
+        if index == 0 {
+            score += 10;
+        } else if matches!(name[index - 1], '_' | '-' | ':' | '.') {
+            score += 3;
+        }
+        if last_match.is_some_and(|last| last + 1 == index) {
+            score += 5;
+        }
+        last_match = Some(index);
+        query_index += 1;
+    }
+    if query_index != query.len() {
+        return None;
+    }
+    // shorter names are better
+    Some(score * 100 - name.len() as i64)
+}
+
+#[test]
+fn tst_fuzzy_score() {
+    assert!(fuzzy_score("afp", "add_foo_plugin").is_some());
+    assert!(fuzzy_score("add_foo_plugin", "add_foo_plugin").is_some());
+    assert!(fuzzy_score("pfa", "add_foo_plugin").is_none());
+    assert!(fuzzy_score("ADD", "add_foo_plugin") > fuzzy_score("afp", "add_foo_plugin"));
+    assert!(fuzzy_score("foo", "foo") > fuzzy_score("foo", "foo_bar"));
+}
+
+#[test]
+fn tst_file_symbols() {
+    let source = r#"function(add_foo_plugin name)
+  add_library(${name} SHARED foo.cpp)
+endfunction()
+macro(my_macro)
+endmacro()
+option(BUILD_TESTING "" ON)
+set(FOO_PATH "" CACHE PATH "foo")
+set(NOT_CACHE "")
+if(BUILD_TESTING)
+  add_executable(foo_test test.cpp)
+  add_custom_target(docs)
+endif()
+"#;
+    let mut parse = tree_sitter::Parser::new();
+    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
+    let tree = parse.parse(source, None).unwrap();
+    let uri = Url::parse("file:///tmp/CMakeLists.txt").unwrap();
+    let mut symbols = Vec::new();
+    get_file_symbols(
+        tree.root_node(),
+        &source.lines().collect(),
+        &uri,
+        &mut symbols,
+    );
+    let names: Vec<(&str, SymbolKind)> = symbols
+        .iter()
+        .map(|symbol| (symbol.name.as_str(), symbol.kind))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("add_foo_plugin", SymbolKind::FUNCTION),
+            ("my_macro", SymbolKind::FUNCTION),
+            ("BUILD_TESTING", SymbolKind::BOOLEAN),
+            ("FOO_PATH", SymbolKind::VARIABLE),
+            ("foo_test", SymbolKind::OBJECT),
+            ("docs", SymbolKind::OBJECT),
+        ]
+    );
+}